@@ -1,116 +1,197 @@
 use {
-    ash::{vk, Device},
+    ash::{ext::descriptor_buffer, prelude::VkResult, vk, Device},
     gpu_descriptor_types::{
-        CreatePoolError, DescriptorDevice, DescriptorPoolCreateFlags, DescriptorTotalCount,
-        DeviceAllocationError,
+        CreateBufferError, CreateLayoutError, CreatePoolError, DescriptorBindingFlags,
+        DescriptorBufferDevice, DescriptorBufferUsageFlags, DescriptorDevice,
+        DescriptorDeviceCapabilities, DescriptorDeviceV2, DescriptorKind,
+        DescriptorPoolCreateFlags, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateFlags,
+        DescriptorSetLayoutDevice, DescriptorTotalCount, DescriptorType, DeviceAllocationError,
+        DeviceLimits, MAX_VENDOR_DESCRIPTOR_KINDS,
     },
+    std::{borrow::Borrow, sync::Arc},
 };
 
-#[repr(transparent)]
-pub struct AshDescriptorDevice {
-    device: Device,
+/// Extension points of `AshDescriptorDevice`.
+///
+/// Allows chaining extension structures, e.g. vendor-specific ones,
+/// to infos prepared by `AshDescriptorDevice` before the device is called.
+pub trait AshDescriptorExtensions {
+    /// Creates descriptor pool from `info`.
+    ///
+    /// Implementation may push extension structures to `info` before calling `create`.
+    /// Default implementation calls `create` with `info` unchanged.
+    fn create_descriptor_pool(
+        &self,
+        info: vk::DescriptorPoolCreateInfo<'_>,
+        create: &mut dyn FnMut(&vk::DescriptorPoolCreateInfo<'_>) -> VkResult<vk::DescriptorPool>,
+    ) -> VkResult<vk::DescriptorPool> {
+        create(&info)
+    }
+
+    /// Allocates descriptor sets from `info`.
+    ///
+    /// Implementation may push extension structures to `info` before calling `allocate`.
+    /// Default implementation calls `allocate` with `info` unchanged.
+    fn allocate_descriptor_sets(
+        &self,
+        info: vk::DescriptorSetAllocateInfo<'_>,
+        allocate: &mut dyn FnMut(
+            &vk::DescriptorSetAllocateInfo<'_>,
+        ) -> VkResult<Vec<vk::DescriptorSet>>,
+    ) -> VkResult<Vec<vk::DescriptorSet>> {
+        allocate(&info)
+    }
+
+    /// Returns host memory allocation callbacks
+    /// used for every descriptor pool, layout and buffer created and destroyed by the device.
+    /// Default implementation returns `None`.
+    fn allocation_callbacks(&self) -> Option<&vk::AllocationCallbacks<'_>> {
+        None
+    }
 }
 
-impl AshDescriptorDevice {
-    pub fn wrap(device: &Device) -> &Self {
-        unsafe {
-            // Safe because `Self` is `repr(transparent)`
-            // with only non-zero-sized field being `D`.
-            &*(device as *const Device as *const Self)
-        }
+/// Extensions that add nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoExtensions;
+
+impl AshDescriptorExtensions for NoExtensions {}
+
+/// Uses the callbacks for host memory allocations and adds nothing else.
+///
+/// `vk::AllocationCallbacks` is neither `Send` nor `Sync`, and so is the device using it.
+/// Use `SharedAllocationCallbacks` to share the device between threads.
+impl AshDescriptorExtensions for vk::AllocationCallbacks<'_> {
+    fn allocation_callbacks(&self) -> Option<&vk::AllocationCallbacks<'_>> {
+        Some(self)
     }
 }
 
-impl DescriptorDevice<vk::DescriptorSetLayout, vk::DescriptorPool, vk::DescriptorSet>
-    for AshDescriptorDevice
-{
-    unsafe fn create_descriptor_pool(
-        &self,
-        descriptor_count: &DescriptorTotalCount,
-        max_sets: u32,
-        flags: DescriptorPoolCreateFlags,
-    ) -> Result<vk::DescriptorPool, CreatePoolError> {
-        let mut array = [vk::DescriptorPoolSize::default(); 13];
-        let mut len = 0;
+/// Allocation callbacks that are safe to use from any thread.
+///
+/// Uses the callbacks for host memory allocations and adds nothing else.
+#[derive(Clone, Copy)]
+pub struct SharedAllocationCallbacks<'a> {
+    callbacks: vk::AllocationCallbacks<'a>,
+}
 
-        if descriptor_count.sampler != 0 {
-            array[len].ty = vk::DescriptorType::SAMPLER;
-            array[len].descriptor_count = descriptor_count.sampler;
-            len += 1;
-        }
+// Safe because `SharedAllocationCallbacks::new` requires callbacks
+// and their user data to be usable from any thread.
+unsafe impl Send for SharedAllocationCallbacks<'_> {}
+unsafe impl Sync for SharedAllocationCallbacks<'_> {}
 
-        if descriptor_count.combined_image_sampler != 0 {
-            array[len].ty = vk::DescriptorType::COMBINED_IMAGE_SAMPLER;
-            array[len].descriptor_count = descriptor_count.combined_image_sampler;
-            len += 1;
-        }
+impl<'a> SharedAllocationCallbacks<'a> {
+    /// Wraps allocation callbacks.
+    ///
+    /// # Safety
+    ///
+    /// Callbacks must be safe to call from any thread, including concurrently,
+    /// with `p_user_data` of `callbacks`.
+    pub unsafe fn new(callbacks: vk::AllocationCallbacks<'a>) -> Self {
+        SharedAllocationCallbacks { callbacks }
+    }
 
-        if descriptor_count.sampled_image != 0 {
-            array[len].ty = vk::DescriptorType::SAMPLED_IMAGE;
-            array[len].descriptor_count = descriptor_count.sampled_image;
-            len += 1;
-        }
+    /// Returns wrapped callbacks.
+    pub fn callbacks(&self) -> &vk::AllocationCallbacks<'a> {
+        &self.callbacks
+    }
+}
 
-        if descriptor_count.storage_image != 0 {
-            array[len].ty = vk::DescriptorType::STORAGE_IMAGE;
-            array[len].descriptor_count = descriptor_count.storage_image;
-            len += 1;
-        }
+impl AshDescriptorExtensions for SharedAllocationCallbacks<'_> {
+    fn allocation_callbacks(&self) -> Option<&vk::AllocationCallbacks<'_>> {
+        Some(&self.callbacks)
+    }
+}
 
-        if descriptor_count.uniform_texel_buffer != 0 {
-            array[len].ty = vk::DescriptorType::UNIFORM_TEXEL_BUFFER;
-            array[len].descriptor_count = descriptor_count.uniform_texel_buffer;
-            len += 1;
-        }
+/// Descriptor device implementation over `Device`.
+///
+/// Device can be borrowed with `wrap`, owned or shared via `Arc`.
+// `repr(C)` keeps `device` at offset zero, `wrap` relies on it.
+#[repr(C)]
+pub struct AshDescriptorDevice<E = NoExtensions, D = Device> {
+    device: D,
+    extensions: E,
+}
 
-        if descriptor_count.storage_texel_buffer != 0 {
-            array[len].ty = vk::DescriptorType::STORAGE_TEXEL_BUFFER;
-            array[len].descriptor_count = descriptor_count.storage_texel_buffer;
-            len += 1;
-        }
+// `wrap` casts borrowed device to `Self`,
+// which is only valid while `NoExtensions` adds neither size nor alignment.
+const _: () = assert!(
+    core::mem::size_of::<AshDescriptorDevice>() == core::mem::size_of::<Device>()
+        && core::mem::align_of::<AshDescriptorDevice>() == core::mem::align_of::<Device>()
+);
 
-        if descriptor_count.uniform_buffer != 0 {
-            array[len].ty = vk::DescriptorType::UNIFORM_BUFFER;
-            array[len].descriptor_count = descriptor_count.uniform_buffer;
-            len += 1;
+impl AshDescriptorDevice {
+    pub fn wrap(device: &Device) -> &Self {
+        unsafe {
+            // Safe because `Self` is `repr(C)`
+            // with `Device` as first field followed by zero-sized `NoExtensions`,
+            // as asserted above.
+            &*(device as *const Device as *const Self)
         }
+    }
+}
 
-        if descriptor_count.storage_buffer != 0 {
-            array[len].ty = vk::DescriptorType::STORAGE_BUFFER;
-            array[len].descriptor_count = descriptor_count.storage_buffer;
-            len += 1;
-        }
+impl From<Device> for AshDescriptorDevice {
+    fn from(device: Device) -> Self {
+        AshDescriptorDevice::with_extensions(device, NoExtensions)
+    }
+}
 
-        if descriptor_count.uniform_buffer_dynamic != 0 {
-            array[len].ty = vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC;
-            array[len].descriptor_count = descriptor_count.uniform_buffer_dynamic;
-            len += 1;
-        }
+impl From<Arc<Device>> for AshDescriptorDevice<NoExtensions, Arc<Device>> {
+    fn from(device: Arc<Device>) -> Self {
+        AshDescriptorDevice::with_extensions(device, NoExtensions)
+    }
+}
 
-        if descriptor_count.storage_buffer_dynamic != 0 {
-            array[len].ty = vk::DescriptorType::STORAGE_BUFFER_DYNAMIC;
-            array[len].descriptor_count = descriptor_count.storage_buffer_dynamic;
-            len += 1;
-        }
+impl<E, D> AshDescriptorDevice<E, D>
+where
+    E: AshDescriptorExtensions,
+    D: Borrow<Device>,
+{
+    /// Wraps device with extensions that are called
+    /// for every descriptor pool creation and descriptor sets allocation.
+    ///
+    /// Pass `vk::AllocationCallbacks` or `SharedAllocationCallbacks`
+    /// to only use custom host memory allocator.
+    pub fn with_extensions(device: D, extensions: E) -> Self {
+        AshDescriptorDevice { device, extensions }
+    }
 
-        if descriptor_count.input_attachment != 0 {
-            array[len].ty = vk::DescriptorType::INPUT_ATTACHMENT;
-            array[len].descriptor_count = descriptor_count.input_attachment;
-            len += 1;
-        }
+    /// Returns wrapped device.
+    pub fn device(&self) -> &Device {
+        self.device.borrow()
+    }
 
-        if descriptor_count.acceleration_structure != 0 {
-            array[len].ty = vk::DescriptorType::ACCELERATION_STRUCTURE_KHR;
-            array[len].descriptor_count = descriptor_count.acceleration_structure;
-            len += 1;
-        }
+    /// Unwraps device.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
 
-        if descriptor_count.inline_uniform_block_bytes != 0 {
-            panic!("Inline uniform blocks are not supported");
-        }
+    /// Returns extensions of the device.
+    pub fn extensions(&self) -> &E {
+        &self.extensions
+    }
+}
 
-        if descriptor_count.inline_uniform_block_bindings != 0 {
-            panic!("Inline uniform blocks are not supported");
+impl<E, D> DescriptorDevice<vk::DescriptorSetLayout, vk::DescriptorPool, vk::DescriptorSet>
+    for AshDescriptorDevice<E, D>
+where
+    E: AshDescriptorExtensions,
+    D: Borrow<Device>,
+{
+    unsafe fn create_descriptor_pool(
+        &self,
+        descriptor_count: &DescriptorTotalCount,
+        max_sets: u32,
+        flags: DescriptorPoolCreateFlags,
+    ) -> Result<vk::DescriptorPool, CreatePoolError> {
+        let mut array = [vk::DescriptorPoolSize::default();
+            DescriptorKind::KNOWN.len() + MAX_VENDOR_DESCRIPTOR_KINDS];
+        let mut len = 0;
+
+        for (kind, count) in descriptor_count.iter() {
+            array[len].ty = descriptor_kind(kind);
+            array[len].descriptor_count = count;
+            len += 1;
         }
 
         let mut ash_flags = vk::DescriptorPoolCreateFlags::empty();
@@ -123,12 +204,19 @@ impl DescriptorDevice<vk::DescriptorSetLayout, vk::DescriptorPool, vk::Descripto
             ash_flags |= vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND;
         }
 
-        let result = self.device.create_descriptor_pool(
-            &vk::DescriptorPoolCreateInfo::default()
+        if flags.contains(DescriptorPoolCreateFlags::HOST_ONLY) {
+            ash_flags |= vk::DescriptorPoolCreateFlags::HOST_ONLY_EXT;
+        }
+
+        let result = self.extensions.create_descriptor_pool(
+            vk::DescriptorPoolCreateInfo::default()
                 .max_sets(max_sets)
                 .pool_sizes(&array[..len])
                 .flags(ash_flags),
-            None,
+            &mut |info| {
+                self.device()
+                    .create_descriptor_pool(info, self.extensions.allocation_callbacks())
+            },
         );
 
         match result {
@@ -141,7 +229,8 @@ impl DescriptorDevice<vk::DescriptorSetLayout, vk::DescriptorPool, vk::Descripto
     }
 
     unsafe fn destroy_descriptor_pool(&self, pool: vk::DescriptorPool) {
-        self.device.destroy_descriptor_pool(pool, None)
+        self.device()
+            .destroy_descriptor_pool(pool, self.extensions.allocation_callbacks())
     }
 
     unsafe fn alloc_descriptor_sets<'a>(
@@ -152,10 +241,11 @@ impl DescriptorDevice<vk::DescriptorSetLayout, vk::DescriptorPool, vk::Descripto
     ) -> Result<(), DeviceAllocationError> {
         let set_layouts: smallvec::SmallVec<[_; 16]> = layouts.copied().collect();
 
-        match self.device.allocate_descriptor_sets(
-            &vk::DescriptorSetAllocateInfo::default()
+        match self.extensions.allocate_descriptor_sets(
+            vk::DescriptorSetAllocateInfo::default()
                 .set_layouts(&set_layouts)
                 .descriptor_pool(*pool),
+            &mut |info| self.device().allocate_descriptor_sets(info),
         ) {
             Ok(allocated) => {
                 sets.extend(allocated);
@@ -179,9 +269,488 @@ impl DescriptorDevice<vk::DescriptorSetLayout, vk::DescriptorPool, vk::Descripto
         sets: impl Iterator<Item = vk::DescriptorSet>,
     ) {
         let sets: smallvec::SmallVec<[_; 16]> = sets.collect();
-        match self.device.free_descriptor_sets(*pool, &sets) {
+        match self.device().free_descriptor_sets(*pool, &sets) {
             Ok(()) => {}
             Err(err) => panic!("Unexpected return code '{}'", err),
         }
     }
 }
+
+impl<E, D> DescriptorDeviceV2 for AshDescriptorDevice<E, D>
+where
+    E: AshDescriptorExtensions,
+    D: Borrow<Device>,
+{
+    type Layout = vk::DescriptorSetLayout;
+    type Pool = vk::DescriptorPool;
+    type Set = vk::DescriptorSet;
+
+    fn capabilities(&self) -> DescriptorDeviceCapabilities {
+        DescriptorDeviceCapabilities::RESET_POOL
+    }
+
+    fn create_descriptor_pool(
+        &self,
+        descriptor_count: &DescriptorTotalCount,
+        max_sets: u32,
+        flags: DescriptorPoolCreateFlags,
+    ) -> Result<vk::DescriptorPool, CreatePoolError> {
+        // Safe, pool creation has no requirements beyond valid device.
+        unsafe { DescriptorDevice::create_descriptor_pool(self, descriptor_count, max_sets, flags) }
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, pool: vk::DescriptorPool) {
+        DescriptorDevice::destroy_descriptor_pool(self, pool)
+    }
+
+    unsafe fn reset_descriptor_pool(&self, pool: &mut vk::DescriptorPool) {
+        match self
+            .device()
+            .reset_descriptor_pool(*pool, vk::DescriptorPoolResetFlags::empty())
+        {
+            Ok(()) => {}
+            Err(err) => panic!("Unexpected return code '{}'", err),
+        }
+    }
+
+    unsafe fn alloc_descriptor_sets<'a>(
+        &self,
+        pool: &mut vk::DescriptorPool,
+        layouts: impl ExactSizeIterator<Item = &'a vk::DescriptorSetLayout>,
+        sets: &mut impl Extend<vk::DescriptorSet>,
+    ) -> Result<(), DeviceAllocationError> {
+        DescriptorDevice::alloc_descriptor_sets(self, pool, layouts, sets)
+    }
+
+    unsafe fn dealloc_descriptor_sets(
+        &self,
+        pool: &mut vk::DescriptorPool,
+        sets: impl Iterator<Item = vk::DescriptorSet>,
+    ) {
+        DescriptorDevice::dealloc_descriptor_sets(self, pool, sets)
+    }
+}
+
+impl<E, D> DescriptorSetLayoutDevice<vk::DescriptorSetLayout> for AshDescriptorDevice<E, D>
+where
+    E: AshDescriptorExtensions,
+    D: Borrow<Device>,
+{
+    unsafe fn create_descriptor_set_layout(
+        &self,
+        bindings: &[DescriptorSetLayoutBinding],
+        flags: DescriptorSetLayoutCreateFlags,
+    ) -> Result<vk::DescriptorSetLayout, CreateLayoutError> {
+        let ash_bindings = bindings
+            .iter()
+            .map(|binding| {
+                let ty = descriptor_type(binding.ty)
+                    .ok_or(CreateLayoutError::UnsupportedDescriptorType(binding.ty))?;
+
+                Ok(vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding.binding)
+                    .descriptor_type(ty)
+                    .descriptor_count(binding.count)
+                    .stage_flags(vk::ShaderStageFlags::from_raw(binding.stages.bits())))
+            })
+            .collect::<Result<smallvec::SmallVec<[_; 16]>, CreateLayoutError>>()?;
+
+        let binding_flags: smallvec::SmallVec<[_; 16]> = bindings
+            .iter()
+            .map(|binding| binding_flags(binding.flags))
+            .collect();
+
+        let mut ash_flags = vk::DescriptorSetLayoutCreateFlags::empty();
+
+        if flags.contains(DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND) {
+            ash_flags |= vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL;
+        }
+
+        if flags.contains(DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL) {
+            ash_flags |= vk::DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL_EXT;
+        }
+
+        if flags.contains(DescriptorSetLayoutCreateFlags::DESCRIPTOR_BUFFER) {
+            ash_flags |= vk::DescriptorSetLayoutCreateFlags::DESCRIPTOR_BUFFER_EXT;
+        }
+
+        let mut binding_flags_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::default().binding_flags(&binding_flags);
+
+        let mut create_info = vk::DescriptorSetLayoutCreateInfo::default()
+            .bindings(&ash_bindings)
+            .flags(ash_flags);
+
+        if bindings.iter().any(|binding| !binding.flags.is_empty()) {
+            create_info = create_info.push_next(&mut binding_flags_info);
+        }
+
+        let result = self
+            .device()
+            .create_descriptor_set_layout(&create_info, self.extensions.allocation_callbacks());
+
+        match result {
+            Ok(layout) => Ok(layout),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => {
+                Err(CreateLayoutError::OutOfDeviceMemory)
+            }
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(CreateLayoutError::OutOfHostMemory),
+            Err(err) => panic!("Unexpected return code '{}'", err),
+        }
+    }
+
+    unsafe fn destroy_descriptor_set_layout(&self, layout: vk::DescriptorSetLayout) {
+        self.device()
+            .destroy_descriptor_set_layout(layout, self.extensions.allocation_callbacks())
+    }
+}
+
+/// Fills `DeviceLimits` from physical device limits
+/// and properties of descriptor indexing, acceleration structure
+/// and inline uniform block features.
+///
+/// Pass `None` for features that are not enabled,
+/// their limits are left unbounded, as are pool limits that Vulkan does not report.
+pub fn device_limits(
+    limits: &vk::PhysicalDeviceLimits,
+    descriptor_indexing: Option<&vk::PhysicalDeviceDescriptorIndexingProperties<'_>>,
+    acceleration_structure: Option<&vk::PhysicalDeviceAccelerationStructurePropertiesKHR<'_>>,
+    inline_uniform_block: Option<&vk::PhysicalDeviceInlineUniformBlockProperties<'_>>,
+) -> DeviceLimits {
+    DeviceLimits {
+        max_descriptor_set_samplers: limits.max_descriptor_set_samplers,
+        max_descriptor_set_uniform_buffers: limits.max_descriptor_set_uniform_buffers,
+        max_descriptor_set_uniform_buffers_dynamic: limits
+            .max_descriptor_set_uniform_buffers_dynamic,
+        max_descriptor_set_storage_buffers: limits.max_descriptor_set_storage_buffers,
+        max_descriptor_set_storage_buffers_dynamic: limits
+            .max_descriptor_set_storage_buffers_dynamic,
+        max_descriptor_set_sampled_images: limits.max_descriptor_set_sampled_images,
+        max_descriptor_set_storage_images: limits.max_descriptor_set_storage_images,
+        max_descriptor_set_input_attachments: limits.max_descriptor_set_input_attachments,
+        max_descriptor_set_acceleration_structures: acceleration_structure
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_acceleration_structures
+            }),
+        max_descriptor_set_inline_uniform_blocks: inline_uniform_block
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_inline_uniform_blocks
+            }),
+        max_descriptor_set_update_after_bind_samplers: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_samplers
+            }),
+        max_descriptor_set_update_after_bind_uniform_buffers: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_uniform_buffers
+            }),
+        max_descriptor_set_update_after_bind_uniform_buffers_dynamic: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_uniform_buffers_dynamic
+            }),
+        max_descriptor_set_update_after_bind_storage_buffers: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_storage_buffers
+            }),
+        max_descriptor_set_update_after_bind_storage_buffers_dynamic: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_storage_buffers_dynamic
+            }),
+        max_descriptor_set_update_after_bind_sampled_images: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_sampled_images
+            }),
+        max_descriptor_set_update_after_bind_storage_images: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_storage_images
+            }),
+        max_descriptor_set_update_after_bind_input_attachments: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_input_attachments
+            }),
+        max_descriptor_set_update_after_bind_acceleration_structures: acceleration_structure
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_acceleration_structures
+            }),
+        max_descriptor_set_update_after_bind_inline_uniform_blocks: inline_uniform_block
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_inline_uniform_blocks
+            }),
+        ..DeviceLimits::UNBOUNDED
+    }
+}
+
+/// Returns `None` for descriptor types that are not supported in layouts.
+fn descriptor_type(ty: DescriptorType) -> Option<vk::DescriptorType> {
+    match ty {
+        DescriptorType::Sampler => Some(vk::DescriptorType::SAMPLER),
+        DescriptorType::CombinedImageSampler => Some(vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+        DescriptorType::SampledImage => Some(vk::DescriptorType::SAMPLED_IMAGE),
+        DescriptorType::StorageImage => Some(vk::DescriptorType::STORAGE_IMAGE),
+        DescriptorType::UniformTexelBuffer => Some(vk::DescriptorType::UNIFORM_TEXEL_BUFFER),
+        DescriptorType::StorageTexelBuffer => Some(vk::DescriptorType::STORAGE_TEXEL_BUFFER),
+        DescriptorType::UniformBuffer => Some(vk::DescriptorType::UNIFORM_BUFFER),
+        DescriptorType::StorageBuffer => Some(vk::DescriptorType::STORAGE_BUFFER),
+        DescriptorType::UniformBufferDynamic => Some(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC),
+        DescriptorType::StorageBufferDynamic => Some(vk::DescriptorType::STORAGE_BUFFER_DYNAMIC),
+        DescriptorType::InputAttachment => Some(vk::DescriptorType::INPUT_ATTACHMENT),
+        DescriptorType::AccelerationStructure => {
+            Some(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+        }
+        DescriptorType::InlineUniformBlock => None,
+    }
+}
+
+fn descriptor_kind(kind: DescriptorKind) -> vk::DescriptorType {
+    match kind {
+        DescriptorKind::Sampler => vk::DescriptorType::SAMPLER,
+        DescriptorKind::CombinedImageSampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+        DescriptorKind::SampledImage => vk::DescriptorType::SAMPLED_IMAGE,
+        DescriptorKind::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+        DescriptorKind::UniformTexelBuffer => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+        DescriptorKind::StorageTexelBuffer => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+        DescriptorKind::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
+        DescriptorKind::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
+        DescriptorKind::UniformBufferDynamic => vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+        DescriptorKind::StorageBufferDynamic => vk::DescriptorType::STORAGE_BUFFER_DYNAMIC,
+        DescriptorKind::InputAttachment => vk::DescriptorType::INPUT_ATTACHMENT,
+        DescriptorKind::AccelerationStructure => vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+        DescriptorKind::InlineUniformBlockBytes | DescriptorKind::InlineUniformBlockBindings => {
+            panic!("Inline uniform blocks are not supported")
+        }
+        DescriptorKind::Vendor(raw) => vk::DescriptorType::from_raw(raw),
+    }
+}
+
+fn binding_flags(flags: DescriptorBindingFlags) -> vk::DescriptorBindingFlags {
+    let mut ash_flags = vk::DescriptorBindingFlags::empty();
+
+    if flags.contains(DescriptorBindingFlags::UPDATE_AFTER_BIND) {
+        ash_flags |= vk::DescriptorBindingFlags::UPDATE_AFTER_BIND;
+    }
+
+    if flags.contains(DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING) {
+        ash_flags |= vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING;
+    }
+
+    if flags.contains(DescriptorBindingFlags::PARTIALLY_BOUND) {
+        ash_flags |= vk::DescriptorBindingFlags::PARTIALLY_BOUND;
+    }
+
+    if flags.contains(DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT) {
+        ash_flags |= vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT;
+    }
+
+    ash_flags
+}
+
+/// Descriptor buffer created by `AshDescriptorBufferDevice`.
+///
+/// Buffer memory is persistently mapped.
+#[derive(Clone, Copy, Debug)]
+pub struct AshDescriptorBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    address: vk::DeviceAddress,
+    mapped: *mut u8,
+}
+
+// Safe because `mapped` pointer is never dereferenced by `AshDescriptorBuffer` itself.
+unsafe impl Send for AshDescriptorBuffer {}
+unsafe impl Sync for AshDescriptorBuffer {}
+
+impl AshDescriptorBuffer {
+    /// Returns raw buffer handle.
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// Returns device address of the buffer.
+    pub fn address(&self) -> vk::DeviceAddress {
+        self.address
+    }
+
+    /// Returns pointer to mapped buffer memory.
+    pub fn mapped(&self) -> *mut u8 {
+        self.mapped
+    }
+}
+
+/// Device that creates descriptor buffers using `VK_EXT_descriptor_buffer`.
+///
+/// Only `allocation_callbacks` of the extensions are used.
+pub struct AshDescriptorBufferDevice<E = NoExtensions> {
+    device: Device,
+    descriptor_buffer: descriptor_buffer::Device,
+    offset_alignment: u64,
+    memory_type_index: u32,
+    extensions: E,
+}
+
+impl AshDescriptorBufferDevice {
+    /// Wraps device with loaded `VK_EXT_descriptor_buffer` functions.
+    ///
+    /// `memory_type_index` must refer to host-visible and host-coherent memory type
+    /// that is suitable for descriptor buffers.
+    /// `bufferDeviceAddress` feature must be enabled.
+    pub fn new(
+        device: Device,
+        descriptor_buffer: descriptor_buffer::Device,
+        properties: &vk::PhysicalDeviceDescriptorBufferPropertiesEXT,
+        memory_type_index: u32,
+    ) -> Self {
+        AshDescriptorBufferDevice::with_extensions(
+            device,
+            descriptor_buffer,
+            properties,
+            memory_type_index,
+            NoExtensions,
+        )
+    }
+}
+
+impl<E: AshDescriptorExtensions> AshDescriptorBufferDevice<E> {
+    /// Same as `new`, but buffers are created, allocated and destroyed
+    /// with allocation callbacks of `extensions`.
+    pub fn with_extensions(
+        device: Device,
+        descriptor_buffer: descriptor_buffer::Device,
+        properties: &vk::PhysicalDeviceDescriptorBufferPropertiesEXT,
+        memory_type_index: u32,
+        extensions: E,
+    ) -> Self {
+        AshDescriptorBufferDevice {
+            device,
+            descriptor_buffer,
+            offset_alignment: properties.descriptor_buffer_offset_alignment,
+            memory_type_index,
+            extensions,
+        }
+    }
+
+    /// Returns extensions of the device.
+    pub fn extensions(&self) -> &E {
+        &self.extensions
+    }
+}
+
+impl<E> DescriptorBufferDevice<vk::DescriptorSetLayout, AshDescriptorBuffer>
+    for AshDescriptorBufferDevice<E>
+where
+    E: AshDescriptorExtensions,
+{
+    fn descriptor_buffer_offset_alignment(&self) -> u64 {
+        self.offset_alignment
+    }
+
+    unsafe fn get_descriptor_set_layout_size(&self, layout: &vk::DescriptorSetLayout) -> u64 {
+        self.descriptor_buffer
+            .get_descriptor_set_layout_size(*layout)
+    }
+
+    unsafe fn get_descriptor_set_layout_binding_offset(
+        &self,
+        layout: &vk::DescriptorSetLayout,
+        binding: u32,
+    ) -> u64 {
+        self.descriptor_buffer
+            .get_descriptor_set_layout_binding_offset(*layout, binding)
+    }
+
+    unsafe fn create_descriptor_buffer(
+        &self,
+        size: u64,
+        usage: DescriptorBufferUsageFlags,
+    ) -> Result<AshDescriptorBuffer, CreateBufferError> {
+        let mut ash_usage = vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+
+        if usage.contains(DescriptorBufferUsageFlags::SAMPLER) {
+            ash_usage |= vk::BufferUsageFlags::SAMPLER_DESCRIPTOR_BUFFER_EXT;
+        }
+
+        if usage.contains(DescriptorBufferUsageFlags::RESOURCE) {
+            ash_usage |= vk::BufferUsageFlags::RESOURCE_DESCRIPTOR_BUFFER_EXT;
+        }
+
+        let callbacks = self.extensions.allocation_callbacks();
+
+        let buffer = self
+            .device
+            .create_buffer(
+                &vk::BufferCreateInfo::default()
+                    .size(size)
+                    .usage(ash_usage)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                callbacks,
+            )
+            .map_err(buffer_error)?;
+
+        let requirements = self.device.get_buffer_memory_requirements(buffer);
+
+        let compatible = 1u32
+            .checked_shl(self.memory_type_index)
+            .is_some_and(|bit| requirements.memory_type_bits & bit != 0);
+
+        if !compatible {
+            self.device.destroy_buffer(buffer, callbacks);
+            return Err(CreateBufferError::IncompatibleMemoryType);
+        }
+
+        let mut flags_info =
+            vk::MemoryAllocateFlagsInfo::default().flags(vk::MemoryAllocateFlags::DEVICE_ADDRESS);
+
+        let memory = match self.device.allocate_memory(
+            &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(self.memory_type_index)
+                .push_next(&mut flags_info),
+            callbacks,
+        ) {
+            Ok(memory) => memory,
+            Err(err) => {
+                self.device.destroy_buffer(buffer, callbacks);
+                return Err(buffer_error(err));
+            }
+        };
+
+        let mapped = match self
+            .device
+            .bind_buffer_memory(buffer, memory, 0)
+            .and_then(|()| {
+                self.device
+                    .map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+            }) {
+            Ok(mapped) => mapped,
+            Err(err) => {
+                self.device.destroy_buffer(buffer, callbacks);
+                self.device.free_memory(memory, callbacks);
+                return Err(buffer_error(err));
+            }
+        };
+
+        let address = self
+            .device
+            .get_buffer_device_address(&vk::BufferDeviceAddressInfo::default().buffer(buffer));
+
+        Ok(AshDescriptorBuffer {
+            buffer,
+            memory,
+            address,
+            mapped: mapped.cast(),
+        })
+    }
+
+    unsafe fn destroy_descriptor_buffer(&self, buffer: AshDescriptorBuffer) {
+        let callbacks = self.extensions.allocation_callbacks();
+        self.device.destroy_buffer(buffer.buffer, callbacks);
+        self.device.free_memory(buffer.memory, callbacks);
+    }
+}
+
+fn buffer_error(err: vk::Result) -> CreateBufferError {
+    match err {
+        vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => CreateBufferError::OutOfDeviceMemory,
+        vk::Result::ERROR_OUT_OF_HOST_MEMORY => CreateBufferError::OutOfHostMemory,
+        err => panic!("Unexpected return code '{}'", err),
+    }
+}
@@ -0,0 +1,547 @@
+//! Tests of `AshDescriptorDevice` over a fake device
+//! that keeps track of objects and of extension structures it receives.
+
+use {
+    ash::{
+        ext::descriptor_buffer,
+        prelude::VkResult,
+        vk::{self, Handle as _},
+        Device, Instance,
+    },
+    gpu_descriptor::DescriptorAllocator,
+    gpu_descriptor_ash::{
+        AshDescriptorBufferDevice, AshDescriptorDevice, AshDescriptorExtensions,
+        SharedAllocationCallbacks,
+    },
+    gpu_descriptor_types::{
+        DescriptorBindingFlags, DescriptorBufferDevice, DescriptorBufferUsageFlags,
+        DescriptorDeviceV2, DescriptorKind, DescriptorPoolCreateFlags, DescriptorSetLayoutBinding,
+        DescriptorSetLayoutCreateFlags, DescriptorSetLayoutDevice, DescriptorTotalCount,
+        DescriptorType, ShaderStageFlags,
+    },
+    std::{
+        cell::RefCell,
+        collections::HashMap,
+        ffi::{c_char, c_void, CStr},
+        ptr::{self, NonNull},
+        sync::Arc,
+    },
+};
+
+#[derive(Default)]
+struct FakeState {
+    next_handle: u64,
+    /// Number of allocated sets in each live pool.
+    pools: HashMap<u64, u32>,
+
+    /// Raw types of structures chained to pool create infos.
+    pool_chains: Vec<i32>,
+
+    /// Raw types of structures chained to set allocate infos.
+    set_chains: Vec<i32>,
+
+    /// Called functions that take allocation callbacks,
+    /// with user data of the callbacks or null if none were passed.
+    allocators: Vec<(&'static str, *mut c_void)>,
+}
+
+impl FakeState {
+    fn live_pools(&self) -> usize {
+        self.pools.len()
+    }
+
+    fn live_sets(&self) -> u32 {
+        self.pools.values().sum()
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<FakeState> = RefCell::new(FakeState::default());
+}
+
+fn with_state<R>(f: impl FnOnce(&mut FakeState) -> R) -> R {
+    STATE.with(|state| f(&mut state.borrow_mut()))
+}
+
+/// Records user data of allocation callbacks passed to `function`.
+unsafe fn record_allocator(function: &'static str, allocator: *const vk::AllocationCallbacks<'_>) {
+    let user_data = allocator
+        .as_ref()
+        .map_or(ptr::null_mut(), |allocator| allocator.p_user_data);
+    with_state(|state| state.allocators.push((function, user_data)));
+}
+
+fn next_handle() -> u64 {
+    with_state(|state| {
+        state.next_handle += 1;
+        state.next_handle
+    })
+}
+
+/// Returns raw types of structures in `p_next` chain.
+unsafe fn chain(mut next: *const c_void) -> Vec<i32> {
+    let mut types = Vec::new();
+    while let Some(base) = (next as *const vk::BaseInStructure<'_>).as_ref() {
+        types.push(base.s_type.as_raw());
+        next = base.p_next.cast();
+    }
+    types
+}
+
+unsafe extern "system" fn create_descriptor_pool(
+    _device: vk::Device,
+    info: *const vk::DescriptorPoolCreateInfo<'_>,
+    allocator: *const vk::AllocationCallbacks<'_>,
+    pool: *mut vk::DescriptorPool,
+) -> vk::Result {
+    record_allocator("vkCreateDescriptorPool", allocator);
+    let types = chain((*info).p_next);
+    with_state(|state| {
+        state.pool_chains.extend(types);
+    });
+    let handle = next_handle();
+    with_state(|state| state.pools.insert(handle, 0));
+    *pool = vk::DescriptorPool::from_raw(handle);
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn destroy_descriptor_pool(
+    _device: vk::Device,
+    pool: vk::DescriptorPool,
+    allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    record_allocator("vkDestroyDescriptorPool", allocator);
+    with_state(|state| state.pools.remove(&pool.as_raw()).unwrap());
+}
+
+unsafe extern "system" fn allocate_descriptor_sets(
+    _device: vk::Device,
+    info: *const vk::DescriptorSetAllocateInfo<'_>,
+    sets: *mut vk::DescriptorSet,
+) -> vk::Result {
+    let info = &*info;
+    let types = chain(info.p_next);
+    with_state(|state| {
+        state.set_chains.extend(types);
+        *state.pools.get_mut(&info.descriptor_pool.as_raw()).unwrap() += info.descriptor_set_count;
+    });
+    for index in 0..info.descriptor_set_count as usize {
+        *sets.add(index) = vk::DescriptorSet::from_raw(next_handle());
+    }
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn free_descriptor_sets(
+    _device: vk::Device,
+    pool: vk::DescriptorPool,
+    count: u32,
+    _sets: *const vk::DescriptorSet,
+) -> vk::Result {
+    with_state(|state| *state.pools.get_mut(&pool.as_raw()).unwrap() -= count);
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn reset_descriptor_pool(
+    _device: vk::Device,
+    pool: vk::DescriptorPool,
+    _flags: vk::DescriptorPoolResetFlags,
+) -> vk::Result {
+    with_state(|state| state.pools.insert(pool.as_raw(), 0));
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn create_descriptor_set_layout(
+    _device: vk::Device,
+    _info: *const vk::DescriptorSetLayoutCreateInfo<'_>,
+    allocator: *const vk::AllocationCallbacks<'_>,
+    layout: *mut vk::DescriptorSetLayout,
+) -> vk::Result {
+    record_allocator("vkCreateDescriptorSetLayout", allocator);
+    *layout = vk::DescriptorSetLayout::from_raw(next_handle());
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn destroy_descriptor_set_layout(
+    _device: vk::Device,
+    _layout: vk::DescriptorSetLayout,
+    allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    record_allocator("vkDestroyDescriptorSetLayout", allocator);
+}
+
+unsafe extern "system" fn create_buffer(
+    _device: vk::Device,
+    _info: *const vk::BufferCreateInfo<'_>,
+    allocator: *const vk::AllocationCallbacks<'_>,
+    buffer: *mut vk::Buffer,
+) -> vk::Result {
+    record_allocator("vkCreateBuffer", allocator);
+    *buffer = vk::Buffer::from_raw(next_handle());
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn destroy_buffer(
+    _device: vk::Device,
+    _buffer: vk::Buffer,
+    allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    record_allocator("vkDestroyBuffer", allocator);
+}
+
+unsafe extern "system" fn get_buffer_memory_requirements(
+    _device: vk::Device,
+    _buffer: vk::Buffer,
+    requirements: *mut vk::MemoryRequirements,
+) {
+    *requirements = vk::MemoryRequirements {
+        size: 256,
+        alignment: 16,
+        memory_type_bits: !0,
+    };
+}
+
+unsafe extern "system" fn allocate_memory(
+    _device: vk::Device,
+    _info: *const vk::MemoryAllocateInfo<'_>,
+    allocator: *const vk::AllocationCallbacks<'_>,
+    memory: *mut vk::DeviceMemory,
+) -> vk::Result {
+    record_allocator("vkAllocateMemory", allocator);
+    *memory = vk::DeviceMemory::from_raw(next_handle());
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn free_memory(
+    _device: vk::Device,
+    _memory: vk::DeviceMemory,
+    allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    record_allocator("vkFreeMemory", allocator);
+}
+
+unsafe extern "system" fn bind_buffer_memory(
+    _device: vk::Device,
+    _buffer: vk::Buffer,
+    _memory: vk::DeviceMemory,
+    _offset: vk::DeviceSize,
+) -> vk::Result {
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn map_memory(
+    _device: vk::Device,
+    _memory: vk::DeviceMemory,
+    _offset: vk::DeviceSize,
+    _size: vk::DeviceSize,
+    _flags: vk::MemoryMapFlags,
+    data: *mut *mut c_void,
+) -> vk::Result {
+    // Mapped memory is never accessed by the tests.
+    *data = NonNull::<u64>::dangling().as_ptr().cast();
+    vk::Result::SUCCESS
+}
+
+unsafe extern "system" fn get_buffer_device_address(
+    _device: vk::Device,
+    _info: *const vk::BufferDeviceAddressInfo<'_>,
+) -> vk::DeviceAddress {
+    0x1000
+}
+
+unsafe extern "system" fn get_device_proc_addr(
+    _device: vk::Device,
+    name: *const c_char,
+) -> vk::PFN_vkVoidFunction {
+    let function = lookup(CStr::from_ptr(name));
+    std::mem::transmute::<*const c_void, vk::PFN_vkVoidFunction>(function)
+}
+
+fn lookup(name: &CStr) -> *const c_void {
+    match name.to_bytes() {
+        b"vkCreateDescriptorPool" => create_descriptor_pool as *const c_void,
+        b"vkDestroyDescriptorPool" => destroy_descriptor_pool as *const c_void,
+        b"vkAllocateDescriptorSets" => allocate_descriptor_sets as *const c_void,
+        b"vkFreeDescriptorSets" => free_descriptor_sets as *const c_void,
+        b"vkResetDescriptorPool" => reset_descriptor_pool as *const c_void,
+        b"vkCreateDescriptorSetLayout" => create_descriptor_set_layout as *const c_void,
+        b"vkDestroyDescriptorSetLayout" => destroy_descriptor_set_layout as *const c_void,
+        b"vkCreateBuffer" => create_buffer as *const c_void,
+        b"vkDestroyBuffer" => destroy_buffer as *const c_void,
+        b"vkGetBufferMemoryRequirements" => get_buffer_memory_requirements as *const c_void,
+        b"vkAllocateMemory" => allocate_memory as *const c_void,
+        b"vkFreeMemory" => free_memory as *const c_void,
+        b"vkBindBufferMemory" => bind_buffer_memory as *const c_void,
+        b"vkMapMemory" => map_memory as *const c_void,
+        b"vkGetBufferDeviceAddress" => get_buffer_device_address as *const c_void,
+        b"vkGetDeviceProcAddr" => get_device_proc_addr as *const c_void,
+        _ => ptr::null(),
+    }
+}
+
+fn fake_device() -> Device {
+    unsafe { Device::load_with(lookup, vk::Device::null()) }
+}
+
+/// Chains structures to every pool create and set allocate info.
+struct ChainExtensions;
+
+impl AshDescriptorExtensions for ChainExtensions {
+    fn create_descriptor_pool(
+        &self,
+        info: vk::DescriptorPoolCreateInfo<'_>,
+        create: &mut dyn FnMut(&vk::DescriptorPoolCreateInfo<'_>) -> VkResult<vk::DescriptorPool>,
+    ) -> VkResult<vk::DescriptorPool> {
+        let mut inline_uniform_block = vk::DescriptorPoolInlineUniformBlockCreateInfo::default()
+            .max_inline_uniform_block_bindings(4);
+        create(&info.push_next(&mut inline_uniform_block))
+    }
+
+    fn allocate_descriptor_sets(
+        &self,
+        info: vk::DescriptorSetAllocateInfo<'_>,
+        allocate: &mut dyn FnMut(
+            &vk::DescriptorSetAllocateInfo<'_>,
+        ) -> VkResult<Vec<vk::DescriptorSet>>,
+    ) -> VkResult<Vec<vk::DescriptorSet>> {
+        let counts = vec![0; info.descriptor_set_count as usize];
+        let mut variable_count = vk::DescriptorSetVariableDescriptorCountAllocateInfo::default()
+            .descriptor_counts(&counts);
+        allocate(&info.push_next(&mut variable_count))
+    }
+}
+
+/// Returns callbacks that are told apart by their user data.
+/// Fake device never calls them.
+fn callbacks(user_data: &mut u32) -> vk::AllocationCallbacks<'_> {
+    vk::AllocationCallbacks::default().user_data((user_data as *mut u32).cast())
+}
+
+/// Allocates and frees sets through `device`.
+fn allocate_and_free<D>(device: &D)
+where
+    D: DescriptorDeviceV2<
+        Layout = vk::DescriptorSetLayout,
+        Pool = vk::DescriptorPool,
+        Set = vk::DescriptorSet,
+    >,
+{
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = vk::DescriptorSetLayout::null();
+
+    unsafe {
+        let sets = allocator
+            .allocate(
+                device,
+                &layout,
+                DescriptorSetLayoutCreateFlags::empty(),
+                &pool_size(),
+                3,
+            )
+            .unwrap();
+        assert_eq!(with_state(|state| state.live_sets()), 3);
+        allocator.free(device, sets);
+        allocator.cleanup(device);
+    }
+
+    with_state(|state| {
+        assert_eq!(state.live_sets(), 0);
+        assert_eq!(state.live_pools(), 0);
+    });
+}
+
+fn pool_size() -> DescriptorTotalCount {
+    DescriptorTotalCount::new().with(DescriptorKind::UniformBuffer, 2)
+}
+
+#[test]
+fn extension_hooks_chain_structures_to_infos() {
+    let device = fake_device();
+    let device = AshDescriptorDevice::with_extensions(&device, ChainExtensions);
+    let layouts = [vk::DescriptorSetLayout::null(); 2];
+
+    unsafe {
+        let mut pool = DescriptorDeviceV2::create_descriptor_pool(
+            &device,
+            &pool_size(),
+            2,
+            DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+        )
+        .unwrap();
+
+        let mut sets = Vec::new();
+        DescriptorDeviceV2::alloc_descriptor_sets(&device, &mut pool, layouts.iter(), &mut sets)
+            .unwrap();
+        assert_eq!(sets.len(), 2);
+
+        DescriptorDeviceV2::dealloc_descriptor_sets(&device, &mut pool, sets.into_iter());
+        DescriptorDeviceV2::destroy_descriptor_pool(&device, pool);
+    }
+
+    with_state(|state| {
+        assert_eq!(
+            state.pool_chains,
+            [vk::StructureType::DESCRIPTOR_POOL_INLINE_UNIFORM_BLOCK_CREATE_INFO.as_raw()]
+        );
+        assert_eq!(
+            state.set_chains,
+            [vk::StructureType::DESCRIPTOR_SET_VARIABLE_DESCRIPTOR_COUNT_ALLOCATE_INFO.as_raw()]
+        );
+        assert_eq!(state.live_pools(), 0);
+        assert_eq!(state.live_sets(), 0);
+    });
+}
+
+#[test]
+fn default_hooks_pass_infos_unchanged() {
+    let device = fake_device();
+    let device = AshDescriptorDevice::wrap(&device);
+    let layouts = [vk::DescriptorSetLayout::null()];
+
+    unsafe {
+        let mut pool = DescriptorDeviceV2::create_descriptor_pool(
+            device,
+            &pool_size(),
+            1,
+            DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+        )
+        .unwrap();
+
+        let mut sets = Vec::new();
+        DescriptorDeviceV2::alloc_descriptor_sets(device, &mut pool, layouts.iter(), &mut sets)
+            .unwrap();
+        DescriptorDeviceV2::dealloc_descriptor_sets(device, &mut pool, sets.into_iter());
+        DescriptorDeviceV2::destroy_descriptor_pool(device, pool);
+    }
+
+    with_state(|state| {
+        assert!(state.pool_chains.is_empty());
+        assert!(state.set_chains.is_empty());
+        assert!(state
+            .allocators
+            .iter()
+            .all(|&(_, user_data)| user_data.is_null()));
+        assert_eq!(state.live_pools(), 0);
+        assert_eq!(state.live_sets(), 0);
+    });
+}
+
+#[test]
+fn allocation_callbacks_are_used_for_pools_and_layouts() {
+    let mut user_data = 0;
+    let callbacks = callbacks(&mut user_data);
+    let expected = callbacks.p_user_data;
+
+    let device = fake_device();
+    let device = AshDescriptorDevice::with_extensions(&device, callbacks);
+    let binding = DescriptorSetLayoutBinding {
+        binding: 0,
+        ty: DescriptorType::UniformBuffer,
+        count: 1,
+        stages: ShaderStageFlags::all(),
+        flags: DescriptorBindingFlags::empty(),
+    };
+
+    unsafe {
+        let pool = DescriptorDeviceV2::create_descriptor_pool(
+            &device,
+            &pool_size(),
+            1,
+            DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+        )
+        .unwrap();
+        DescriptorDeviceV2::destroy_descriptor_pool(&device, pool);
+
+        let layout = device
+            .create_descriptor_set_layout(&[binding], DescriptorSetLayoutCreateFlags::empty())
+            .unwrap();
+        device.destroy_descriptor_set_layout(layout);
+    }
+
+    with_state(|state| {
+        assert_eq!(
+            state.allocators,
+            [
+                ("vkCreateDescriptorPool", expected),
+                ("vkDestroyDescriptorPool", expected),
+                ("vkCreateDescriptorSetLayout", expected),
+                ("vkDestroyDescriptorSetLayout", expected),
+            ]
+        );
+    });
+}
+
+#[test]
+fn allocation_callbacks_are_used_for_descriptor_buffers() {
+    let mut user_data = 0;
+    let callbacks = unsafe { SharedAllocationCallbacks::new(callbacks(&mut user_data)) };
+    let expected = callbacks.callbacks().p_user_data;
+
+    let (instance, device) = unsafe {
+        let instance = Instance::load_with(lookup, vk::Instance::null());
+        let device = Device::load(instance.fp_v1_0(), vk::Device::null());
+        (instance, device)
+    };
+    let descriptor_buffer = descriptor_buffer::Device::new(&instance, &device);
+    let device = AshDescriptorBufferDevice::with_extensions(
+        device,
+        descriptor_buffer,
+        &vk::PhysicalDeviceDescriptorBufferPropertiesEXT::default(),
+        0,
+        callbacks,
+    );
+
+    unsafe {
+        let buffer = device
+            .create_descriptor_buffer(256, DescriptorBufferUsageFlags::RESOURCE)
+            .unwrap();
+        assert_eq!(buffer.address(), 0x1000);
+        device.destroy_descriptor_buffer(buffer);
+    }
+
+    with_state(|state| {
+        assert_eq!(
+            state.allocators,
+            [
+                ("vkCreateBuffer", expected),
+                ("vkAllocateMemory", expected),
+                ("vkDestroyBuffer", expected),
+                ("vkFreeMemory", expected),
+            ]
+        );
+    });
+}
+
+#[test]
+fn allocator_works_through_owned_device() {
+    let device = AshDescriptorDevice::from(fake_device());
+    allocate_and_free(&device);
+}
+
+#[test]
+fn allocator_works_through_shared_device() {
+    let device = Arc::new(fake_device());
+    let descriptor_device = AshDescriptorDevice::from(device.clone());
+    allocate_and_free(&descriptor_device);
+    allocate_and_free(&Arc::new(descriptor_device));
+    assert_eq!(Arc::strong_count(&device), 1);
+}
+
+#[test]
+fn allocator_works_through_extensions() {
+    let device = fake_device();
+    allocate_and_free(&AshDescriptorDevice::with_extensions(
+        &device,
+        ChainExtensions,
+    ));
+
+    with_state(|state| {
+        assert_eq!(
+            state.pool_chains,
+            [vk::StructureType::DESCRIPTOR_POOL_INLINE_UNIFORM_BLOCK_CREATE_INFO.as_raw()]
+        );
+        assert_eq!(
+            state.set_chains,
+            [vk::StructureType::DESCRIPTOR_SET_VARIABLE_DESCRIPTOR_COUNT_ALLOCATE_INFO.as_raw()]
+        );
+    });
+}
@@ -1,116 +1,201 @@
 use {
-    erupt::{vk1_0, DeviceLoader},
+    erupt::{
+        extensions::khr_acceleration_structure, vk1_0, vk1_2, vk1_3, DeviceLoader, ExtendableFrom,
+        SmallVec,
+    },
     gpu_descriptor_types::{
-        CreatePoolError, DescriptorDevice, DescriptorPoolCreateFlags, DescriptorTotalCount,
-        DeviceAllocationError,
+        CreateLayoutError, CreatePoolError, DescriptorBindingFlags, DescriptorDevice,
+        DescriptorDeviceCapabilities, DescriptorDeviceV2, DescriptorKind,
+        DescriptorPoolCreateFlags, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateFlags,
+        DescriptorSetLayoutDevice, DescriptorTotalCount, DescriptorType, DeviceAllocationError,
+        DeviceLimits, MAX_VENDOR_DESCRIPTOR_KINDS,
     },
+    std::{borrow::Borrow, sync::Arc},
 };
 
-#[repr(transparent)]
-pub struct EruptDescriptorDevice {
-    device: DeviceLoader,
+/// Extension points of `EruptDescriptorDevice`.
+///
+/// Allows chaining extension structures, e.g. vendor-specific ones,
+/// to infos prepared by `EruptDescriptorDevice` before the device is called.
+pub trait EruptDescriptorExtensions {
+    /// Creates descriptor pool from `info`.
+    ///
+    /// Implementation may extend `info` with extension structures before calling `create`.
+    /// Default implementation calls `create` with `info` unchanged.
+    fn create_descriptor_pool(
+        &self,
+        info: vk1_0::DescriptorPoolCreateInfoBuilder<'_>,
+        create: &mut dyn FnMut(
+            &vk1_0::DescriptorPoolCreateInfo,
+        ) -> Result<vk1_0::DescriptorPool, vk1_0::Result>,
+    ) -> Result<vk1_0::DescriptorPool, vk1_0::Result> {
+        create(&info)
+    }
+
+    /// Allocates descriptor sets from `info`.
+    ///
+    /// Implementation may extend `info` with extension structures before calling `allocate`.
+    /// Default implementation calls `allocate` with `info` unchanged.
+    fn allocate_descriptor_sets(
+        &self,
+        info: vk1_0::DescriptorSetAllocateInfoBuilder<'_>,
+        allocate: &mut dyn FnMut(
+            &vk1_0::DescriptorSetAllocateInfo,
+        ) -> Result<SmallVec<vk1_0::DescriptorSet>, vk1_0::Result>,
+    ) -> Result<SmallVec<vk1_0::DescriptorSet>, vk1_0::Result> {
+        allocate(&info)
+    }
+
+    /// Returns host memory allocation callbacks
+    /// used for every descriptor pool and layout created and destroyed by the device.
+    /// Default implementation returns `None`.
+    fn allocation_callbacks(&self) -> Option<&vk1_0::AllocationCallbacks> {
+        None
+    }
 }
 
-impl EruptDescriptorDevice {
-    pub fn wrap(device: &DeviceLoader) -> &Self {
-        unsafe {
-            // Safe because `Self` is `repr(transparent)`
-            // with only non-zero-sized field being `D`.
-            &*(device as *const DeviceLoader as *const Self)
-        }
+/// Extensions that add nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoExtensions;
+
+impl EruptDescriptorExtensions for NoExtensions {}
+
+/// Uses the callbacks for host memory allocations and adds nothing else.
+///
+/// `vk1_0::AllocationCallbacks` is neither `Send` nor `Sync`, and so is the device using it.
+/// Use `SharedAllocationCallbacks` to share the device between threads.
+impl EruptDescriptorExtensions for vk1_0::AllocationCallbacks {
+    fn allocation_callbacks(&self) -> Option<&vk1_0::AllocationCallbacks> {
+        Some(self)
     }
 }
 
-impl DescriptorDevice<vk1_0::DescriptorSetLayout, vk1_0::DescriptorPool, vk1_0::DescriptorSet>
-    for EruptDescriptorDevice
-{
-    unsafe fn create_descriptor_pool(
-        &self,
-        descriptor_count: &DescriptorTotalCount,
-        max_sets: u32,
-        flags: DescriptorPoolCreateFlags,
-    ) -> Result<vk1_0::DescriptorPool, CreatePoolError> {
-        let mut array = [vk1_0::DescriptorPoolSizeBuilder::default(); 13];
-        let mut len = 0;
+/// Allocation callbacks that are safe to use from any thread.
+///
+/// Uses the callbacks for host memory allocations and adds nothing else.
+#[derive(Clone, Copy)]
+pub struct SharedAllocationCallbacks {
+    callbacks: vk1_0::AllocationCallbacks,
+}
 
-        if descriptor_count.sampler != 0 {
-            array[len]._type = vk1_0::DescriptorType::SAMPLER;
-            array[len].descriptor_count = descriptor_count.sampler;
-            len += 1;
-        }
+// Safe because `SharedAllocationCallbacks::new` requires callbacks
+// and their user data to be usable from any thread.
+unsafe impl Send for SharedAllocationCallbacks {}
+unsafe impl Sync for SharedAllocationCallbacks {}
 
-        if descriptor_count.combined_image_sampler != 0 {
-            array[len]._type = vk1_0::DescriptorType::COMBINED_IMAGE_SAMPLER;
-            array[len].descriptor_count = descriptor_count.combined_image_sampler;
-            len += 1;
-        }
+impl SharedAllocationCallbacks {
+    /// Wraps allocation callbacks.
+    ///
+    /// # Safety
+    ///
+    /// Callbacks must be safe to call from any thread, including concurrently,
+    /// with `p_user_data` of `callbacks`.
+    pub unsafe fn new(callbacks: vk1_0::AllocationCallbacks) -> Self {
+        SharedAllocationCallbacks { callbacks }
+    }
 
-        if descriptor_count.sampled_image != 0 {
-            array[len]._type = vk1_0::DescriptorType::SAMPLED_IMAGE;
-            array[len].descriptor_count = descriptor_count.sampled_image;
-            len += 1;
-        }
+    /// Returns wrapped callbacks.
+    pub fn callbacks(&self) -> &vk1_0::AllocationCallbacks {
+        &self.callbacks
+    }
+}
 
-        if descriptor_count.storage_image != 0 {
-            array[len]._type = vk1_0::DescriptorType::STORAGE_IMAGE;
-            array[len].descriptor_count = descriptor_count.storage_image;
-            len += 1;
-        }
+impl EruptDescriptorExtensions for SharedAllocationCallbacks {
+    fn allocation_callbacks(&self) -> Option<&vk1_0::AllocationCallbacks> {
+        Some(&self.callbacks)
+    }
+}
 
-        if descriptor_count.uniform_texel_buffer != 0 {
-            array[len]._type = vk1_0::DescriptorType::UNIFORM_TEXEL_BUFFER;
-            array[len].descriptor_count = descriptor_count.uniform_texel_buffer;
-            len += 1;
-        }
+/// Descriptor device implementation over `DeviceLoader`.
+///
+/// Device can be borrowed with `wrap`, owned or shared via `Arc`.
+// `repr(C)` keeps `device` at offset zero, `wrap` relies on it.
+#[repr(C)]
+pub struct EruptDescriptorDevice<E = NoExtensions, D = DeviceLoader> {
+    device: D,
+    extensions: E,
+}
 
-        if descriptor_count.storage_texel_buffer != 0 {
-            array[len]._type = vk1_0::DescriptorType::STORAGE_TEXEL_BUFFER;
-            array[len].descriptor_count = descriptor_count.storage_texel_buffer;
-            len += 1;
-        }
+// `wrap` casts borrowed device to `Self`,
+// which is only valid while `NoExtensions` adds neither size nor alignment.
+const _: () = assert!(
+    core::mem::size_of::<EruptDescriptorDevice>() == core::mem::size_of::<DeviceLoader>()
+        && core::mem::align_of::<EruptDescriptorDevice>() == core::mem::align_of::<DeviceLoader>()
+);
 
-        if descriptor_count.uniform_buffer != 0 {
-            array[len]._type = vk1_0::DescriptorType::UNIFORM_BUFFER;
-            array[len].descriptor_count = descriptor_count.uniform_buffer;
-            len += 1;
+impl EruptDescriptorDevice {
+    pub fn wrap(device: &DeviceLoader) -> &Self {
+        unsafe {
+            // Safe because `Self` is `repr(C)`
+            // with `DeviceLoader` as first field followed by zero-sized `NoExtensions`,
+            // as asserted above.
+            &*(device as *const DeviceLoader as *const Self)
         }
+    }
+}
 
-        if descriptor_count.storage_buffer != 0 {
-            array[len]._type = vk1_0::DescriptorType::STORAGE_BUFFER;
-            array[len].descriptor_count = descriptor_count.storage_buffer;
-            len += 1;
-        }
+impl From<DeviceLoader> for EruptDescriptorDevice {
+    fn from(device: DeviceLoader) -> Self {
+        EruptDescriptorDevice::with_extensions(device, NoExtensions)
+    }
+}
 
-        if descriptor_count.uniform_buffer_dynamic != 0 {
-            array[len]._type = vk1_0::DescriptorType::UNIFORM_BUFFER_DYNAMIC;
-            array[len].descriptor_count = descriptor_count.uniform_buffer_dynamic;
-            len += 1;
-        }
+impl From<Arc<DeviceLoader>> for EruptDescriptorDevice<NoExtensions, Arc<DeviceLoader>> {
+    fn from(device: Arc<DeviceLoader>) -> Self {
+        EruptDescriptorDevice::with_extensions(device, NoExtensions)
+    }
+}
 
-        if descriptor_count.storage_buffer_dynamic != 0 {
-            array[len]._type = vk1_0::DescriptorType::STORAGE_BUFFER_DYNAMIC;
-            array[len].descriptor_count = descriptor_count.storage_buffer_dynamic;
-            len += 1;
-        }
+impl<E, D> EruptDescriptorDevice<E, D>
+where
+    E: EruptDescriptorExtensions,
+    D: Borrow<DeviceLoader>,
+{
+    /// Wraps device with extensions that are called
+    /// for every descriptor pool creation and descriptor sets allocation.
+    ///
+    /// Pass `vk1_0::AllocationCallbacks` or `SharedAllocationCallbacks`
+    /// to only use custom host memory allocator.
+    pub fn with_extensions(device: D, extensions: E) -> Self {
+        EruptDescriptorDevice { device, extensions }
+    }
 
-        if descriptor_count.input_attachment != 0 {
-            array[len]._type = vk1_0::DescriptorType::INPUT_ATTACHMENT;
-            array[len].descriptor_count = descriptor_count.input_attachment;
-            len += 1;
-        }
+    /// Returns wrapped device.
+    pub fn device(&self) -> &DeviceLoader {
+        self.device.borrow()
+    }
 
-        if descriptor_count.acceleration_structure != 0 {
-            array[len]._type = vk1_0::DescriptorType::ACCELERATION_STRUCTURE_KHR;
-            array[len].descriptor_count = descriptor_count.acceleration_structure;
-            len += 1;
-        }
+    /// Unwraps device.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
 
-        if descriptor_count.inline_uniform_block_bytes != 0 {
-            panic!("Inline uniform blocks are not supported");
-        }
+    /// Returns extensions of the device.
+    pub fn extensions(&self) -> &E {
+        &self.extensions
+    }
+}
+
+impl<E, D> DescriptorDevice<vk1_0::DescriptorSetLayout, vk1_0::DescriptorPool, vk1_0::DescriptorSet>
+    for EruptDescriptorDevice<E, D>
+where
+    E: EruptDescriptorExtensions,
+    D: Borrow<DeviceLoader>,
+{
+    unsafe fn create_descriptor_pool(
+        &self,
+        descriptor_count: &DescriptorTotalCount,
+        max_sets: u32,
+        flags: DescriptorPoolCreateFlags,
+    ) -> Result<vk1_0::DescriptorPool, CreatePoolError> {
+        let mut array = [vk1_0::DescriptorPoolSizeBuilder::default();
+            DescriptorKind::KNOWN.len() + MAX_VENDOR_DESCRIPTOR_KINDS];
+        let mut len = 0;
 
-        if descriptor_count.inline_uniform_block_bindings != 0 {
-            panic!("Inline uniform blocks are not supported");
+        for (kind, count) in descriptor_count.iter() {
+            array[len]._type = descriptor_kind(kind);
+            array[len].descriptor_count = count;
+            len += 1;
         }
 
         let mut erupt_flags = vk1_0::DescriptorPoolCreateFlags::empty();
@@ -123,16 +208,22 @@ impl DescriptorDevice<vk1_0::DescriptorSetLayout, vk1_0::DescriptorPool, vk1_0::
             erupt_flags |= vk1_0::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND;
         }
 
-        let result = self
-            .device
-            .create_descriptor_pool(
-                &vk1_0::DescriptorPoolCreateInfoBuilder::default()
-                    .max_sets(max_sets)
-                    .pool_sizes(&array[..len])
-                    .flags(erupt_flags),
-                None,
-            )
-            .result();
+        // Same bit as `VK_DESCRIPTOR_POOL_CREATE_HOST_ONLY_BIT_EXT`.
+        if flags.contains(DescriptorPoolCreateFlags::HOST_ONLY) {
+            erupt_flags |= vk1_0::DescriptorPoolCreateFlags::HOST_ONLY_VALVE;
+        }
+
+        let result = self.extensions.create_descriptor_pool(
+            vk1_0::DescriptorPoolCreateInfoBuilder::default()
+                .max_sets(max_sets)
+                .pool_sizes(&array[..len])
+                .flags(erupt_flags),
+            &mut |info| {
+                self.device()
+                    .create_descriptor_pool(info, self.extensions.allocation_callbacks())
+                    .result()
+            },
+        );
 
         match result {
             Ok(pool) => Ok(pool),
@@ -146,7 +237,8 @@ impl DescriptorDevice<vk1_0::DescriptorSetLayout, vk1_0::DescriptorPool, vk1_0::
     }
 
     unsafe fn destroy_descriptor_pool(&self, pool: vk1_0::DescriptorPool) {
-        self.device.destroy_descriptor_pool(pool, None)
+        self.device()
+            .destroy_descriptor_pool(pool, self.extensions.allocation_callbacks())
     }
 
     unsafe fn alloc_descriptor_sets<'a>(
@@ -157,15 +249,12 @@ impl DescriptorDevice<vk1_0::DescriptorSetLayout, vk1_0::DescriptorPool, vk1_0::
     ) -> Result<(), DeviceAllocationError> {
         let set_layouts: smallvec::SmallVec<[_; 16]> = layouts.copied().collect();
 
-        match self
-            .device
-            .allocate_descriptor_sets(
-                &vk1_0::DescriptorSetAllocateInfoBuilder::default()
-                    .set_layouts(&set_layouts)
-                    .descriptor_pool(*pool),
-            )
-            .result()
-        {
+        match self.extensions.allocate_descriptor_sets(
+            vk1_0::DescriptorSetAllocateInfoBuilder::default()
+                .set_layouts(&set_layouts)
+                .descriptor_pool(*pool),
+            &mut |info| self.device().allocate_descriptor_sets(info).result(),
+        ) {
             Ok(allocated) => {
                 sets.extend(allocated);
                 Ok(())
@@ -192,9 +281,285 @@ impl DescriptorDevice<vk1_0::DescriptorSetLayout, vk1_0::DescriptorPool, vk1_0::
         sets: impl Iterator<Item = vk1_0::DescriptorSet>,
     ) {
         let sets: smallvec::SmallVec<[_; 16]> = sets.collect();
-        match self.device.free_descriptor_sets(*pool, &sets).result() {
+        match self.device().free_descriptor_sets(*pool, &sets).result() {
             Ok(()) => {}
             Err(err) => panic!("Unexpected return code '{}'", err),
         }
     }
 }
+
+impl<E, D> DescriptorDeviceV2 for EruptDescriptorDevice<E, D>
+where
+    E: EruptDescriptorExtensions,
+    D: Borrow<DeviceLoader>,
+{
+    type Layout = vk1_0::DescriptorSetLayout;
+    type Pool = vk1_0::DescriptorPool;
+    type Set = vk1_0::DescriptorSet;
+
+    fn capabilities(&self) -> DescriptorDeviceCapabilities {
+        DescriptorDeviceCapabilities::RESET_POOL
+    }
+
+    fn create_descriptor_pool(
+        &self,
+        descriptor_count: &DescriptorTotalCount,
+        max_sets: u32,
+        flags: DescriptorPoolCreateFlags,
+    ) -> Result<vk1_0::DescriptorPool, CreatePoolError> {
+        // Safe, pool creation has no requirements beyond valid device.
+        unsafe { DescriptorDevice::create_descriptor_pool(self, descriptor_count, max_sets, flags) }
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, pool: vk1_0::DescriptorPool) {
+        DescriptorDevice::destroy_descriptor_pool(self, pool)
+    }
+
+    unsafe fn reset_descriptor_pool(&self, pool: &mut vk1_0::DescriptorPool) {
+        match self
+            .device()
+            .reset_descriptor_pool(*pool, vk1_0::DescriptorPoolResetFlags::empty())
+            .result()
+        {
+            Ok(()) => {}
+            Err(err) => panic!("Unexpected return code '{}'", err),
+        }
+    }
+
+    unsafe fn alloc_descriptor_sets<'a>(
+        &self,
+        pool: &mut vk1_0::DescriptorPool,
+        layouts: impl ExactSizeIterator<Item = &'a vk1_0::DescriptorSetLayout>,
+        sets: &mut impl Extend<vk1_0::DescriptorSet>,
+    ) -> Result<(), DeviceAllocationError> {
+        DescriptorDevice::alloc_descriptor_sets(self, pool, layouts, sets)
+    }
+
+    unsafe fn dealloc_descriptor_sets(
+        &self,
+        pool: &mut vk1_0::DescriptorPool,
+        sets: impl Iterator<Item = vk1_0::DescriptorSet>,
+    ) {
+        DescriptorDevice::dealloc_descriptor_sets(self, pool, sets)
+    }
+}
+
+impl<E, D> DescriptorSetLayoutDevice<vk1_0::DescriptorSetLayout> for EruptDescriptorDevice<E, D>
+where
+    E: EruptDescriptorExtensions,
+    D: Borrow<DeviceLoader>,
+{
+    unsafe fn create_descriptor_set_layout(
+        &self,
+        bindings: &[DescriptorSetLayoutBinding],
+        flags: DescriptorSetLayoutCreateFlags,
+    ) -> Result<vk1_0::DescriptorSetLayout, CreateLayoutError> {
+        let erupt_bindings = bindings
+            .iter()
+            .map(|binding| {
+                let ty = descriptor_type(binding.ty)
+                    .ok_or(CreateLayoutError::UnsupportedDescriptorType(binding.ty))?;
+
+                Ok(vk1_0::DescriptorSetLayoutBindingBuilder::new()
+                    .binding(binding.binding)
+                    .descriptor_type(ty)
+                    .descriptor_count(binding.count)
+                    .stage_flags(vk1_0::ShaderStageFlags::from_bits_truncate(
+                        binding.stages.bits(),
+                    )))
+            })
+            .collect::<Result<smallvec::SmallVec<[_; 16]>, CreateLayoutError>>()?;
+
+        let binding_flags: smallvec::SmallVec<[_; 16]> = bindings
+            .iter()
+            .map(|binding| binding_flags(binding.flags))
+            .collect();
+
+        let mut erupt_flags = vk1_0::DescriptorSetLayoutCreateFlags::empty();
+
+        if flags.contains(DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND) {
+            erupt_flags |= vk1_0::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL;
+        }
+
+        // Same bit as `VK_DESCRIPTOR_SET_LAYOUT_CREATE_HOST_ONLY_POOL_BIT_EXT`.
+        if flags.contains(DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL) {
+            erupt_flags |= vk1_0::DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL_VALVE;
+        }
+
+        if flags.contains(DescriptorSetLayoutCreateFlags::DESCRIPTOR_BUFFER) {
+            // `VK_DESCRIPTOR_SET_LAYOUT_CREATE_DESCRIPTOR_BUFFER_BIT_EXT` is not generated by erupt.
+            erupt_flags |= vk1_0::DescriptorSetLayoutCreateFlags::from_bits_unchecked(0x10);
+        }
+
+        let mut binding_flags_info = vk1_2::DescriptorSetLayoutBindingFlagsCreateInfoBuilder::new()
+            .binding_flags(&binding_flags);
+
+        let mut create_info = vk1_0::DescriptorSetLayoutCreateInfoBuilder::new()
+            .bindings(&erupt_bindings)
+            .flags(erupt_flags);
+
+        if bindings.iter().any(|binding| !binding.flags.is_empty()) {
+            create_info = create_info.extend_from(&mut binding_flags_info);
+        }
+
+        let result = self
+            .device()
+            .create_descriptor_set_layout(&create_info, self.extensions.allocation_callbacks())
+            .result();
+
+        match result {
+            Ok(layout) => Ok(layout),
+            Err(vk1_0::Result::ERROR_OUT_OF_DEVICE_MEMORY) => {
+                Err(CreateLayoutError::OutOfDeviceMemory)
+            }
+            Err(vk1_0::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(CreateLayoutError::OutOfHostMemory),
+            Err(err) => panic!("Unexpected return code '{}'", err),
+        }
+    }
+
+    unsafe fn destroy_descriptor_set_layout(&self, layout: vk1_0::DescriptorSetLayout) {
+        self.device()
+            .destroy_descriptor_set_layout(layout, self.extensions.allocation_callbacks())
+    }
+}
+
+/// Fills `DeviceLimits` from physical device limits
+/// and properties of descriptor indexing, acceleration structure
+/// and inline uniform block features.
+///
+/// Pass `None` for features that are not enabled,
+/// their limits are left unbounded, as are pool limits that Vulkan does not report.
+pub fn device_limits(
+    limits: &vk1_0::PhysicalDeviceLimits,
+    descriptor_indexing: Option<&vk1_2::PhysicalDeviceDescriptorIndexingProperties>,
+    acceleration_structure: Option<
+        &khr_acceleration_structure::PhysicalDeviceAccelerationStructurePropertiesKHR,
+    >,
+    inline_uniform_block: Option<&vk1_3::PhysicalDeviceInlineUniformBlockProperties>,
+) -> DeviceLimits {
+    DeviceLimits {
+        max_descriptor_set_samplers: limits.max_descriptor_set_samplers,
+        max_descriptor_set_uniform_buffers: limits.max_descriptor_set_uniform_buffers,
+        max_descriptor_set_uniform_buffers_dynamic: limits
+            .max_descriptor_set_uniform_buffers_dynamic,
+        max_descriptor_set_storage_buffers: limits.max_descriptor_set_storage_buffers,
+        max_descriptor_set_storage_buffers_dynamic: limits
+            .max_descriptor_set_storage_buffers_dynamic,
+        max_descriptor_set_sampled_images: limits.max_descriptor_set_sampled_images,
+        max_descriptor_set_storage_images: limits.max_descriptor_set_storage_images,
+        max_descriptor_set_input_attachments: limits.max_descriptor_set_input_attachments,
+        max_descriptor_set_acceleration_structures: acceleration_structure
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_acceleration_structures
+            }),
+        max_descriptor_set_inline_uniform_blocks: inline_uniform_block
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_inline_uniform_blocks
+            }),
+        max_descriptor_set_update_after_bind_samplers: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_samplers
+            }),
+        max_descriptor_set_update_after_bind_uniform_buffers: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_uniform_buffers
+            }),
+        max_descriptor_set_update_after_bind_uniform_buffers_dynamic: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_uniform_buffers_dynamic
+            }),
+        max_descriptor_set_update_after_bind_storage_buffers: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_storage_buffers
+            }),
+        max_descriptor_set_update_after_bind_storage_buffers_dynamic: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_storage_buffers_dynamic
+            }),
+        max_descriptor_set_update_after_bind_sampled_images: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_sampled_images
+            }),
+        max_descriptor_set_update_after_bind_storage_images: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_storage_images
+            }),
+        max_descriptor_set_update_after_bind_input_attachments: descriptor_indexing
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_input_attachments
+            }),
+        max_descriptor_set_update_after_bind_acceleration_structures: acceleration_structure
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_acceleration_structures
+            }),
+        max_descriptor_set_update_after_bind_inline_uniform_blocks: inline_uniform_block
+            .map_or(u32::MAX, |properties| {
+                properties.max_descriptor_set_update_after_bind_inline_uniform_blocks
+            }),
+        ..DeviceLimits::UNBOUNDED
+    }
+}
+
+/// Returns `None` for descriptor types that are not supported in layouts.
+fn descriptor_type(ty: DescriptorType) -> Option<vk1_0::DescriptorType> {
+    match ty {
+        DescriptorType::Sampler => Some(vk1_0::DescriptorType::SAMPLER),
+        DescriptorType::CombinedImageSampler => Some(vk1_0::DescriptorType::COMBINED_IMAGE_SAMPLER),
+        DescriptorType::SampledImage => Some(vk1_0::DescriptorType::SAMPLED_IMAGE),
+        DescriptorType::StorageImage => Some(vk1_0::DescriptorType::STORAGE_IMAGE),
+        DescriptorType::UniformTexelBuffer => Some(vk1_0::DescriptorType::UNIFORM_TEXEL_BUFFER),
+        DescriptorType::StorageTexelBuffer => Some(vk1_0::DescriptorType::STORAGE_TEXEL_BUFFER),
+        DescriptorType::UniformBuffer => Some(vk1_0::DescriptorType::UNIFORM_BUFFER),
+        DescriptorType::StorageBuffer => Some(vk1_0::DescriptorType::STORAGE_BUFFER),
+        DescriptorType::UniformBufferDynamic => Some(vk1_0::DescriptorType::UNIFORM_BUFFER_DYNAMIC),
+        DescriptorType::StorageBufferDynamic => Some(vk1_0::DescriptorType::STORAGE_BUFFER_DYNAMIC),
+        DescriptorType::InputAttachment => Some(vk1_0::DescriptorType::INPUT_ATTACHMENT),
+        DescriptorType::AccelerationStructure => {
+            Some(vk1_0::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+        }
+        DescriptorType::InlineUniformBlock => None,
+    }
+}
+
+fn descriptor_kind(kind: DescriptorKind) -> vk1_0::DescriptorType {
+    match kind {
+        DescriptorKind::Sampler => vk1_0::DescriptorType::SAMPLER,
+        DescriptorKind::CombinedImageSampler => vk1_0::DescriptorType::COMBINED_IMAGE_SAMPLER,
+        DescriptorKind::SampledImage => vk1_0::DescriptorType::SAMPLED_IMAGE,
+        DescriptorKind::StorageImage => vk1_0::DescriptorType::STORAGE_IMAGE,
+        DescriptorKind::UniformTexelBuffer => vk1_0::DescriptorType::UNIFORM_TEXEL_BUFFER,
+        DescriptorKind::StorageTexelBuffer => vk1_0::DescriptorType::STORAGE_TEXEL_BUFFER,
+        DescriptorKind::UniformBuffer => vk1_0::DescriptorType::UNIFORM_BUFFER,
+        DescriptorKind::StorageBuffer => vk1_0::DescriptorType::STORAGE_BUFFER,
+        DescriptorKind::UniformBufferDynamic => vk1_0::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+        DescriptorKind::StorageBufferDynamic => vk1_0::DescriptorType::STORAGE_BUFFER_DYNAMIC,
+        DescriptorKind::InputAttachment => vk1_0::DescriptorType::INPUT_ATTACHMENT,
+        DescriptorKind::AccelerationStructure => vk1_0::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+        DescriptorKind::InlineUniformBlockBytes | DescriptorKind::InlineUniformBlockBindings => {
+            panic!("Inline uniform blocks are not supported")
+        }
+        DescriptorKind::Vendor(raw) => vk1_0::DescriptorType(raw),
+    }
+}
+
+fn binding_flags(flags: DescriptorBindingFlags) -> vk1_2::DescriptorBindingFlags {
+    let mut erupt_flags = vk1_2::DescriptorBindingFlags::empty();
+
+    if flags.contains(DescriptorBindingFlags::UPDATE_AFTER_BIND) {
+        erupt_flags |= vk1_2::DescriptorBindingFlags::UPDATE_AFTER_BIND;
+    }
+
+    if flags.contains(DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING) {
+        erupt_flags |= vk1_2::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING;
+    }
+
+    if flags.contains(DescriptorBindingFlags::PARTIALLY_BOUND) {
+        erupt_flags |= vk1_2::DescriptorBindingFlags::PARTIALLY_BOUND;
+    }
+
+    if flags.contains(DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT) {
+        erupt_flags |= vk1_2::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT;
+    }
+
+    erupt_flags
+}
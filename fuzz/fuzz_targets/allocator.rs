@@ -0,0 +1,233 @@
+//! Runs arbitrary sequences of allocator operations against `MockDevice`
+//! and checks allocator invariants after each step.
+
+#![no_main]
+
+use {
+    arbitrary::Arbitrary,
+    gpu_descriptor::{
+        mock::{MockDevice, MockSet},
+        CreatePoolError, DescriptorAllocator, DescriptorDeviceCapabilities, DescriptorKind,
+        DescriptorSet, DescriptorSetLayoutCreateFlags, DescriptorSetLifetime, DescriptorTotalCount,
+        DeviceAllocationError, DeviceLimits, OutOfMemoryPolicy, PoolSizing,
+    },
+    libfuzzer_sys::fuzz_target,
+};
+
+#[derive(Arbitrary, Debug)]
+enum PoolError {
+    OutOfDeviceMemory,
+    OutOfHostMemory,
+    Fragmentation,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Allocate {
+        layout: u8,
+        update_after_bind: bool,
+        host_only: bool,
+        transient: bool,
+        count: u8,
+    },
+    /// Frees `count` held sets starting at `start`, taking every `step`-th one.
+    Free {
+        start: u16,
+        step: u8,
+        count: u8,
+    },
+    FreeAll,
+    Cleanup,
+    /// Imitates device loss, replacing device with a new one.
+    ForgetAll,
+    DestroyAll,
+    FailNextPoolCreation(PoolError),
+    /// Only out-of-memory errors are injected.
+    /// Allocator treats pool errors from a fresh pool as a bug and panics.
+    FailNextAllocation {
+        device_memory: bool,
+    },
+    /// Toggles support for pool reset.
+    ResetPool(bool),
+    AdaptivePoolSizing(bool),
+    /// Imports profile exported from the allocator itself.
+    ReimportProfile,
+    SetPoolLimits {
+        max_sets: u8,
+        max_descriptors: u8,
+    },
+    SetOutOfMemoryPolicy {
+        retries: u8,
+        cleanup: bool,
+    },
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    max_update_after_bind: u16,
+    ops: Vec<Op>,
+}
+
+fn layout(sampler: u32, sampled_image: u32, uniform_buffer: u32) -> DescriptorTotalCount {
+    DescriptorTotalCount::new()
+        .with(DescriptorKind::Sampler, sampler)
+        .with(DescriptorKind::SampledImage, sampled_image)
+        .with(DescriptorKind::UniformBuffer, uniform_buffer)
+}
+
+/// Few layouts so that buckets are shared between allocations.
+fn layouts() -> [DescriptorTotalCount; 4] {
+    [
+        layout(0, 0, 0),
+        layout(1, 0, 0),
+        layout(0, 4, 2),
+        // Raw type of VK_DESCRIPTOR_TYPE_MUTABLE_EXT.
+        layout(2, 16, 1).with(DescriptorKind::Vendor(1_000_351_000), 3),
+    ]
+}
+
+fuzz_target!(|input: Input| {
+    let mut device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(input.max_update_after_bind.into());
+    let mut held: Vec<DescriptorSet<MockSet>> = Vec::new();
+
+    // Sets allocated from lost devices.
+    let mut stale: Vec<DescriptorSet<MockSet>> = Vec::new();
+
+    let layouts = layouts();
+
+    for op in input.ops {
+        match op {
+            Op::Allocate {
+                layout,
+                update_after_bind,
+                host_only,
+                transient,
+                count,
+            } => {
+                let layout = &layouts[usize::from(layout) % layouts.len()];
+                // Layout cannot be both update-after-bind and host-only.
+                let flags = if update_after_bind {
+                    DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND
+                } else if host_only {
+                    DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL
+                } else {
+                    DescriptorSetLayoutCreateFlags::empty()
+                };
+
+                let lifetime = if transient {
+                    DescriptorSetLifetime::Transient
+                } else {
+                    DescriptorSetLifetime::Persistent
+                };
+
+                let result = unsafe {
+                    allocator.allocate_with_lifetime(
+                        &device,
+                        layout,
+                        flags,
+                        layout,
+                        count.into(),
+                        lifetime,
+                    )
+                };
+
+                match result {
+                    Ok(sets) => {
+                        assert_eq!(sets.len(), usize::from(count));
+                        held.extend(sets);
+                    }
+                    Err(_) => {
+                        // Partially allocated sets must be returned.
+                        assert_eq!(device.live_sets(), held.len() as u64);
+                    }
+                }
+            }
+            Op::Free { start, step, count } => {
+                if held.is_empty() {
+                    continue;
+                }
+                let step = usize::from(step).max(1);
+                let mut index = usize::from(start) % held.len();
+                let mut sets = Vec::new();
+                for _ in 0..count {
+                    if held.is_empty() {
+                        break;
+                    }
+                    index %= held.len();
+                    sets.push(held.swap_remove(index));
+                    index += step;
+                }
+                unsafe { allocator.free(&device, sets) };
+            }
+            Op::FreeAll => unsafe {
+                allocator.free(&device, held.drain(..));
+                allocator.free(&device, stale.drain(..));
+            },
+            Op::DestroyAll => {
+                unsafe { allocator.destroy_all(&device) };
+                stale.append(&mut held);
+                assert_eq!(device.live_pools(), 0);
+            }
+            Op::ForgetAll => {
+                allocator.forget_all();
+                stale.append(&mut held);
+                device = MockDevice::new();
+            }
+            Op::Cleanup => unsafe { allocator.cleanup(&device) },
+            Op::FailNextPoolCreation(err) => device.fail_next_pool_creation(match err {
+                PoolError::OutOfDeviceMemory => CreatePoolError::OutOfDeviceMemory,
+                PoolError::OutOfHostMemory => CreatePoolError::OutOfHostMemory,
+                PoolError::Fragmentation => CreatePoolError::Fragmentation,
+            }),
+            Op::ResetPool(enabled) => device.set_capabilities(if enabled {
+                DescriptorDeviceCapabilities::RESET_POOL
+            } else {
+                DescriptorDeviceCapabilities::empty()
+            }),
+            Op::ReimportProfile => {
+                let profile = allocator.pool_size_profile();
+                allocator.import_pool_size_profile(&profile);
+            }
+            Op::AdaptivePoolSizing(adaptive) => allocator.set_pool_sizing(if adaptive {
+                PoolSizing::Adaptive
+            } else {
+                PoolSizing::Fixed
+            }),
+            Op::SetPoolLimits {
+                max_sets,
+                max_descriptors,
+            } => allocator.set_device_limits(DeviceLimits {
+                max_pool_sets: max_sets.into(),
+                max_pool_descriptors: max_descriptors.into(),
+                ..DeviceLimits::UNBOUNDED
+            }),
+            Op::SetOutOfMemoryPolicy { retries, cleanup } => {
+                allocator.set_out_of_memory_policy(OutOfMemoryPolicy {
+                    retries: retries.into(),
+                    cleanup,
+                    release_memory: None,
+                })
+            }
+            Op::FailNextAllocation { device_memory } => {
+                device.fail_next_allocation(if device_memory {
+                    DeviceAllocationError::OutOfDeviceMemory
+                } else {
+                    DeviceAllocationError::OutOfHostMemory
+                })
+            }
+        }
+
+        allocator.check_invariants();
+        assert_eq!(device.live_sets(), held.len() as u64);
+    }
+
+    unsafe {
+        allocator.free(&device, stale.drain(..));
+        allocator.free(&device, held.drain(..));
+        allocator.cleanup(&device);
+    }
+    allocator.check_invariants();
+    assert_eq!(device.live_sets(), 0);
+    assert_eq!(device.live_pools(), 0);
+});
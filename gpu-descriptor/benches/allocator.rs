@@ -0,0 +1,230 @@
+//! Benchmarks of allocator hot paths against `MockDevice`.
+//!
+//! Mock device does no work beyond bookkeeping and all sequences are deterministic,
+//! so results depend only on allocator itself and are reproducible without GPU.
+
+use {
+    criterion::{criterion_group, criterion_main, Criterion, Throughput},
+    gpu_descriptor::{
+        mock::{MockDevice, MockPool, MockSet},
+        DescriptorAllocator, DescriptorKind, DescriptorSet, DescriptorSetLayoutCreateFlags,
+        DescriptorSetLifetime, DescriptorTotalCount,
+    },
+    std::{
+        hint::black_box,
+        time::{Duration, Instant},
+    },
+};
+
+fn base_layout() -> DescriptorTotalCount {
+    DescriptorTotalCount::new()
+        .with(DescriptorKind::CombinedImageSampler, 4)
+        .with(DescriptorKind::UniformBuffer, 2)
+        .with(DescriptorKind::StorageBuffer, 1)
+}
+
+fn layout(index: u32) -> DescriptorTotalCount {
+    base_layout().with(DescriptorKind::SampledImage, index + 1)
+}
+
+unsafe fn allocate(
+    allocator: &mut DescriptorAllocator<MockPool, MockSet>,
+    device: &MockDevice,
+    layout: &DescriptorTotalCount,
+    count: u32,
+) -> Vec<DescriptorSet<MockSet>> {
+    allocator
+        .allocate(
+            device,
+            layout,
+            DescriptorSetLayoutCreateFlags::empty(),
+            layout,
+            count,
+        )
+        .unwrap()
+}
+
+fn single_set(c: &mut Criterion) {
+    let layout = base_layout();
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+
+    // Keep one set alive so pool is never destroyed.
+    let keep = unsafe { allocate(&mut allocator, &device, &layout, 1) };
+
+    let mut group = c.benchmark_group("single set");
+    group.bench_function("allocate", |b| {
+        b.iter(|| unsafe {
+            let sets = allocate(&mut allocator, &device, black_box(&layout), 1);
+            allocator.free(&device, sets);
+        })
+    });
+    group.bench_function("allocate_one", |b| {
+        b.iter(|| unsafe {
+            let set = allocator
+                .allocate_one(
+                    &device,
+                    black_box(&layout),
+                    DescriptorSetLayoutCreateFlags::empty(),
+                    &layout,
+                    DescriptorSetLifetime::Persistent,
+                )
+                .unwrap();
+            allocator.free(&device, Some(set));
+        })
+    });
+    group.finish();
+
+    unsafe {
+        allocator.free(&device, keep);
+        allocator.cleanup(&device);
+    }
+}
+
+fn large_batch(c: &mut Criterion) {
+    const COUNT: u32 = 4096;
+
+    let layout = base_layout();
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+
+    let mut group = c.benchmark_group("large batch");
+    group.throughput(Throughput::Elements(COUNT.into()));
+    group.bench_function("allocate and free", |b| {
+        b.iter(|| unsafe {
+            let sets = allocate(&mut allocator, &device, black_box(&layout), COUNT);
+            allocator.free(&device, sets);
+        })
+    });
+    group.finish();
+
+    unsafe { allocator.cleanup(&device) };
+}
+
+fn many_buckets(c: &mut Criterion) {
+    const BUCKETS: u32 = 256;
+    const COUNT: u32 = 16;
+
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layouts: Vec<_> = (0..BUCKETS).map(layout).collect();
+    let mut sets = Vec::new();
+
+    let mut group = c.benchmark_group("many buckets");
+    group.throughput(Throughput::Elements(u64::from(BUCKETS * COUNT)));
+    group.bench_function("allocate and free", |b| {
+        b.iter(|| unsafe {
+            for layout in &layouts {
+                sets.extend(allocate(&mut allocator, &device, black_box(layout), COUNT));
+            }
+            allocator.free(&device, sets.drain(..));
+        })
+    });
+    group.finish();
+
+    unsafe { allocator.cleanup(&device) };
+}
+
+type FreeFn = unsafe fn(
+    &mut DescriptorAllocator<MockPool, MockSet>,
+    &MockDevice,
+    Vec<DescriptorSet<MockSet>>,
+);
+
+unsafe fn free_ordered(
+    allocator: &mut DescriptorAllocator<MockPool, MockSet>,
+    device: &MockDevice,
+    sets: Vec<DescriptorSet<MockSet>>,
+) {
+    allocator.free(device, sets)
+}
+
+unsafe fn free_unordered(
+    allocator: &mut DescriptorAllocator<MockPool, MockSet>,
+    device: &MockDevice,
+    sets: Vec<DescriptorSet<MockSet>>,
+) {
+    allocator.free_unordered(device, sets)
+}
+
+fn interleaved_free(c: &mut Criterion) {
+    const COUNT: u32 = 4096;
+
+    let layout = base_layout();
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+
+    let mut group = c.benchmark_group("interleaved free");
+    group.throughput(Throughput::Elements(COUNT.into()));
+    for (name, free) in [
+        ("ordered", free_ordered as FreeFn),
+        ("unordered", free_unordered as FreeFn),
+    ] {
+        group.bench_function(format!("across pools, {}", name), |b| {
+            b.iter_custom(|iters| {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iters {
+                    // Sets span several pools, take every 7th one to interleave them.
+                    let mut sets: Vec<_> =
+                        unsafe { allocate(&mut allocator, &device, &layout, COUNT) }
+                            .into_iter()
+                            .map(Some)
+                            .collect();
+                    let len = sets.len();
+                    let sets: Vec<_> = (0..len)
+                        .map(|index| sets[index * 7 % len].take().unwrap())
+                        .collect();
+
+                    let start = Instant::now();
+                    unsafe { free(&mut allocator, &device, black_box(sets)) };
+                    elapsed += start.elapsed();
+                }
+                elapsed
+            })
+        });
+    }
+    group.finish();
+
+    unsafe { allocator.cleanup(&device) };
+}
+
+fn cleanup(c: &mut Criterion) {
+    const POOLS: u32 = 4096;
+
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layouts: Vec<_> = (0..POOLS).map(layout).collect();
+
+    let mut group = c.benchmark_group("cleanup");
+    group.throughput(Throughput::Elements(POOLS.into()));
+    group.bench_function("thousands of pools", |b| {
+        b.iter_custom(|iters| {
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..iters {
+                // Each bucket keeps its last pool after all sets are freed.
+                for layout in &layouts {
+                    unsafe {
+                        let sets = allocate(&mut allocator, &device, layout, 1);
+                        allocator.free(&device, sets);
+                    }
+                }
+
+                let start = Instant::now();
+                unsafe { allocator.cleanup(&device) };
+                elapsed += start.elapsed();
+            }
+            elapsed
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    single_set,
+    large_batch,
+    many_buckets,
+    interleaved_free,
+    cleanup
+);
+criterion_main!(benches);
@@ -1,36 +1,34 @@
 use {
-    alloc::{collections::VecDeque, vec::Vec},
+    crate::{
+        dump::{DescriptorAllocatorDump, DescriptorBucketDump, DescriptorPoolDump},
+        listener::{BucketInfo, DescriptorAllocatorListener, Listener, PoolInfo},
+        profile::{PoolSizeProfile, PoolSizeProfileEntry},
+        recovery::OutOfMemoryPolicy,
+    },
+    alloc::{boxed::Box, collections::VecDeque, vec::Vec},
     core::{
+        cmp::Ordering,
         convert::TryFrom as _,
         fmt::{self, Debug, Display},
     },
     gpu_descriptor_types::{
-        CreatePoolError, DescriptorDevice, DescriptorPoolCreateFlags, DescriptorTotalCount,
-        DeviceAllocationError,
+        CreatePoolError, DescriptorDeviceCapabilities, DescriptorDeviceV2,
+        DescriptorPoolCreateFlags, DescriptorSetLayoutCreateFlags, DescriptorTotalCount,
+        DeviceAllocationError, DeviceLimits,
     },
     hashbrown::HashMap,
 };
 
-bitflags::bitflags! {
-    /// Flags to augment descriptor set allocation.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
-    pub struct DescriptorSetLayoutCreateFlags: u32 {
-        /// Specified that descriptor set must be allocated from\
-        /// pool with `DescriptorPoolCreateFlags::UPDATE_AFTER_BIND`.
-        ///
-        /// This flag must be specified when and only when layout was created with matching backend-specific flag,
-        /// that allows layout to have UpdateAfterBind bindings.
-        const UPDATE_AFTER_BIND = 0x2;
-    }
-}
-
 /// Descriptor set from allocator.
 #[derive(Debug)]
 pub struct DescriptorSet<S> {
     raw: S,
-    pool_id: u64,
-    size: DescriptorTotalCount,
-    update_after_bind: bool,
+    pub(crate) pool_id: u64,
+    pub(crate) epoch: u64,
+    pub(crate) size: DescriptorTotalCount,
+    pub(crate) update_after_bind: bool,
+    pub(crate) host_only: bool,
+    pub(crate) lifetime: DescriptorSetLifetime,
 }
 
 impl<S> DescriptorSet<S> {
@@ -47,6 +45,110 @@ impl<S> DescriptorSet<S> {
     pub unsafe fn raw_mut(&mut self) -> &mut S {
         &mut self.raw
     }
+
+    /// Returns lifetime hint the set was allocated with.
+    pub fn lifetime(&self) -> DescriptorSetLifetime {
+        self.lifetime
+    }
+}
+
+/// Expected lifetime of descriptor sets.
+///
+/// Sets with different lifetimes are allocated from separate pools,
+/// so that few long-lived sets do not pin pools full of freed short-lived ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DescriptorSetLifetime {
+    /// Sets that live for many frames, e.g. material sets.
+    ///
+    /// Pools grow with number of sets allocated.
+    /// Empty pools are destroyed only from the front of the bucket, oldest first,
+    /// so an empty pool is kept while any older pool of the bucket has live sets.
+    /// The last pool of the bucket is kept for reuse when empty
+    /// until `DescriptorAllocator::cleanup` is called.
+    #[default]
+    Persistent,
+
+    /// Sets that are freed shortly after allocation, e.g. per-frame sets.
+    ///
+    /// Pools grow with peak number of sets allocated
+    /// and are kept for reuse when empty until `DescriptorAllocator::cleanup` is called.
+    Transient,
+}
+
+/// Strategy of choosing number of sets for new descriptor pools.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PoolSizing {
+    /// Pools grow with number of sets currently allocated from the bucket
+    /// (or peak number for transient sets), from 64 up to 512 sets.
+    #[default]
+    Fixed,
+
+    /// Pools are sized from moving averages of live sets and of sets allocated per call.
+    ///
+    /// Buckets that spiked once return to smaller pools as usage settles,
+    /// and buckets that shrink get pools down to 8 sets.
+    Adaptive,
+}
+
+/// Statistics of descriptor allocator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DescriptorAllocatorStatistics {
+    /// Number of buckets currently alive.
+    pub buckets: u32,
+
+    /// Number of descriptor pools currently alive.
+    pub pools: u32,
+
+    /// Number of descriptor sets currently allocated.
+    pub allocated_sets: u32,
+
+    /// Total number of sets all pools were created with.
+    pub reserved_sets: u64,
+
+    /// Number of descriptors in currently allocated sets.
+    pub allocated_descriptors: u32,
+}
+
+/// Statistics of one descriptor bucket.
+///
+/// Buckets group sets with equal descriptor counts, update-after-bind and host-only flags
+/// and lifetime hint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorBucketStatistics {
+    /// Number of descriptors in each set of the bucket.
+    pub size: DescriptorTotalCount,
+
+    /// Whether sets of the bucket are allocated from update-after-bind pools.
+    pub update_after_bind: bool,
+
+    /// Whether sets of the bucket are allocated from host-only pools.
+    pub host_only: bool,
+
+    /// Lifetime hint of sets in the bucket.
+    pub lifetime: DescriptorSetLifetime,
+
+    /// Number of pools currently owned by the bucket.
+    pub pools: u32,
+
+    /// Number of sets currently allocated from the bucket.
+    pub allocated_sets: u32,
+
+    /// Maximum number of sets allocated from the bucket at once.
+    pub peak_sets: u32,
+
+    /// Total number of sets pools of the bucket were created with.
+    pub reserved_sets: u64,
+
+    /// Moving average of number of sets allocated from the bucket.
+    pub average_live_sets: u32,
+
+    /// Moving average of number of sets allocated per allocation call.
+    pub sets_per_allocation: u32,
+
+    /// Number of sets next pool of the bucket would be created with
+    /// under current pool sizing strategy.
+    pub next_pool_sets: u32,
 }
 
 /// AllocationError that may occur during descriptor sets allocation.
@@ -65,6 +167,18 @@ pub enum AllocationError {
     /// with flag `CREATE_UPDATE_AFTER_BIND_BIT` set exceeds `max_update_after_bind_descriptors_in_all_pools`
     /// Or fragmentation of the underlying hardware resources occurs.
     Fragmentation,
+
+    /// Layout exceeds named device limit set with `DescriptorAllocator::set_device_limits`.
+    /// Sets with such layout can never be allocated.
+    LimitExceeded(&'static str),
+
+    /// Number of descriptors in requested sets or in all allocated sets
+    /// does not fit in `u32`.
+    Overflow,
+
+    /// Layout flags combine `UPDATE_AFTER_BIND` and `HOST_ONLY_POOL`
+    /// or contain `DESCRIPTOR_BUFFER`, no pool can serve such layout.
+    InvalidLayoutFlags,
 }
 
 impl Display for AllocationError {
@@ -73,6 +187,13 @@ impl Display for AllocationError {
             AllocationError::OutOfDeviceMemory => fmt.write_str("Device memory exhausted"),
             AllocationError::OutOfHostMemory => fmt.write_str("Host memory exhausted"),
             AllocationError::Fragmentation => fmt.write_str("Fragmentation"),
+            AllocationError::LimitExceeded(limit) => {
+                write!(fmt, "Layout exceeds device limit `{}`", limit)
+            }
+            AllocationError::Overflow => fmt.write_str("Descriptor count overflow"),
+            AllocationError::InvalidLayoutFlags => {
+                fmt.write_str("Layout flags cannot be served by descriptor pools")
+            }
         }
     }
 }
@@ -90,13 +211,50 @@ impl From<CreatePoolError> for AllocationError {
     }
 }
 
-const MIN_SETS: u32 = 64;
-const MAX_SETS: u32 = 512;
+pub(crate) const MIN_SETS: u32 = 64;
+pub(crate) const MAX_SETS: u32 = 512;
+
+/// Parameters of new pools shared by all buckets.
+#[derive(Debug, Default)]
+struct PoolConfig {
+    sizing: PoolSizing,
+    limits: DeviceLimits,
+}
+
+/// Lower bound for pools sized with `PoolSizing::Adaptive`.
+const ADAPTIVE_MIN_SETS: u32 = 8;
+
+/// Number of average allocation calls new adaptive pool should be able to serve.
+const ADAPTIVE_BATCHES: u32 = 4;
+
+/// Newest sample contributes 1 / 2^AVERAGE_SHIFT to moving averages.
+const AVERAGE_SHIFT: u32 = 3;
+
+/// Number of fractional bits in fixed-point moving averages.
+const AVERAGE_FRACTION: u32 = 8;
+
+/// Adds sample to fixed-point exponential moving average.
+fn moving_average(average: u64, sample: u32) -> u64 {
+    let sample = u64::from(sample) << AVERAGE_FRACTION;
+    average - (average >> AVERAGE_SHIFT) + (sample >> AVERAGE_SHIFT)
+}
+
+/// Rounds fixed-point moving average to the nearest integer.
+fn average_value(average: u64) -> u32 {
+    let value = (average + (1 << (AVERAGE_FRACTION - 1))) >> AVERAGE_FRACTION;
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
 
 #[derive(Debug)]
 struct DescriptorPool<P> {
     raw: P,
 
+    /// Number of descriptors pool was created with.
+    size: DescriptorTotalCount,
+
+    /// Number of sets pool was created with.
+    max_sets: u32,
+
     /// Number of sets allocated from pool.
     allocated: u32,
 
@@ -109,7 +267,24 @@ struct DescriptorBucket<P> {
     offset: u64,
     pools: VecDeque<DescriptorPool<P>>,
     total: u32,
+
+    /// Maximum number of sets allocated at once.
+    peak: u32,
+
+    /// Fixed-point moving average of `total`, sampled on each allocation and free.
+    average_live: u64,
+
+    /// Fixed-point moving average of number of sets requested per allocation.
+    sets_per_allocation: u64,
+
+    /// Epoch of the allocator when bucket was created.
+    epoch: u64,
+
+    /// Peak number of sets from imported profile.
+    profile_peak: u32,
     update_after_bind: bool,
+    host_only: bool,
+    lifetime: DescriptorSetLifetime,
     size: DescriptorTotalCount,
 }
 
@@ -146,75 +321,153 @@ impl<P> Drop for DescriptorBucket<P> {
 }
 
 impl<P> DescriptorBucket<P> {
-    fn new(update_after_bind: bool, size: DescriptorTotalCount) -> Self {
+    fn new(
+        epoch: u64,
+        profile_peak: u32,
+        update_after_bind: bool,
+        host_only: bool,
+        lifetime: DescriptorSetLifetime,
+        size: DescriptorTotalCount,
+    ) -> Self {
         DescriptorBucket {
             offset: 0,
             pools: VecDeque::new(),
             total: 0,
+            peak: 0,
+            average_live: 0,
+            sets_per_allocation: 0,
+            epoch,
+            profile_peak,
             update_after_bind,
+            host_only,
+            lifetime,
             size,
         }
     }
 
-    fn new_pool_size(&self, minimal_set_count: u32) -> (DescriptorTotalCount, u32) {
-        let mut max_sets = MIN_SETS // at least MIN_SETS
+    fn info(&self) -> BucketInfo {
+        BucketInfo {
+            size: self.size,
+            update_after_bind: self.update_after_bind,
+            host_only: self.host_only,
+            lifetime: self.lifetime,
+            total: self.total,
+            pools: self.pools.len() as u32,
+        }
+    }
+
+    fn pool_flags(&self) -> DescriptorPoolCreateFlags {
+        let mut flags = DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET;
+        if self.update_after_bind {
+            flags |= DescriptorPoolCreateFlags::UPDATE_AFTER_BIND;
+        }
+        if self.host_only {
+            flags |= DescriptorPoolCreateFlags::HOST_ONLY;
+        }
+        flags
+    }
+
+    fn pool_info(&self, pool: &DescriptorPool<P>, pool_id: u64) -> PoolInfo {
+        PoolInfo {
+            pool_id,
+            descriptor_count: pool.size,
+            max_sets: pool.max_sets,
+            flags: self.pool_flags(),
+        }
+    }
+
+    fn statistics(&self, config: &PoolConfig) -> DescriptorBucketStatistics {
+        DescriptorBucketStatistics {
+            size: self.size,
+            update_after_bind: self.update_after_bind,
+            host_only: self.host_only,
+            lifetime: self.lifetime,
+            pools: self.pools.len() as u32,
+            allocated_sets: self.total,
+            peak_sets: self.peak,
+            reserved_sets: self.pools.iter().map(|pool| u64::from(pool.max_sets)).sum(),
+            average_live_sets: average_value(self.average_live),
+            sets_per_allocation: average_value(self.sets_per_allocation),
+            next_pool_sets: self.new_pool_size(1, config).1,
+        }
+    }
+
+    fn new_pool_size(
+        &self,
+        minimal_set_count: u32,
+        config: &PoolConfig,
+    ) -> (DescriptorTotalCount, u32) {
+        let (min_sets, expected) = match config.sizing {
+            PoolSizing::Fixed => {
+                // Transient sets come and go in waves, size pools for the highest wave seen.
+                let allocated = match self.lifetime {
+                    DescriptorSetLifetime::Persistent => self.total,
+                    DescriptorSetLifetime::Transient => self.peak,
+                };
+                (MIN_SETS, allocated)
+            }
+            PoolSizing::Adaptive => {
+                // Expect usage to stay around the average, with room for a few more calls.
+                let average = average_value(self.average_live);
+                let batches =
+                    average_value(self.sets_per_allocation).saturating_mul(ADAPTIVE_BATCHES);
+                (ADAPTIVE_MIN_SETS, average.max(batches))
+            }
+        };
+
+        // Until pools cover peak from profile, expect the rest of it.
+        let reserved: u64 = self.pools.iter().map(|pool| u64::from(pool.max_sets)).sum();
+        let profiled = u64::from(self.profile_peak).saturating_sub(reserved) as u32;
+        let expected = expected.max(profiled);
+
+        let mut max_sets = min_sets // at least minimal number of sets for the strategy
             .max(minimal_set_count) // at least enough for allocation
-            .max(self.total.min(MAX_SETS)) // at least as much as was allocated so far capped to MAX_SETS
+            .max(expected.min(MAX_SETS)) // at least as much as expected capped to MAX_SETS
             .checked_next_power_of_two() // rounded up to nearest 2^N
             .unwrap_or(i32::MAX as u32);
 
-        max_sets = (u32::MAX / self.size.sampler.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.combined_image_sampler.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.sampled_image.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.storage_image.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.uniform_texel_buffer.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.storage_texel_buffer.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.uniform_buffer.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.storage_buffer.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.uniform_buffer_dynamic.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.storage_buffer_dynamic.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.input_attachment.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.acceleration_structure.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.inline_uniform_block_bytes.max(1)).min(max_sets);
-        max_sets = (u32::MAX / self.size.inline_uniform_block_bindings.max(1)).min(max_sets);
-
-        let mut pool_size = DescriptorTotalCount {
-            sampler: self.size.sampler * max_sets,
-            combined_image_sampler: self.size.combined_image_sampler * max_sets,
-            sampled_image: self.size.sampled_image * max_sets,
-            storage_image: self.size.storage_image * max_sets,
-            uniform_texel_buffer: self.size.uniform_texel_buffer * max_sets,
-            storage_texel_buffer: self.size.storage_texel_buffer * max_sets,
-            uniform_buffer: self.size.uniform_buffer * max_sets,
-            storage_buffer: self.size.storage_buffer * max_sets,
-            uniform_buffer_dynamic: self.size.uniform_buffer_dynamic * max_sets,
-            storage_buffer_dynamic: self.size.storage_buffer_dynamic * max_sets,
-            input_attachment: self.size.input_attachment * max_sets,
-            acceleration_structure: self.size.acceleration_structure * max_sets,
-            inline_uniform_block_bytes: self.size.inline_uniform_block_bytes * max_sets,
-            inline_uniform_block_bindings: self.size.inline_uniform_block_bindings * max_sets,
-        };
+        max_sets = max_sets.min(config.limits.max_pool_sets.max(1));
+
+        // Only descriptor types present in the layout limit the pool.
+        // Caller ensures that one set fits, so pool has room for at least one.
+        for (_, count) in self.size.iter() {
+            max_sets = max_sets.min(config.limits.max_pool_descriptors / count);
+        }
 
-        if pool_size == Default::default() {
-            pool_size.sampler = 1;
+        let mut pool_size = self
+            .size
+            .checked_mul(max_sets)
+            .expect("Pool is clamped to `max_pool_descriptors` of each type");
+
+        if pool_size.is_empty() {
+            pool_size.set_sampler(1);
         }
 
         (pool_size, max_sets)
     }
 
-    unsafe fn allocate<L, S>(
+    unsafe fn allocate<D, S>(
         &mut self,
-        device: &impl DescriptorDevice<L, P, S>,
-        layout: &L,
+        device: &D,
+        layout: &D::Layout,
         mut count: u32,
+        config: &PoolConfig,
         allocated_sets: &mut Vec<DescriptorSet<S>>,
-    ) -> Result<(), AllocationError> {
+        listener: &mut Listener,
+    ) -> Result<(), AllocationError>
+    where
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
         debug_assert!(usize::try_from(count).is_ok(), "Must be ensured by caller");
 
         if count == 0 {
             return Ok(());
         }
 
+        self.sets_per_allocation = moving_average(self.sets_per_allocation, count);
+
+        let pools = self.pools.len() as u32;
+
         for (index, pool) in self.pools.iter_mut().enumerate().rev() {
             if pool.available == 0 {
                 continue;
@@ -229,8 +482,11 @@ impl<P> DescriptorBucket<P> {
                 &mut pool.raw,
                 (0..allocate).map(|_| layout),
                 &mut Allocation {
+                    epoch: self.epoch,
                     size: self.size,
                     update_after_bind: self.update_after_bind,
+                    host_only: self.host_only,
+                    lifetime: self.lifetime,
                     pool_id: index as u64 + self.offset,
                     sets: allocated_sets,
                 },
@@ -261,6 +517,19 @@ impl<P> DescriptorBucket<P> {
             pool.available -= allocate;
             pool.allocated += allocate;
             self.total += allocate;
+            self.peak = self.peak.max(self.total);
+            self.average_live = moving_average(self.average_live, self.total);
+
+            let pool_id = index as u64 + self.offset;
+            let info = BucketInfo {
+                size: self.size,
+                update_after_bind: self.update_after_bind,
+                host_only: self.host_only,
+                lifetime: self.lifetime,
+                total: self.total,
+                pools,
+            };
+            listener.emit(|listener| listener.on_sets_allocated(&info, pool_id, allocate));
 
             if count == 0 {
                 return Ok(());
@@ -268,7 +537,7 @@ impl<P> DescriptorBucket<P> {
         }
 
         while count > 0 {
-            let (pool_size, max_sets) = self.new_pool_size(count);
+            let (pool_size, max_sets) = self.new_pool_size(count, config);
             #[cfg(feature = "tracing")]
             tracing::trace!(
                 "Create new pool with {} sets and {:?} descriptors",
@@ -276,27 +545,30 @@ impl<P> DescriptorBucket<P> {
                 pool_size,
             );
 
-            let mut raw = device.create_descriptor_pool(
-                &pool_size,
-                max_sets,
-                if self.update_after_bind {
-                    DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET
-                        | DescriptorPoolCreateFlags::UPDATE_AFTER_BIND
-                } else {
-                    DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET
-                },
-            )?;
+            let flags = self.pool_flags();
+            let mut raw = device.create_descriptor_pool(&pool_size, max_sets, flags)?;
 
             let pool_id = self.pools.len() as u64 + self.offset;
+            let pool_info = PoolInfo {
+                pool_id,
+                descriptor_count: pool_size,
+                max_sets,
+                flags,
+            };
+            let info = self.info();
+            listener.emit(|listener| listener.on_pool_created(&info, &pool_info));
 
             let allocate = max_sets.min(count);
             let result = device.alloc_descriptor_sets(
                 &mut raw,
                 (0..allocate).map(|_| layout),
                 &mut Allocation {
+                    epoch: self.epoch,
                     pool_id,
                     size: self.size,
                     update_after_bind: self.update_after_bind,
+                    host_only: self.host_only,
+                    lifetime: self.lifetime,
                     sets: allocated_sets,
                 },
             );
@@ -305,6 +577,7 @@ impl<P> DescriptorBucket<P> {
                 Ok(()) => {}
                 Err(err) => {
                     device.destroy_descriptor_pool(raw);
+                    listener.emit(|listener| listener.on_pool_destroyed(&info, &pool_info));
                     match err {
                         DeviceAllocationError::OutOfDeviceMemory => {
                             return Err(AllocationError::OutOfDeviceMemory)
@@ -326,81 +599,186 @@ impl<P> DescriptorBucket<P> {
             count -= allocate;
             self.pools.push_back(DescriptorPool {
                 raw,
+                size: pool_size,
+                max_sets,
                 allocated: allocate,
                 available: max_sets - allocate,
             });
             self.total += allocate;
+            self.peak = self.peak.max(self.total);
+            self.average_live = moving_average(self.average_live, self.total);
+
+            let info = self.info();
+            listener.emit(|listener| listener.on_sets_allocated(&info, pool_id, allocate));
         }
 
         Ok(())
     }
 
-    unsafe fn free<L, S>(
+    unsafe fn free<S>(
         &mut self,
-        device: &impl DescriptorDevice<L, P, S>,
-        raw_sets: impl IntoIterator<Item = S>,
+        device: &impl DescriptorDeviceV2<Pool = P, Set = S>,
+        mut raw_sets: impl ExactSizeIterator<Item = S>,
         pool_id: u64,
+        listener: &mut Listener,
     ) {
-        let pool = usize::try_from(pool_id - self.offset)
+        let index = usize::try_from(pool_id - self.offset)
             .ok()
-            .and_then(|index| self.pools.get_mut(index))
+            .filter(|&index| index < self.pools.len())
             .expect("Invalid pool id");
 
-        let mut raw_sets = raw_sets.into_iter();
-        let mut count = 0;
-        device.dealloc_descriptor_sets(&mut pool.raw, raw_sets.by_ref().inspect(|_| count += 1));
+        let count = raw_sets.len() as u32;
+
+        // Pool that becomes empty is destroyed below if it would end up at the front
+        // of persistent bucket and is not the last one.
+        let destroyed = self.lifetime == DescriptorSetLifetime::Persistent
+            && count == self.pools[index].allocated
+            && index + 1 < self.pools.len()
+            && self
+                .pools
+                .iter()
+                .take(index)
+                .all(|pool| pool.allocated == 0);
+
+        let pool = &mut self.pools[index];
+
+        if destroyed {
+            // Sets are freed with the pool.
+            raw_sets.for_each(drop);
+            pool.available = pool.max_sets;
+        } else if count == pool.allocated
+            && device
+                .capabilities()
+                .contains(DescriptorDeviceCapabilities::RESET_POOL)
+        {
+            // All sets of the pool are freed, resetting is cheaper
+            // and also undoes any fragmentation of the pool.
+            raw_sets.for_each(drop);
+            device.reset_descriptor_pool(&mut pool.raw);
+            pool.available = pool.max_sets;
+        } else {
+            let mut freed = 0;
+            device
+                .dealloc_descriptor_sets(&mut pool.raw, raw_sets.by_ref().inspect(|_| freed += 1));
+
+            debug_assert!(
+                freed == count && raw_sets.next().is_none(),
+                "Device must deallocated all sets from iterator"
+            );
 
-        debug_assert!(
-            raw_sets.next().is_none(),
-            "Device must deallocated all sets from iterator"
-        );
+            pool.available += count;
+        }
 
-        pool.available += count;
         pool.allocated -= count;
         self.total -= count;
+        self.average_live = moving_average(self.average_live, self.total);
         #[cfg(feature = "tracing")]
         tracing::trace!("Freed {} from descriptor bucket", count);
 
+        let info = self.info();
+        listener.emit(|listener| listener.on_sets_freed(&info, pool_id, count));
+
+        if self.lifetime == DescriptorSetLifetime::Transient {
+            // Keep empty pools for reuse until cleanup.
+            return;
+        }
+
         while let Some(pool) = self.pools.pop_front() {
             if self.pools.is_empty() || pool.allocated != 0 {
                 self.pools.push_front(pool);
                 break;
             }
 
-            #[cfg(feature = "tracing")]
-            tracing::trace!("Destroying old descriptor pool");
-
-            device.destroy_descriptor_pool(pool.raw);
-            self.offset += 1;
+            self.destroy_pool(device, pool, listener);
         }
     }
 
-    unsafe fn cleanup<L, S>(&mut self, device: &impl DescriptorDevice<L, P, S>) {
+    /// Destroys empty pools from the front of the queue.
+    /// Returns number of destroyed pools.
+    unsafe fn cleanup<S>(
+        &mut self,
+        device: &impl DescriptorDeviceV2<Pool = P, Set = S>,
+        listener: &mut Listener,
+    ) -> u32 {
+        let mut destroyed = 0;
         while let Some(pool) = self.pools.pop_front() {
             if pool.allocated != 0 {
                 self.pools.push_front(pool);
                 break;
             }
 
-            #[cfg(feature = "tracing")]
-            tracing::trace!("Destroying old descriptor pool");
+            self.destroy_pool(device, pool, listener);
+            destroyed += 1;
+        }
+        destroyed
+    }
 
-            device.destroy_descriptor_pool(pool.raw);
-            self.offset += 1;
+    /// Destroys all pools, including ones with allocated sets.
+    unsafe fn destroy_all<S>(
+        &mut self,
+        device: &impl DescriptorDeviceV2<Pool = P, Set = S>,
+        listener: &mut Listener,
+    ) {
+        while let Some(pool) = self.pools.pop_front() {
+            self.total -= pool.allocated;
+            self.destroy_pool(device, pool, listener);
         }
+        debug_assert_eq!(self.total, 0);
+    }
+
+    /// Destroys pool just popped from the front of the queue.
+    unsafe fn destroy_pool<S>(
+        &mut self,
+        device: &impl DescriptorDeviceV2<Pool = P, Set = S>,
+        pool: DescriptorPool<P>,
+        listener: &mut Listener,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!("Destroying old descriptor pool");
+
+        let pool_info = self.pool_info(&pool, self.offset);
+        device.destroy_descriptor_pool(pool.raw);
+        self.offset += 1;
+
+        let info = self.info();
+        listener.emit(|listener| listener.on_pool_destroyed(&info, &pool_info));
     }
 }
 
+/// Key of the bucket: descriptor counts, update-after-bind and host-only flags and lifetime hint.
+type BucketKey = (DescriptorTotalCount, bool, bool, DescriptorSetLifetime);
+
+/// Orders bucket keys by flags, lifetime hint and then by descriptor counts.
+///
+/// Descriptor counts have no natural order,
+/// they are compared as sequences of non-zero counts of each kind.
+fn cmp_bucket_keys(lhs: &BucketKey, rhs: &BucketKey) -> Ordering {
+    let flags = |key: &BucketKey| (key.1, key.2, key.3 == DescriptorSetLifetime::Transient);
+    flags(lhs)
+        .cmp(&flags(rhs))
+        .then_with(|| lhs.0.iter().cmp(rhs.0.iter()))
+}
+
 /// Descriptor allocator.
 /// Can be used to allocate descriptor sets for any layout.
 #[derive(Debug)]
 pub struct DescriptorAllocator<P, S> {
-    buckets: HashMap<(DescriptorTotalCount, bool), DescriptorBucket<P>>,
+    buckets: HashMap<BucketKey, DescriptorBucket<P>>,
     sets_cache: Vec<DescriptorSet<S>>,
     raw_sets_cache: Vec<S>,
     max_update_after_bind_descriptors_in_all_pools: u32,
     current_update_after_bind_descriptors_in_all_pools: u32,
     total: u32,
+    listener: Listener,
+    out_of_memory_policy: OutOfMemoryPolicy,
+    pool_config: PoolConfig,
+
+    /// Peak numbers of sets of buckets that are imported or no longer exist.
+    profile_peaks: HashMap<BucketKey, u32>,
+
+    /// Incremented when all pools are forgotten or destroyed.
+    /// Sets from previous epochs are ignored when freed.
+    epoch: u64,
 }
 
 impl<P, S> Drop for DescriptorAllocator<P, S> {
@@ -424,44 +802,481 @@ impl<P, S> DescriptorAllocator<P, S> {
             raw_sets_cache: Vec::new(),
             max_update_after_bind_descriptors_in_all_pools,
             current_update_after_bind_descriptors_in_all_pools: 0,
+            listener: Listener::default(),
+            out_of_memory_policy: OutOfMemoryPolicy::default(),
+            pool_config: PoolConfig::default(),
+            profile_peaks: HashMap::default(),
+            epoch: 0,
+        }
+    }
+
+    /// Returns peak numbers of sets observed in buckets,
+    /// including buckets that no longer exist and imported ones.
+    pub fn pool_size_profile(&self) -> PoolSizeProfile {
+        let mut peaks = self.profile_peaks.clone();
+        for (key, bucket) in &self.buckets {
+            let peak = peaks.entry(*key).or_insert(0);
+            *peak = (*peak).max(bucket.peak);
+        }
+
+        let mut entries: Vec<_> = peaks
+            .into_iter()
+            .map(
+                |((size, update_after_bind, host_only, lifetime), peak)| PoolSizeProfileEntry {
+                    size,
+                    update_after_bind,
+                    host_only,
+                    lifetime,
+                    peak,
+                },
+            )
+            .collect();
+
+        entries.sort_unstable_by(|lhs, rhs| {
+            cmp_bucket_keys(
+                &(lhs.size, lhs.update_after_bind, lhs.host_only, lhs.lifetime),
+                &(rhs.size, rhs.update_after_bind, rhs.host_only, rhs.lifetime),
+            )
+        });
+
+        PoolSizeProfile { entries }
+    }
+
+    /// Imports peak numbers of sets, e.g. exported on previous launch.
+    ///
+    /// New pools are sized to cover imported peak of their bucket,
+    /// instead of growing from small pools.
+    /// Imported peaks are merged with ones already known, larger value wins.
+    pub fn import_pool_size_profile(&mut self, profile: &PoolSizeProfile) {
+        for entry in &profile.entries {
+            let key = (
+                entry.size,
+                entry.update_after_bind,
+                entry.host_only,
+                entry.lifetime,
+            );
+            let peak = self.profile_peaks.entry(key).or_insert(0);
+            *peak = (*peak).max(entry.peak);
+
+            if let Some(bucket) = self.buckets.get_mut(&key) {
+                bucket.profile_peak = bucket.profile_peak.max(entry.peak);
+            }
+        }
+    }
+
+    /// Remembers peak number of sets of the bucket that is going to be removed.
+    fn retire_bucket(profile_peaks: &mut HashMap<BucketKey, u32>, key: &BucketKey, peak: u32) {
+        let retired = profile_peaks.entry(*key).or_insert(0);
+        *retired = (*retired).max(peak);
+    }
+
+    /// Discards all pools and buckets without calling the device
+    /// and resets all counters.
+    ///
+    /// Intended for use after device is lost, when pools cannot be destroyed
+    /// and the allocator is going to be used with a new device.
+    /// Pools that are not destroyed by the device are leaked.
+    ///
+    /// Sets allocated before this call are ignored when passed to `free`.
+    pub fn forget_all(&mut self) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!("Forgetting {} descriptor buckets", self.buckets.len());
+
+        for bucket in self.buckets.values_mut() {
+            // Outstanding sets are forgotten on purpose.
+            bucket.total = 0;
+        }
+        self.reset();
+    }
+
+    /// Destroys all pools, including ones with allocated sets,
+    /// and leaves allocator empty and ready for reuse.
+    ///
+    /// Intended for shutdown, as neither `cleanup` nor drop destroys pools with allocated sets.
+    ///
+    /// # Safety
+    ///
+    /// * Same `device` instance must be passed to all method calls of
+    ///   one `DescriptorAllocator` instance.
+    /// * All outstanding sets become invalid.
+    ///   None of them can be referenced in any pending command buffers.
+    ///   They are ignored when passed to `free`.
+    pub unsafe fn destroy_all(&mut self, device: &impl DescriptorDeviceV2<Pool = P, Set = S>) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!("Destroying {} descriptor buckets", self.buckets.len());
+
+        for bucket in self.buckets.values_mut() {
+            bucket.destroy_all(device, &mut self.listener);
+        }
+        self.reset();
+    }
+
+    /// Discards all buckets and starts new epoch.
+    /// Buckets must have no sets accounted.
+    fn reset(&mut self) {
+        let profile_peaks = &mut self.profile_peaks;
+        for (key, bucket) in self.buckets.drain() {
+            Self::retire_bucket(profile_peaks, &key, bucket.peak);
+        }
+        self.sets_cache.clear();
+        self.raw_sets_cache.clear();
+        self.current_update_after_bind_descriptors_in_all_pools = 0;
+        self.total = 0;
+        self.epoch += 1;
+    }
+
+    /// Sets strategy of choosing number of sets for new pools.
+    /// Existing pools are not affected.
+    pub fn set_pool_sizing(&mut self, sizing: PoolSizing) {
+        self.pool_config.sizing = sizing;
+    }
+
+    /// Returns strategy of choosing number of sets for new pools.
+    pub fn pool_sizing(&self) -> PoolSizing {
+        self.pool_config.sizing
+    }
+
+    /// Sets device limits.
+    ///
+    /// New pools are clamped to pool limits and allocation of sets with layouts
+    /// exceeding per-set limits fails with `AllocationError::LimitExceeded`.
+    /// Per-set limits are not checked for update-after-bind layouts,
+    /// as Vulkan applies separate, higher limits to them.
+    /// Existing pools are not affected.
+    pub fn set_device_limits(&mut self, limits: DeviceLimits) {
+        self.pool_config.limits = limits;
+    }
+
+    /// Returns device limits.
+    pub fn device_limits(&self) -> &DeviceLimits {
+        &self.pool_config.limits
+    }
+
+    /// Returns statistics of the allocator.
+    pub fn statistics(&self) -> DescriptorAllocatorStatistics {
+        let mut statistics = DescriptorAllocatorStatistics {
+            buckets: self.buckets.len() as u32,
+            allocated_descriptors: self.total,
+            ..DescriptorAllocatorStatistics::default()
+        };
+
+        for bucket in self.buckets.values() {
+            statistics.allocated_sets += bucket.total;
+            for pool in &bucket.pools {
+                statistics.pools += 1;
+                statistics.reserved_sets += u64::from(pool.max_sets);
+            }
         }
+
+        statistics
+    }
+
+    /// Returns snapshot of all buckets and pools for bug reports.
+    pub fn dump(&self) -> DescriptorAllocatorDump {
+        let mut buckets: Vec<_> = self
+            .buckets
+            .values()
+            .map(|bucket| DescriptorBucketDump {
+                size: bucket.size,
+                update_after_bind: bucket.update_after_bind,
+                host_only: bucket.host_only,
+                lifetime: bucket.lifetime,
+                total: bucket.total,
+                peak: bucket.peak,
+                pool_ids: bucket.offset..bucket.offset + bucket.pools.len() as u64,
+                pools: bucket
+                    .pools
+                    .iter()
+                    .zip(bucket.offset..)
+                    .map(|(pool, pool_id)| DescriptorPoolDump {
+                        pool_id,
+                        max_sets: pool.max_sets,
+                        allocated: pool.allocated,
+                        available: pool.available,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        buckets.sort_unstable_by(|lhs, rhs| {
+            cmp_bucket_keys(
+                &(lhs.size, lhs.update_after_bind, lhs.host_only, lhs.lifetime),
+                &(rhs.size, rhs.update_after_bind, rhs.host_only, rhs.lifetime),
+            )
+        });
+
+        DescriptorAllocatorDump {
+            total_descriptors: self.total,
+            update_after_bind_descriptors: self.current_update_after_bind_descriptors_in_all_pools,
+            max_update_after_bind_descriptors: self.max_update_after_bind_descriptors_in_all_pools,
+            buckets,
+        }
+    }
+
+    /// Returns statistics of each bucket, including sizes chosen for their next pools.
+    pub fn bucket_statistics(&self) -> impl Iterator<Item = DescriptorBucketStatistics> + '_ {
+        let config = &self.pool_config;
+        self.buckets
+            .values()
+            .map(move |bucket| bucket.statistics(config))
+    }
+
+    /// Sets listener that will be notified about allocator activity.
+    /// Replaces previously set listener.
+    pub fn set_listener(&mut self, listener: Box<dyn DescriptorAllocatorListener + Send>) {
+        self.listener.0 = Some(listener);
+    }
+
+    /// Removes listener and returns it.
+    pub fn take_listener(&mut self) -> Option<Box<dyn DescriptorAllocatorListener + Send>> {
+        self.listener.0.take()
+    }
+
+    /// Sets policy of recovery from out-of-memory errors.
+    /// Replaces previously set policy.
+    pub fn set_out_of_memory_policy(&mut self, policy: OutOfMemoryPolicy) {
+        self.out_of_memory_policy = policy;
+    }
+
+    /// Replaces policy of recovery from out-of-memory errors with default one
+    /// and returns previous policy.
+    pub fn take_out_of_memory_policy(&mut self) -> OutOfMemoryPolicy {
+        core::mem::take(&mut self.out_of_memory_policy)
     }
 
     /// Allocate descriptor set with specified layout.
     ///
+    /// If device runs out of memory, allocator tries to recover
+    /// according to `OutOfMemoryPolicy` set with `set_out_of_memory_policy`.
+    ///
     /// # Safety
     ///
     /// * Same `device` instance must be passed to all method calls of
     ///   one `DescriptorAllocator` instance.
     /// * `flags` must match flags that were used to create the layout.
     /// * `layout_descriptor_count` must match descriptor numbers in the layout.
-    pub unsafe fn allocate<L, D>(
+    pub unsafe fn allocate<D>(
         &mut self,
         device: &D,
-        layout: &L,
+        layout: &D::Layout,
         flags: DescriptorSetLayoutCreateFlags,
         layout_descriptor_count: &DescriptorTotalCount,
         count: u32,
     ) -> Result<Vec<DescriptorSet<S>>, AllocationError>
     where
         S: Debug,
-        L: Debug,
-        D: DescriptorDevice<L, P, S>,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        self.allocate_with_lifetime(
+            device,
+            layout,
+            flags,
+            layout_descriptor_count,
+            count,
+            DescriptorSetLifetime::Persistent,
+        )
+    }
+
+    /// Allocate descriptor set with specified layout and lifetime hint.
+    ///
+    /// Sets with different lifetime hints never share pools.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `allocate`.
+    pub unsafe fn allocate_with_lifetime<D>(
+        &mut self,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+        count: u32,
+        lifetime: DescriptorSetLifetime,
+    ) -> Result<Vec<DescriptorSet<S>>, AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
     {
         if count == 0 {
             return Ok(Vec::new());
         }
 
-        let descriptor_count = count * layout_descriptor_count.total();
+        self.allocate_cached(
+            device,
+            layout,
+            flags,
+            layout_descriptor_count,
+            count,
+            lifetime,
+        )?;
+
+        // Keep the cache with its capacity for next calls.
+        let mut sets = Vec::with_capacity(self.sets_cache.len());
+        sets.append(&mut self.sets_cache);
+        Ok(sets)
+    }
+
+    /// Allocate descriptor sets with specified layout and lifetime hint
+    /// into caller-provided collection.
+    ///
+    /// Unlike `allocate` this method does not allocate memory
+    /// once allocator's internal buffers are warmed up.
+    /// `out` is not modified if allocation fails.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `allocate`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn allocate_into<D>(
+        &mut self,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+        count: u32,
+        lifetime: DescriptorSetLifetime,
+        out: &mut impl Extend<DescriptorSet<S>>,
+    ) -> Result<(), AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        self.allocate_cached(
+            device,
+            layout,
+            flags,
+            layout_descriptor_count,
+            count,
+            lifetime,
+        )?;
+        out.extend(self.sets_cache.drain(..));
+        Ok(())
+    }
+
+    /// Allocate one descriptor set with specified layout and lifetime hint.
+    ///
+    /// Unlike `allocate` this method does not allocate memory
+    /// once allocator's internal buffers are warmed up.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `allocate`.
+    pub unsafe fn allocate_one<D>(
+        &mut self,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+        lifetime: DescriptorSetLifetime,
+    ) -> Result<DescriptorSet<S>, AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        self.allocate_cached(device, layout, flags, layout_descriptor_count, 1, lifetime)?;
+        debug_assert_eq!(self.sets_cache.len(), 1);
+        Ok(self.sets_cache.pop().expect("One set must be allocated"))
+    }
+
+    /// Allocates descriptor sets into `sets_cache`.
+    /// Cache is left empty if allocation fails.
+    unsafe fn allocate_cached<D>(
+        &mut self,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+        count: u32,
+        lifetime: DescriptorSetLifetime,
+    ) -> Result<(), AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        debug_assert!(self.sets_cache.is_empty());
+
+        if count == 0 {
+            return Ok(());
+        }
 
         let update_after_bind = flags.contains(DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND);
+        let host_only = flags.contains(DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL);
+
+        if update_after_bind && host_only
+            || flags.contains(DescriptorSetLayoutCreateFlags::DESCRIPTOR_BUFFER)
+        {
+            let info = self.bucket_info(
+                layout_descriptor_count,
+                update_after_bind,
+                host_only,
+                lifetime,
+            );
+            let err = AllocationError::InvalidLayoutFlags;
+            self.listener
+                .emit(|listener| listener.on_error(&info, &err));
+            return Err(err);
+        }
+
+        let descriptor_count = match layout_descriptor_count
+            .checked_mul(count)
+            .and_then(|total_count| total_count.checked_total())
+            .filter(|&descriptor_count| self.total.checked_add(descriptor_count).is_some())
+        {
+            Some(descriptor_count) => descriptor_count,
+            None => {
+                let info = self.bucket_info(
+                    layout_descriptor_count,
+                    update_after_bind,
+                    host_only,
+                    lifetime,
+                );
+                let err = AllocationError::Overflow;
+                self.listener
+                    .emit(|listener| listener.on_error(&info, &err));
+                return Err(err);
+            }
+        };
+
+        let limits = &self.pool_config.limits;
+        let exceeded_limit = if !limits.pool_fits_set(layout_descriptor_count) {
+            Some("max_pool_descriptors")
+        } else {
+            limits.exceeded_set_limit(layout_descriptor_count, update_after_bind)
+        };
+
+        if let Some(limit) = exceeded_limit {
+            let info = self.bucket_info(
+                layout_descriptor_count,
+                update_after_bind,
+                host_only,
+                lifetime,
+            );
+            let err = AllocationError::LimitExceeded(limit);
+            self.listener
+                .emit(|listener| listener.on_error(&info, &err));
+            return Err(err);
+        }
 
         if update_after_bind
             && self.max_update_after_bind_descriptors_in_all_pools
                 - self.current_update_after_bind_descriptors_in_all_pools
                 < descriptor_count
         {
-            return Err(AllocationError::Fragmentation);
+            let info = self.bucket_info(
+                layout_descriptor_count,
+                update_after_bind,
+                host_only,
+                lifetime,
+            );
+            let err = AllocationError::Fragmentation;
+            self.listener
+                .emit(|listener| listener.on_error(&info, &err));
+            return Err(err);
         }
 
         #[cfg(feature = "tracing")]
@@ -472,18 +1287,71 @@ impl<P, S> DescriptorAllocator<P, S> {
             layout_descriptor_count
         );
 
-        let bucket = self
-            .buckets
-            .entry((*layout_descriptor_count, update_after_bind))
-            .or_insert_with(|| DescriptorBucket::new(update_after_bind, *layout_descriptor_count));
-        match bucket.allocate(device, layout, count, &mut self.sets_cache) {
+        let key = (
+            *layout_descriptor_count,
+            update_after_bind,
+            host_only,
+            lifetime,
+        );
+        let epoch = self.epoch;
+        let profile_peak = self.profile_peaks.get(&key).copied().unwrap_or(0);
+        let mut retries = 0;
+
+        let result = loop {
+            let bucket = self.buckets.entry(key).or_insert_with(|| {
+                DescriptorBucket::new(
+                    epoch,
+                    profile_peak,
+                    update_after_bind,
+                    host_only,
+                    lifetime,
+                    *layout_descriptor_count,
+                )
+            });
+
+            // Sets allocated before the failure are kept, only the rest is requested again.
+            let remaining = count - self.sets_cache.len() as u32;
+            match bucket.allocate(
+                device,
+                layout,
+                remaining,
+                &self.pool_config,
+                &mut self.sets_cache,
+                &mut self.listener,
+            ) {
+                Err(
+                    err @ (AllocationError::OutOfDeviceMemory | AllocationError::OutOfHostMemory),
+                ) if retries < self.out_of_memory_policy.retries => {
+                    retries += 1;
+                    if !self.release_memory(device, &err) {
+                        break Err(err);
+                    }
+
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!("Retry allocation after {} ({})", err, retries);
+                }
+                result => break result,
+            }
+        };
+
+        let bucket = self.buckets.entry(key).or_insert_with(|| {
+            DescriptorBucket::new(
+                epoch,
+                profile_peak,
+                update_after_bind,
+                host_only,
+                lifetime,
+                *layout_descriptor_count,
+            )
+        });
+        match result {
             Ok(()) => {
                 self.total += descriptor_count;
                 if update_after_bind {
                     self.current_update_after_bind_descriptors_in_all_pools += descriptor_count;
                 }
 
-                Ok(core::mem::take(&mut self.sets_cache))
+                Ok(())
             }
             Err(err) => {
                 debug_assert!(self.raw_sets_cache.is_empty());
@@ -495,7 +1363,12 @@ impl<P, S> DescriptorAllocator<P, S> {
                     if Some(set.pool_id) != last {
                         if let Some(last_id) = last {
                             // Free contiguous range of sets from one pool in one go.
-                            bucket.free(device, self.raw_sets_cache.drain(..), last_id);
+                            bucket.free(
+                                device,
+                                self.raw_sets_cache.drain(..),
+                                last_id,
+                                &mut self.listener,
+                            );
                         }
                     }
                     last = Some(set.pool_id);
@@ -503,14 +1376,67 @@ impl<P, S> DescriptorAllocator<P, S> {
                 }
 
                 if let Some(last_id) = last {
-                    bucket.free(device, self.raw_sets_cache.drain(..), last_id);
+                    bucket.free(
+                        device,
+                        self.raw_sets_cache.drain(..),
+                        last_id,
+                        &mut self.listener,
+                    );
                 }
 
+                let info = bucket.info();
+                self.listener
+                    .emit(|listener| listener.on_error(&info, &err));
+
                 Err(err)
             }
         }
     }
 
+    /// Returns info of the bucket with specified key, even if it does not exist yet.
+    fn bucket_info(
+        &self,
+        size: &DescriptorTotalCount,
+        update_after_bind: bool,
+        host_only: bool,
+        lifetime: DescriptorSetLifetime,
+    ) -> BucketInfo {
+        match self
+            .buckets
+            .get(&(*size, update_after_bind, host_only, lifetime))
+        {
+            Some(bucket) => bucket.info(),
+            None => BucketInfo {
+                size: *size,
+                update_after_bind,
+                host_only,
+                lifetime,
+                total: 0,
+                pools: 0,
+            },
+        }
+    }
+
+    /// Releases memory according to out-of-memory policy.
+    /// Returns `true` if anything was released and allocation may be retried.
+    unsafe fn release_memory(
+        &mut self,
+        device: &impl DescriptorDeviceV2<Pool = P, Set = S>,
+        err: &AllocationError,
+    ) -> bool {
+        let mut released = false;
+
+        if self.out_of_memory_policy.cleanup {
+            released |= self.cleanup_buckets(device) > 0;
+        }
+
+        if let Some(release_memory) = &mut self.out_of_memory_policy.release_memory {
+            released |= release_memory(err);
+        }
+
+        released
+    }
+
     /// Free descriptor sets.
     ///
     /// # Safety
@@ -520,31 +1446,40 @@ impl<P, S> DescriptorAllocator<P, S> {
     /// * None of descriptor sets can be referenced in any pending command buffers.
     /// * All command buffers where at least one of descriptor sets referenced
     ///   move to invalid state.
-    pub unsafe fn free<L, D, I>(&mut self, device: &D, sets: I)
+    ///
+    /// Sets allocated before `forget_all` or `destroy_all` call are ignored.
+    pub unsafe fn free<D, I>(&mut self, device: &D, sets: I)
     where
-        D: DescriptorDevice<L, P, S>,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
         I: IntoIterator<Item = DescriptorSet<S>>,
     {
         debug_assert!(self.raw_sets_cache.is_empty());
 
-        let mut last_key = (EMPTY_COUNT, false);
+        let mut last_key = (EMPTY_COUNT, false, false, DescriptorSetLifetime::Persistent);
         let mut last_pool_id = None;
 
         let mut descriptor_count = 0;
 
         // Batch freeing of adjacent descriptor sets that belong to the same bucket and pool.
         for set in sets {
-            descriptor_count += set.size.total();
+            if set.epoch != self.epoch {
+                // Pool of the set was forgotten or destroyed.
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Ignoring descriptor set from previous epoch");
+                continue;
+            }
 
-            if last_key != (set.size, set.update_after_bind) || last_pool_id != Some(set.pool_id) {
+            let key = (set.size, set.update_after_bind, set.host_only, set.lifetime);
+            if last_key != key || last_pool_id != Some(set.pool_id) {
                 if let Some(pool_id) = last_pool_id {
                     self.free_raw_sets_cache(device, &last_key, pool_id, descriptor_count);
                     descriptor_count = 0;
                 }
 
-                last_key = (set.size, set.update_after_bind);
+                last_key = key;
                 last_pool_id = Some(set.pool_id);
             }
+            descriptor_count += set.size.total();
             self.raw_sets_cache.push(set.raw);
         }
 
@@ -553,15 +1488,46 @@ impl<P, S> DescriptorAllocator<P, S> {
         }
     }
 
+    /// Free descriptor sets in any order.
+    ///
+    /// Unlike `free`, which batches only adjacent sets from the same pool,
+    /// this method groups all sets by bucket and pool first,
+    /// so device is called once per pool at the cost of sorting.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `free`.
+    pub unsafe fn free_unordered<D, I>(&mut self, device: &D, sets: I)
+    where
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+        I: IntoIterator<Item = DescriptorSet<S>>,
+    {
+        // Reuse allocation cache, it is always empty between calls.
+        let mut sorted = core::mem::take(&mut self.sets_cache);
+        debug_assert!(sorted.is_empty());
+
+        sorted.extend(sets);
+        sorted.sort_unstable_by(|lhs, rhs| {
+            cmp_bucket_keys(
+                &(lhs.size, lhs.update_after_bind, lhs.host_only, lhs.lifetime),
+                &(rhs.size, rhs.update_after_bind, rhs.host_only, rhs.lifetime),
+            )
+            .then(lhs.pool_id.cmp(&rhs.pool_id))
+        });
+
+        self.free(device, sorted.drain(..));
+        self.sets_cache = sorted;
+    }
+
     /// Frees the cached descriptor sets which must be allocated from the same bucket and pool.
-    unsafe fn free_raw_sets_cache<L, D>(
+    unsafe fn free_raw_sets_cache<D>(
         &mut self,
         device: &D,
-        bucket_key: &(DescriptorTotalCount, bool),
+        bucket_key: &BucketKey,
         pool_id: u64,
         descriptor_count: u32,
     ) where
-        D: DescriptorDevice<L, P, S>,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
     {
         let bucket = self
             .buckets
@@ -572,7 +1538,12 @@ impl<P, S> DescriptorAllocator<P, S> {
             .ok()
             .is_some_and(|count| count <= bucket.total));
 
-        bucket.free(device, self.raw_sets_cache.drain(..), pool_id);
+        bucket.free(
+            device,
+            self.raw_sets_cache.drain(..),
+            pool_id,
+            &mut self.listener,
+        );
 
         self.total -= descriptor_count;
         if bucket.update_after_bind {
@@ -586,34 +1557,130 @@ impl<P, S> DescriptorAllocator<P, S> {
     ///
     /// * Same `device` instance must be passed to all method calls of
     ///   one `DescriptorAllocator` instance.
-    pub unsafe fn cleanup<L>(&mut self, device: &impl DescriptorDevice<L, P, S>) {
+    pub unsafe fn cleanup(&mut self, device: &impl DescriptorDeviceV2<Pool = P, Set = S>) {
+        self.cleanup_buckets(device);
+    }
+
+    /// Destroys empty pools of all buckets and returns number of destroyed pools.
+    unsafe fn cleanup_buckets(
+        &mut self,
+        device: &impl DescriptorDeviceV2<Pool = P, Set = S>,
+    ) -> u32 {
+        let mut destroyed = 0;
         for bucket in self.buckets.values_mut() {
-            bucket.cleanup(device)
+            destroyed += bucket.cleanup(device, &mut self.listener);
+        }
+        let profile_peaks = &mut self.profile_peaks;
+        self.buckets.retain(|key, bucket| {
+            if bucket.pools.is_empty() {
+                Self::retire_bucket(profile_peaks, key, bucket.peak);
+                false
+            } else {
+                true
+            }
+        });
+        destroyed
+    }
+
+    /// Checks consistency of allocator's internal bookkeeping.
+    ///
+    /// Intended for tests and fuzzing.
+    /// Walks every bucket and pool, so it should not be called on hot paths.
+    /// Available only in debug builds or with `mock` feature enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any invariant is violated.
+    #[cfg(any(debug_assertions, feature = "mock"))]
+    pub fn check_invariants(&self) {
+        assert!(self.sets_cache.is_empty(), "Sets cache must be empty");
+        assert!(
+            self.raw_sets_cache.is_empty(),
+            "Raw sets cache must be empty"
+        );
+
+        let mut total = 0u64;
+        let mut update_after_bind_total = 0u64;
+
+        for (&(size, update_after_bind, host_only, lifetime), bucket) in &self.buckets {
+            assert_eq!(bucket.size, size, "Bucket size must match its key");
+            assert_eq!(
+                bucket.update_after_bind, update_after_bind,
+                "Bucket update-after-bind flag must match its key"
+            );
+            assert_eq!(
+                bucket.host_only, host_only,
+                "Bucket host-only flag must match its key"
+            );
+            assert_eq!(
+                bucket.lifetime, lifetime,
+                "Bucket lifetime must match its key"
+            );
+            assert_eq!(
+                bucket.epoch, self.epoch,
+                "Buckets of previous epochs must be discarded"
+            );
+            assert!(
+                bucket.total <= bucket.peak,
+                "Bucket peak must not be below total"
+            );
+
+            let mut allocated = 0u64;
+            for pool in &bucket.pools {
+                assert!(
+                    u64::from(pool.allocated) + u64::from(pool.available)
+                        <= u64::from(pool.max_sets),
+                    "Pool must not have more sets than it was created with"
+                );
+                allocated += u64::from(pool.allocated);
+            }
+
+            assert_eq!(
+                allocated,
+                u64::from(bucket.total),
+                "Bucket total must be equal to sum of sets allocated from its pools"
+            );
+            assert!(
+                bucket
+                    .offset
+                    .checked_add(bucket.pools.len() as u64)
+                    .is_some(),
+                "Pool ids must not overflow"
+            );
+
+            let descriptors = allocated * u64::from(size.total());
+            total += descriptors;
+            if update_after_bind {
+                update_after_bind_total += descriptors;
+            }
         }
-        self.buckets.retain(|_, bucket| !bucket.pools.is_empty());
+
+        assert_eq!(
+            total,
+            u64::from(self.total),
+            "Allocator total must be equal to sum of descriptors allocated from all buckets"
+        );
+        assert_eq!(
+            update_after_bind_total,
+            u64::from(self.current_update_after_bind_descriptors_in_all_pools),
+            "Update-after-bind total must be equal to sum of descriptors allocated from update-after-bind buckets"
+        );
+        assert!(
+            self.current_update_after_bind_descriptors_in_all_pools
+                <= self.max_update_after_bind_descriptors_in_all_pools,
+            "Update-after-bind descriptors must not exceed the limit"
+        );
     }
 }
 
 /// Empty descriptor per_type.
-const EMPTY_COUNT: DescriptorTotalCount = DescriptorTotalCount {
-    sampler: 0,
-    combined_image_sampler: 0,
-    sampled_image: 0,
-    storage_image: 0,
-    uniform_texel_buffer: 0,
-    storage_texel_buffer: 0,
-    uniform_buffer: 0,
-    storage_buffer: 0,
-    uniform_buffer_dynamic: 0,
-    storage_buffer_dynamic: 0,
-    input_attachment: 0,
-    acceleration_structure: 0,
-    inline_uniform_block_bytes: 0,
-    inline_uniform_block_bindings: 0,
-};
+const EMPTY_COUNT: DescriptorTotalCount = DescriptorTotalCount::new();
 
 struct Allocation<'a, S> {
+    epoch: u64,
     update_after_bind: bool,
+    host_only: bool,
+    lifetime: DescriptorSetLifetime,
     size: DescriptorTotalCount,
     pool_id: u64,
     sets: &'a mut Vec<DescriptorSet<S>>,
@@ -622,12 +1689,18 @@ struct Allocation<'a, S> {
 impl<S> Extend<S> for Allocation<'_, S> {
     fn extend<T: IntoIterator<Item = S>>(&mut self, iter: T) {
         let update_after_bind = self.update_after_bind;
+        let host_only = self.host_only;
+        let lifetime = self.lifetime;
         let size = self.size;
         let pool_id = self.pool_id;
+        let epoch = self.epoch;
         self.sets.extend(iter.into_iter().map(|raw| DescriptorSet {
             raw,
             pool_id,
+            epoch,
             update_after_bind,
+            host_only,
+            lifetime,
             size,
         }))
     }
@@ -0,0 +1,287 @@
+use {
+    crate::allocator::{AllocationError, DescriptorAllocator, DescriptorSet},
+    alloc::{collections::VecDeque, vec::Vec},
+    core::fmt::{self, Debug, Display},
+    gpu_descriptor_types::{
+        DescriptorDeviceV2, DescriptorSetLayoutCreateFlags, DescriptorTotalCount, DescriptorType,
+    },
+};
+
+/// Handle to one array element of the bindless heap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BindlessSlot {
+    ty: DescriptorType,
+    index: u32,
+    generation: u32,
+}
+
+impl BindlessSlot {
+    /// Returns type of descriptor stored in the slot.
+    pub fn ty(&self) -> DescriptorType {
+        self.ty
+    }
+
+    /// Returns index of the slot in descriptor array.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns generation of the slot.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// Error that may occur during bindless slot allocation or deallocation.
+#[derive(Debug)]
+pub enum BindlessError {
+    /// Heap has no array for descriptors of requested type.
+    UnknownType,
+
+    /// All slots of the array are in use or awaiting reuse.
+    OutOfSlots,
+
+    /// Slot was already freed.
+    StaleSlot,
+}
+
+impl Display for BindlessError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindlessError::UnknownType => fmt.write_str("Unknown descriptor type"),
+            BindlessError::OutOfSlots => fmt.write_str("Out of slots"),
+            BindlessError::StaleSlot => fmt.write_str("Stale slot"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BindlessError {}
+
+/// Statistics of one descriptor array of bindless heap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BindlessStatistics {
+    /// Number of slots in the array.
+    pub capacity: u32,
+
+    /// Number of slots currently in use.
+    pub allocated: u32,
+
+    /// Number of freed slots that are not yet available for reuse.
+    pub pending: u32,
+}
+
+#[derive(Debug)]
+struct SlotArray {
+    ty: DescriptorType,
+    capacity: u32,
+
+    /// Current generation of each slot ever allocated.
+    generations: Vec<u32>,
+
+    /// Slots available for reuse.
+    free: Vec<u32>,
+
+    /// Freed slots with frame they were freed in.
+    pending: VecDeque<(u64, u32)>,
+
+    allocated: u32,
+}
+
+/// Descriptor types that can form arrays of bindless heap.
+const ARRAY_TYPES: [DescriptorType; 12] = [
+    DescriptorType::Sampler,
+    DescriptorType::CombinedImageSampler,
+    DescriptorType::SampledImage,
+    DescriptorType::StorageImage,
+    DescriptorType::UniformTexelBuffer,
+    DescriptorType::StorageTexelBuffer,
+    DescriptorType::UniformBuffer,
+    DescriptorType::StorageBuffer,
+    DescriptorType::UniformBufferDynamic,
+    DescriptorType::StorageBufferDynamic,
+    DescriptorType::InputAttachment,
+    DescriptorType::AccelerationStructure,
+];
+
+fn array_capacity(count: &DescriptorTotalCount, ty: DescriptorType) -> u32 {
+    match ty {
+        // Inline uniform blocks cannot be arrayed.
+        DescriptorType::InlineUniformBlock => 0,
+        _ => count.get(ty.into()),
+    }
+}
+
+/// Global descriptor heap for bindless rendering.
+///
+/// Owns one large descriptor set allocated from `DescriptorAllocator`
+/// and hands out indices into its descriptor arrays, one array per descriptor type.
+/// Freed indices are reused only after the frame they were freed in is completed.
+#[derive(Debug)]
+pub struct BindlessHeap<S> {
+    set: DescriptorSet<S>,
+    arrays: Vec<SlotArray>,
+    frame: u64,
+}
+
+impl<S> BindlessHeap<S> {
+    /// Allocates heap's descriptor set from the allocator.
+    ///
+    /// Layout must have one array binding per descriptor type.
+    /// Size of each array is taken from `layout_descriptor_count`.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::allocate`.
+    pub unsafe fn new<P, D>(
+        allocator: &mut DescriptorAllocator<P, S>,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+    ) -> Result<Self, AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        let mut sets = allocator.allocate(device, layout, flags, layout_descriptor_count, 1)?;
+        let set = sets.pop().expect("One set must be allocated");
+
+        let arrays = ARRAY_TYPES
+            .iter()
+            .filter_map(|&ty| {
+                let capacity = array_capacity(layout_descriptor_count, ty);
+                if capacity == 0 {
+                    return None;
+                }
+                Some(SlotArray {
+                    ty,
+                    capacity,
+                    generations: Vec::new(),
+                    free: Vec::new(),
+                    pending: VecDeque::new(),
+                    allocated: 0,
+                })
+            })
+            .collect();
+
+        Ok(BindlessHeap {
+            set,
+            arrays,
+            frame: 0,
+        })
+    }
+
+    /// Returns reference to raw descriptor set of the heap.
+    pub fn raw(&self) -> &S {
+        self.set.raw()
+    }
+
+    /// Returns current frame number.
+    /// Slots freed now are tagged with this number.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Advances heap to the next frame.
+    ///
+    /// Slots freed during frames up to and including `completed_frame`
+    /// become available for reuse.
+    pub fn advance(&mut self, completed_frame: u64) {
+        self.frame += 1;
+
+        for array in &mut self.arrays {
+            while let Some(&(frame, index)) = array.pending.front() {
+                if frame > completed_frame {
+                    break;
+                }
+                array.pending.pop_front();
+                array.free.push(index);
+            }
+        }
+    }
+
+    /// Allocates a slot in descriptor array of specified type.
+    pub fn allocate(&mut self, ty: DescriptorType) -> Result<BindlessSlot, BindlessError> {
+        let array = self.array_mut(ty)?;
+
+        let index = match array.free.pop() {
+            Some(index) => index,
+            None => {
+                let index = array.generations.len() as u32;
+                if index == array.capacity {
+                    return Err(BindlessError::OutOfSlots);
+                }
+                array.generations.push(0);
+                index
+            }
+        };
+
+        array.allocated += 1;
+
+        Ok(BindlessSlot {
+            ty,
+            index,
+            generation: array.generations[index as usize],
+        })
+    }
+
+    /// Frees the slot.
+    /// Slot will be reused after current frame is completed.
+    pub fn free(&mut self, slot: BindlessSlot) -> Result<(), BindlessError> {
+        let frame = self.frame;
+        let array = self.array_mut(slot.ty)?;
+
+        match array.generations.get_mut(slot.index as usize) {
+            Some(generation) if *generation == slot.generation => {
+                *generation = generation.wrapping_add(1);
+            }
+            _ => return Err(BindlessError::StaleSlot),
+        }
+
+        array.allocated -= 1;
+        array.pending.push_back((frame, slot.index));
+        Ok(())
+    }
+
+    /// Returns `true` if slot is allocated from this heap and not freed yet.
+    pub fn is_valid(&self, slot: BindlessSlot) -> bool {
+        self.arrays
+            .iter()
+            .find(|array| array.ty == slot.ty)
+            .and_then(|array| array.generations.get(slot.index as usize))
+            .is_some_and(|&generation| generation == slot.generation)
+    }
+
+    /// Returns statistics of descriptor array of specified type.
+    pub fn statistics(&self, ty: DescriptorType) -> BindlessStatistics {
+        match self.arrays.iter().find(|array| array.ty == ty) {
+            Some(array) => BindlessStatistics {
+                capacity: array.capacity,
+                allocated: array.allocated,
+                pending: array.pending.len() as u32,
+            },
+            None => BindlessStatistics::default(),
+        }
+    }
+
+    /// Returns heap's descriptor set back to the allocator.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::free`.
+    pub unsafe fn destroy<P, D>(self, allocator: &mut DescriptorAllocator<P, S>, device: &D)
+    where
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        allocator.free(device, Some(self.set));
+    }
+
+    fn array_mut(&mut self, ty: DescriptorType) -> Result<&mut SlotArray, BindlessError> {
+        self.arrays
+            .iter_mut()
+            .find(|array| array.ty == ty)
+            .ok_or(BindlessError::UnknownType)
+    }
+}
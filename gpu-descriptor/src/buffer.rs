@@ -0,0 +1,733 @@
+use {
+    crate::{
+        allocator::{MAX_SETS, MIN_SETS},
+        listener::{
+            DescriptorBufferAllocatorListener, DescriptorBufferBucketInfo, DescriptorBufferInfo,
+            Listener,
+        },
+    },
+    alloc::{boxed::Box, collections::VecDeque, vec::Vec},
+    core::{
+        convert::TryFrom as _,
+        fmt::{self, Debug, Display},
+    },
+    gpu_descriptor_types::{CreateBufferError, DescriptorBufferDevice, DescriptorBufferUsageFlags},
+    hashbrown::HashMap,
+};
+
+/// Range of descriptor buffer that holds one descriptor set.
+#[derive(Debug)]
+pub struct DescriptorBufferAllocation<B> {
+    buffer: B,
+    buffer_id: u64,
+    offset: u64,
+    stride: u64,
+    size: u64,
+    usage: DescriptorBufferUsageFlags,
+}
+
+impl<B> DescriptorBufferAllocation<B> {
+    /// Returns reference to raw descriptor buffer.
+    pub fn buffer(&self) -> &B {
+        &self.buffer
+    }
+
+    /// Returns offset of the descriptor set within descriptor buffer.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns number of bytes descriptor set occupies.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns offset of the binding within descriptor buffer.
+    ///
+    /// # Safety
+    ///
+    /// * `layout` must be the layout this range was allocated with.
+    /// * Layout must have specified binding.
+    pub unsafe fn binding_offset<L>(
+        &self,
+        device: &impl DescriptorBufferDevice<L, B>,
+        layout: &L,
+        binding: u32,
+    ) -> u64 {
+        self.offset + device.get_descriptor_set_layout_binding_offset(layout, binding)
+    }
+}
+
+/// Error that may occur during descriptor buffer ranges allocation.
+#[derive(Debug)]
+pub enum BufferAllocationError {
+    /// Backend reported that device memory has been exhausted.\
+    /// Deallocating device memory or other resources may increase chance
+    /// that another allocation would succeed.
+    OutOfDeviceMemory,
+
+    /// Backend reported that host memory has been exhausted.\
+    /// Deallocating host memory may increase chance that another allocation would succeed.
+    OutOfHostMemory,
+
+    /// Descriptor buffer cannot be bound to memory of the type the device allocates.
+    IncompatibleMemoryType,
+
+    /// Size in bytes of requested ranges or of all allocated ranges does not fit in `u64`.
+    Overflow,
+}
+
+impl Display for BufferAllocationError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferAllocationError::OutOfDeviceMemory => fmt.write_str("Device memory exhausted"),
+            BufferAllocationError::OutOfHostMemory => fmt.write_str("Host memory exhausted"),
+            BufferAllocationError::IncompatibleMemoryType => {
+                fmt.write_str("Memory type is not suitable for descriptor buffers")
+            }
+            BufferAllocationError::Overflow => fmt.write_str("Descriptor buffer size overflow"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BufferAllocationError {}
+
+impl From<CreateBufferError> for BufferAllocationError {
+    fn from(err: CreateBufferError) -> Self {
+        match err {
+            CreateBufferError::OutOfDeviceMemory => BufferAllocationError::OutOfDeviceMemory,
+            CreateBufferError::OutOfHostMemory => BufferAllocationError::OutOfHostMemory,
+            CreateBufferError::IncompatibleMemoryType => {
+                BufferAllocationError::IncompatibleMemoryType
+            }
+        }
+    }
+}
+
+/// Statistics of descriptor buffer allocator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DescriptorBufferStatistics {
+    /// Number of descriptor buffers currently alive.
+    pub buffers: u32,
+
+    /// Number of descriptor sets currently allocated.
+    pub allocated_sets: u32,
+
+    /// Number of bytes occupied by allocated descriptor sets, including alignment padding.
+    pub allocated_bytes: u64,
+
+    /// Total size of all descriptor buffers.
+    pub reserved_bytes: u64,
+}
+
+/// Statistics of one descriptor buffer bucket.
+///
+/// Buckets group ranges with equal stride and usage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorBufferBucketStatistics {
+    /// Number of bytes each range of the bucket occupies, including alignment padding.
+    pub stride: u64,
+
+    /// Usage of descriptor buffers of the bucket.
+    pub usage: DescriptorBufferUsageFlags,
+
+    /// Number of descriptor buffers currently owned by the bucket.
+    pub buffers: u32,
+
+    /// Number of ranges currently allocated from the bucket.
+    pub allocated_sets: u32,
+
+    /// Maximum number of ranges allocated from the bucket at once.
+    pub peak_sets: u32,
+
+    /// Total size of descriptor buffers of the bucket.
+    pub reserved_bytes: u64,
+}
+
+#[derive(Debug)]
+struct DescriptorBuffer<B> {
+    raw: B,
+
+    /// Slots that were allocated and freed since.
+    free: Vec<u32>,
+
+    /// Slots starting from this one were never allocated.
+    next: u32,
+
+    /// Total number of slots in the buffer.
+    capacity: u32,
+
+    /// Number of sets allocated from buffer.
+    allocated: u32,
+}
+
+#[derive(Debug)]
+struct DescriptorBufferBucket<B> {
+    offset: u64,
+    buffers: VecDeque<DescriptorBuffer<B>>,
+    total: u32,
+    peak: u32,
+    stride: u64,
+    usage: DescriptorBufferUsageFlags,
+}
+
+impl<B> Drop for DescriptorBufferBucket<B> {
+    #[cfg(feature = "tracing")]
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        {
+            if std::thread::panicking() {
+                return;
+            }
+        }
+        if self.total > 0 {
+            tracing::error!("Descriptor buffer ranges were not deallocated");
+        }
+    }
+
+    #[cfg(all(not(feature = "tracing"), feature = "std"))]
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        if self.total > 0 {
+            eprintln!("Descriptor buffer ranges were not deallocated")
+        }
+    }
+
+    #[cfg(all(not(feature = "tracing"), not(feature = "std")))]
+    fn drop(&mut self) {
+        if self.total > 0 {
+            panic!("Descriptor buffer ranges were not deallocated")
+        }
+    }
+}
+
+impl<B> DescriptorBufferBucket<B> {
+    fn new(stride: u64, usage: DescriptorBufferUsageFlags) -> Self {
+        DescriptorBufferBucket {
+            offset: 0,
+            buffers: VecDeque::new(),
+            total: 0,
+            peak: 0,
+            stride,
+            usage,
+        }
+    }
+
+    fn info(&self) -> DescriptorBufferBucketInfo {
+        DescriptorBufferBucketInfo {
+            stride: self.stride,
+            usage: self.usage,
+            total: self.total,
+            buffers: self.buffers.len() as u32,
+        }
+    }
+
+    fn buffer_info(&self, buffer: &DescriptorBuffer<B>, buffer_id: u64) -> DescriptorBufferInfo {
+        DescriptorBufferInfo {
+            buffer_id,
+            capacity: buffer.capacity,
+            size: u64::from(buffer.capacity) * self.stride,
+        }
+    }
+
+    fn statistics(&self) -> DescriptorBufferBucketStatistics {
+        DescriptorBufferBucketStatistics {
+            stride: self.stride,
+            usage: self.usage,
+            buffers: self.buffers.len() as u32,
+            allocated_sets: self.total,
+            peak_sets: self.peak,
+            reserved_bytes: self
+                .buffers
+                .iter()
+                .map(|buffer| u64::from(buffer.capacity) * self.stride)
+                .sum(),
+        }
+    }
+
+    fn new_buffer_capacity(&self, minimal_set_count: u32) -> u32 {
+        let max_sets = MIN_SETS // at least MIN_SETS
+            .max(minimal_set_count) // at least enough for allocation
+            .max(self.total.min(MAX_SETS)) // at least as much as was allocated so far capped to MAX_SETS
+            .checked_next_power_of_two() // rounded up to nearest 2^N
+            .unwrap_or(i32::MAX as u32);
+
+        u32::try_from(u64::MAX / self.stride)
+            .unwrap_or(u32::MAX)
+            .min(max_sets)
+    }
+
+    unsafe fn allocate<L>(
+        &mut self,
+        device: &impl DescriptorBufferDevice<L, B>,
+        size: u64,
+        count: u32,
+        allocations: &mut Vec<DescriptorBufferAllocation<B>>,
+        listener: &mut Listener<dyn DescriptorBufferAllocatorListener + Send>,
+    ) -> Result<(), BufferAllocationError>
+    where
+        B: Clone,
+    {
+        if count == 0 {
+            return Ok(());
+        }
+
+        let result = self.allocate_ranges(device, size, count, allocations, listener);
+        self.peak = self.peak.max(self.total);
+        result
+    }
+
+    unsafe fn allocate_ranges<L>(
+        &mut self,
+        device: &impl DescriptorBufferDevice<L, B>,
+        size: u64,
+        mut count: u32,
+        allocations: &mut Vec<DescriptorBufferAllocation<B>>,
+        listener: &mut Listener<dyn DescriptorBufferAllocatorListener + Send>,
+    ) -> Result<(), BufferAllocationError>
+    where
+        B: Clone,
+    {
+        for index in (0..self.buffers.len()).rev() {
+            let buffer = &mut self.buffers[index];
+            let available = buffer.capacity - buffer.allocated;
+            if available == 0 {
+                continue;
+            }
+
+            let allocate = available.min(count);
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                "Allocate `{}` sets from exising descriptor buffer",
+                allocate
+            );
+
+            for _ in 0..allocate {
+                let slot = match buffer.free.pop() {
+                    Some(slot) => slot,
+                    None => {
+                        let slot = buffer.next;
+                        buffer.next += 1;
+                        slot
+                    }
+                };
+
+                allocations.push(DescriptorBufferAllocation {
+                    buffer: buffer.raw.clone(),
+                    buffer_id: index as u64 + self.offset,
+                    offset: u64::from(slot) * self.stride,
+                    stride: self.stride,
+                    size,
+                    usage: self.usage,
+                });
+            }
+
+            count -= allocate;
+            buffer.allocated += allocate;
+            self.total += allocate;
+
+            let buffer_id = index as u64 + self.offset;
+            let info = self.info();
+            listener.emit(|listener| listener.on_ranges_allocated(&info, buffer_id, allocate));
+
+            if count == 0 {
+                return Ok(());
+            }
+        }
+
+        while count > 0 {
+            let capacity = self.new_buffer_capacity(count);
+            let buffer_size = u64::from(capacity) * self.stride;
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                "Create new descriptor buffer with {} sets and {} bytes",
+                capacity,
+                buffer_size,
+            );
+
+            let raw = device.create_descriptor_buffer(buffer_size, self.usage)?;
+            let buffer_id = self.buffers.len() as u64 + self.offset;
+
+            let allocate = capacity.min(count);
+            allocations.extend((0..allocate).map(|slot| DescriptorBufferAllocation {
+                buffer: raw.clone(),
+                buffer_id,
+                offset: u64::from(slot) * self.stride,
+                stride: self.stride,
+                size,
+                usage: self.usage,
+            }));
+
+            count -= allocate;
+            self.buffers.push_back(DescriptorBuffer {
+                raw,
+                free: Vec::new(),
+                next: allocate,
+                capacity,
+                allocated: allocate,
+            });
+            self.total += allocate;
+
+            let buffer_info = DescriptorBufferInfo {
+                buffer_id,
+                capacity,
+                size: buffer_size,
+            };
+            let info = self.info();
+            listener.emit(|listener| {
+                listener.on_buffer_created(&info, &buffer_info);
+                listener.on_ranges_allocated(&info, buffer_id, allocate);
+            });
+        }
+
+        Ok(())
+    }
+
+    unsafe fn free<L>(
+        &mut self,
+        device: &impl DescriptorBufferDevice<L, B>,
+        slots: impl IntoIterator<Item = u32>,
+        buffer_id: u64,
+        listener: &mut Listener<dyn DescriptorBufferAllocatorListener + Send>,
+    ) {
+        let buffer = usize::try_from(buffer_id - self.offset)
+            .ok()
+            .and_then(|index| self.buffers.get_mut(index))
+            .expect("Invalid buffer id");
+
+        let before = buffer.free.len();
+        buffer.free.extend(slots);
+        let count = (buffer.free.len() - before) as u32;
+
+        debug_assert!(count <= buffer.allocated);
+
+        buffer.allocated -= count;
+        self.total -= count;
+        #[cfg(feature = "tracing")]
+        tracing::trace!("Freed {} from descriptor buffer bucket", count);
+
+        let info = self.info();
+        listener.emit(|listener| listener.on_ranges_freed(&info, buffer_id, count));
+
+        while let Some(buffer) = self.buffers.pop_front() {
+            if self.buffers.is_empty() || buffer.allocated != 0 {
+                self.buffers.push_front(buffer);
+                break;
+            }
+
+            self.destroy_buffer(device, buffer, listener);
+        }
+    }
+
+    unsafe fn cleanup<L>(
+        &mut self,
+        device: &impl DescriptorBufferDevice<L, B>,
+        listener: &mut Listener<dyn DescriptorBufferAllocatorListener + Send>,
+    ) {
+        while let Some(buffer) = self.buffers.pop_front() {
+            if buffer.allocated != 0 {
+                self.buffers.push_front(buffer);
+                break;
+            }
+
+            self.destroy_buffer(device, buffer, listener);
+        }
+    }
+
+    /// Destroys buffer just popped from the front of the queue.
+    unsafe fn destroy_buffer<L>(
+        &mut self,
+        device: &impl DescriptorBufferDevice<L, B>,
+        buffer: DescriptorBuffer<B>,
+        listener: &mut Listener<dyn DescriptorBufferAllocatorListener + Send>,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!("Destroying old descriptor buffer");
+
+        let buffer_info = self.buffer_info(&buffer, self.offset);
+        device.destroy_descriptor_buffer(buffer.raw);
+        self.offset += 1;
+
+        let info = self.info();
+        listener.emit(|listener| listener.on_buffer_destroyed(&info, &buffer_info));
+    }
+}
+
+/// Descriptor buffer allocator.
+/// Sub-allocates ranges for descriptor sets of any layout from large descriptor buffers.
+///
+/// Sets with equally sized layouts share buckets,
+/// each bucket carves its buffers into fixed-size aligned slots.
+#[derive(Debug)]
+pub struct DescriptorBufferAllocator<B> {
+    buckets: HashMap<(u64, DescriptorBufferUsageFlags), DescriptorBufferBucket<B>>,
+    slots_cache: Vec<u32>,
+    total: u64,
+    listener: Listener<dyn DescriptorBufferAllocatorListener + Send>,
+}
+
+impl<B> Drop for DescriptorBufferAllocator<B> {
+    fn drop(&mut self) {
+        if self.buckets.drain().any(|(_, bucket)| bucket.total != 0) {
+            #[cfg(feature = "tracing")]
+            tracing::error!(
+                "`DescriptorBufferAllocator` is dropped while some descriptor buffer ranges were not deallocated"
+            );
+        }
+    }
+}
+
+impl<B> Default for DescriptorBufferAllocator<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> DescriptorBufferAllocator<B> {
+    /// Create new allocator instance.
+    pub fn new() -> Self {
+        DescriptorBufferAllocator {
+            buckets: HashMap::default(),
+            slots_cache: Vec::new(),
+            total: 0,
+            listener: Listener::default(),
+        }
+    }
+
+    /// Allocate ranges for descriptor sets with specified layout.
+    ///
+    /// # Safety
+    ///
+    /// * Same `device` instance must be passed to all method calls of
+    ///   one `DescriptorBufferAllocator` instance.
+    /// * `layout` must be created for use with descriptor buffers.
+    /// * `usage` must allow all descriptor types used in the layout.
+    pub unsafe fn allocate<L, D>(
+        &mut self,
+        device: &D,
+        layout: &L,
+        usage: DescriptorBufferUsageFlags,
+        count: u32,
+    ) -> Result<Vec<DescriptorBufferAllocation<B>>, BufferAllocationError>
+    where
+        B: Clone,
+        L: Debug,
+        D: DescriptorBufferDevice<L, B>,
+    {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let size = device.get_descriptor_set_layout_size(layout);
+        let alignment = device.descriptor_buffer_offset_alignment().max(1);
+        let stride = match size.max(1).checked_add(alignment - 1) {
+            Some(padded) => padded / alignment * alignment,
+            None => {
+                let err = BufferAllocationError::Overflow;
+                self.emit_error(u64::MAX, usage, &err);
+                return Err(err);
+            }
+        };
+
+        let bytes = match u64::from(count)
+            .checked_mul(stride)
+            .filter(|&bytes| self.total.checked_add(bytes).is_some())
+        {
+            Some(bytes) => bytes,
+            None => {
+                let err = BufferAllocationError::Overflow;
+                self.emit_error(stride, usage, &err);
+                return Err(err);
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            "Allocating {} descriptor buffer ranges with layout {:?} @ {} bytes",
+            count,
+            layout,
+            stride
+        );
+
+        let bucket = self
+            .buckets
+            .entry((stride, usage))
+            .or_insert_with(|| DescriptorBufferBucket::new(stride, usage));
+
+        let mut allocations = Vec::new();
+        match bucket.allocate(device, size, count, &mut allocations, &mut self.listener) {
+            Ok(()) => {
+                self.total += bytes;
+                Ok(allocations)
+            }
+            Err(err) => {
+                // Free ranges allocated so far.
+                let mut last = None;
+
+                for allocation in allocations.drain(..) {
+                    if Some(allocation.buffer_id) != last {
+                        if let Some(last_id) = last {
+                            bucket.free(
+                                device,
+                                self.slots_cache.drain(..),
+                                last_id,
+                                &mut self.listener,
+                            );
+                        }
+                    }
+                    last = Some(allocation.buffer_id);
+                    self.slots_cache
+                        .push((allocation.offset / allocation.stride) as u32);
+                }
+
+                if let Some(last_id) = last {
+                    bucket.free(
+                        device,
+                        self.slots_cache.drain(..),
+                        last_id,
+                        &mut self.listener,
+                    );
+                }
+
+                let info = bucket.info();
+                self.listener
+                    .emit(|listener| listener.on_error(&info, &err));
+                Err(err)
+            }
+        }
+    }
+
+    /// Free descriptor buffer ranges.
+    ///
+    /// # Safety
+    ///
+    /// * Same `device` instance must be passed to all method calls of
+    ///   one `DescriptorBufferAllocator` instance.
+    /// * None of the ranges can be referenced in any pending command buffers.
+    pub unsafe fn free<L, D, I>(&mut self, device: &D, allocations: I)
+    where
+        D: DescriptorBufferDevice<L, B>,
+        I: IntoIterator<Item = DescriptorBufferAllocation<B>>,
+    {
+        debug_assert!(self.slots_cache.is_empty());
+
+        let mut last_key = (0, DescriptorBufferUsageFlags::empty());
+        let mut last_buffer_id = None;
+
+        // Batch freeing of adjacent ranges that belong to the same bucket and buffer.
+        for allocation in allocations {
+            if last_key != (allocation.stride, allocation.usage)
+                || last_buffer_id != Some(allocation.buffer_id)
+            {
+                if let Some(buffer_id) = last_buffer_id {
+                    self.free_slots_cache(device, &last_key, buffer_id);
+                }
+
+                last_key = (allocation.stride, allocation.usage);
+                last_buffer_id = Some(allocation.buffer_id);
+            }
+            self.slots_cache
+                .push((allocation.offset / allocation.stride) as u32);
+        }
+
+        if let Some(buffer_id) = last_buffer_id {
+            self.free_slots_cache(device, &last_key, buffer_id);
+        }
+    }
+
+    /// Frees the cached slots which must be allocated from the same bucket and buffer.
+    unsafe fn free_slots_cache<L, D>(
+        &mut self,
+        device: &D,
+        bucket_key: &(u64, DescriptorBufferUsageFlags),
+        buffer_id: u64,
+    ) where
+        D: DescriptorBufferDevice<L, B>,
+    {
+        let bucket = self
+            .buckets
+            .get_mut(bucket_key)
+            .expect("Range must be allocated from this allocator");
+
+        self.total -= self.slots_cache.len() as u64 * bucket.stride;
+        bucket.free(
+            device,
+            self.slots_cache.drain(..),
+            buffer_id,
+            &mut self.listener,
+        );
+    }
+
+    /// Notifies listener about allocation failure before bucket is created.
+    fn emit_error(
+        &mut self,
+        stride: u64,
+        usage: DescriptorBufferUsageFlags,
+        err: &BufferAllocationError,
+    ) {
+        let info = match self.buckets.get(&(stride, usage)) {
+            Some(bucket) => bucket.info(),
+            None => DescriptorBufferBucketInfo {
+                stride,
+                usage,
+                total: 0,
+                buffers: 0,
+            },
+        };
+        self.listener.emit(|listener| listener.on_error(&info, err));
+    }
+
+    /// Perform cleanup to allow resources reuse.
+    ///
+    /// # Safety
+    ///
+    /// * Same `device` instance must be passed to all method calls of
+    ///   one `DescriptorBufferAllocator` instance.
+    pub unsafe fn cleanup<L>(&mut self, device: &impl DescriptorBufferDevice<L, B>) {
+        for bucket in self.buckets.values_mut() {
+            bucket.cleanup(device, &mut self.listener)
+        }
+        self.buckets.retain(|_, bucket| !bucket.buffers.is_empty());
+    }
+
+    /// Returns statistics of the allocator.
+    pub fn statistics(&self) -> DescriptorBufferStatistics {
+        let mut statistics = DescriptorBufferStatistics {
+            allocated_bytes: self.total,
+            ..DescriptorBufferStatistics::default()
+        };
+
+        for bucket in self.buckets.values() {
+            statistics.allocated_sets += bucket.total;
+            for buffer in &bucket.buffers {
+                statistics.buffers += 1;
+                statistics.reserved_bytes += u64::from(buffer.capacity) * bucket.stride;
+            }
+        }
+
+        statistics
+    }
+
+    /// Returns statistics of each bucket.
+    pub fn bucket_statistics(&self) -> impl Iterator<Item = DescriptorBufferBucketStatistics> + '_ {
+        self.buckets
+            .values()
+            .map(DescriptorBufferBucket::statistics)
+    }
+
+    /// Sets listener that will be notified about allocator activity.
+    /// Replaces previously set listener.
+    pub fn set_listener(&mut self, listener: Box<dyn DescriptorBufferAllocatorListener + Send>) {
+        self.listener.0 = Some(listener);
+    }
+
+    /// Removes listener and returns it.
+    pub fn take_listener(&mut self) -> Option<Box<dyn DescriptorBufferAllocatorListener + Send>> {
+        self.listener.0.take()
+    }
+}
@@ -0,0 +1,150 @@
+use {
+    crate::allocator::DescriptorSetLifetime,
+    alloc::vec::Vec,
+    core::{
+        fmt::{self, Display},
+        ops::Range,
+    },
+    gpu_descriptor_types::DescriptorTotalCount,
+};
+
+#[cfg(feature = "serde")]
+use alloc::string::String;
+
+/// Snapshot of `DescriptorAllocator` state for bug reports.
+///
+/// Formats as human-readable text with `Display`.
+/// With `serde` feature it can be serialized, e.g. to JSON with `to_json`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DescriptorAllocatorDump {
+    /// Number of descriptors in all allocated sets.
+    pub total_descriptors: u32,
+
+    /// Number of descriptors in sets allocated from update-after-bind pools.
+    pub update_after_bind_descriptors: u32,
+
+    /// Limit of descriptors in update-after-bind pools the allocator was created with.
+    pub max_update_after_bind_descriptors: u32,
+
+    /// All buckets of the allocator, sorted by their keys.
+    pub buckets: Vec<DescriptorBucketDump>,
+}
+
+/// Snapshot of one bucket of `DescriptorAllocator`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DescriptorBucketDump {
+    /// Number of descriptors in each set of the bucket.
+    pub size: DescriptorTotalCount,
+
+    /// Whether sets of the bucket are allocated from update-after-bind pools.
+    pub update_after_bind: bool,
+
+    /// Whether sets of the bucket are allocated from host-only pools.
+    pub host_only: bool,
+
+    /// Lifetime hint of sets in the bucket.
+    pub lifetime: DescriptorSetLifetime,
+
+    /// Number of sets currently allocated from the bucket.
+    pub total: u32,
+
+    /// Maximum number of sets allocated from the bucket at once.
+    pub peak: u32,
+
+    /// Range of ids of the bucket's pools.
+    /// Ids below the range belong to destroyed pools.
+    pub pool_ids: Range<u64>,
+
+    /// Pools of the bucket, from oldest to newest.
+    pub pools: Vec<DescriptorPoolDump>,
+}
+
+/// Snapshot of one pool of `DescriptorAllocator`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DescriptorPoolDump {
+    /// Id of the pool within its bucket.
+    pub pool_id: u64,
+
+    /// Number of sets the pool was created with.
+    pub max_sets: u32,
+
+    /// Number of sets allocated from the pool.
+    pub allocated: u32,
+
+    /// Expected number of sets still available in the pool.
+    pub available: u32,
+}
+
+#[cfg(feature = "serde")]
+impl DescriptorAllocatorDump {
+    /// Serializes the dump to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Dump must be serializable")
+    }
+}
+
+impl Display for DescriptorAllocatorDump {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            fmt,
+            "DescriptorAllocator: {} buckets, {} descriptors, {}/{} update-after-bind descriptors",
+            self.buckets.len(),
+            self.total_descriptors,
+            self.update_after_bind_descriptors,
+            self.max_update_after_bind_descriptors,
+        )?;
+
+        for bucket in &self.buckets {
+            writeln!(
+                fmt,
+                "  bucket [{}]{}{} {:?}: {} sets (peak {}), pools {}..{}",
+                CountDisplay(&bucket.size),
+                if bucket.update_after_bind {
+                    " update-after-bind"
+                } else {
+                    ""
+                },
+                if bucket.host_only { " host-only" } else { "" },
+                bucket.lifetime,
+                bucket.total,
+                bucket.peak,
+                bucket.pool_ids.start,
+                bucket.pool_ids.end,
+            )?;
+
+            for pool in &bucket.pools {
+                writeln!(
+                    fmt,
+                    "    pool {}: {}/{} sets allocated, {} available",
+                    pool.pool_id, pool.allocated, pool.max_sets, pool.available,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats non-zero descriptor counts.
+struct CountDisplay<'a>(&'a DescriptorTotalCount);
+
+impl Display for CountDisplay<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (kind, count) in self.0.iter() {
+            if !first {
+                fmt.write_str(", ")?;
+            }
+            first = false;
+            write!(fmt, "{:?}: {}", kind, count)?;
+        }
+
+        if first {
+            fmt.write_str("empty")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use {
+    alloc::{boxed::Box, sync::Arc, vec::Vec},
+    gpu_descriptor_types::{
+        CreateLayoutError, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateFlags,
+        DescriptorSetLayoutDevice, DescriptorTotalCount,
+    },
+    hashbrown::{Equivalent, HashMap},
+};
+
+/// Descriptor set layout from cache.
+///
+/// Cheap to clone handle that carries descriptor counts and flags
+/// matching the layout, ready to be passed to `DescriptorAllocator::allocate`.
+#[derive(Debug)]
+pub struct DescriptorSetLayout<L> {
+    inner: Arc<LayoutInner<L>>,
+}
+
+#[derive(Debug)]
+struct LayoutInner<L> {
+    raw: L,
+    flags: DescriptorSetLayoutCreateFlags,
+    descriptor_count: DescriptorTotalCount,
+}
+
+impl<L> Clone for DescriptorSetLayout<L> {
+    fn clone(&self) -> Self {
+        DescriptorSetLayout {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<L> DescriptorSetLayout<L> {
+    /// Returns reference to raw descriptor set layout.
+    pub fn raw(&self) -> &L {
+        &self.inner.raw
+    }
+
+    /// Returns flags the layout was created with.
+    pub fn flags(&self) -> DescriptorSetLayoutCreateFlags {
+        self.inner.flags
+    }
+
+    /// Returns number of descriptors in the layout.
+    pub fn descriptor_count(&self) -> &DescriptorTotalCount {
+        &self.inner.descriptor_count
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct LayoutKey {
+    bindings: Box<[DescriptorSetLayoutBinding]>,
+    flags: DescriptorSetLayoutCreateFlags,
+}
+
+/// Borrowed counterpart of `LayoutKey`.
+/// Must hash exactly as `LayoutKey` does.
+#[derive(Hash)]
+struct LayoutKeyRef<'a> {
+    bindings: &'a [DescriptorSetLayoutBinding],
+    flags: DescriptorSetLayoutCreateFlags,
+}
+
+impl Equivalent<LayoutKey> for LayoutKeyRef<'_> {
+    fn equivalent(&self, key: &LayoutKey) -> bool {
+        *self.bindings == *key.bindings && self.flags == key.flags
+    }
+}
+
+/// Descriptor set layout cache.
+/// Creates each unique layout only once and hands out ref-counted handles to it.
+#[derive(Debug)]
+pub struct DescriptorSetLayoutCache<L> {
+    layouts: HashMap<LayoutKey, DescriptorSetLayout<L>>,
+    bindings_cache: Vec<DescriptorSetLayoutBinding>,
+}
+
+impl<L> Drop for DescriptorSetLayoutCache<L> {
+    fn drop(&mut self) {
+        if !self.layouts.is_empty() {
+            #[cfg(feature = "tracing")]
+            tracing::error!(
+                "`DescriptorSetLayoutCache` is dropped while some layouts were not destroyed"
+            );
+        }
+    }
+}
+
+impl<L> Default for DescriptorSetLayoutCache<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L> DescriptorSetLayoutCache<L> {
+    /// Create new cache instance.
+    pub fn new() -> Self {
+        DescriptorSetLayoutCache {
+            layouts: HashMap::default(),
+            bindings_cache: Vec::new(),
+        }
+    }
+
+    /// Returns number of layouts in the cache.
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    /// Returns `true` if cache holds no layouts.
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    /// Returns layout with specified bindings and flags.
+    /// Creates new layout if there is no matching one in the cache.
+    ///
+    /// Order of bindings does not matter.
+    ///
+    /// # Safety
+    ///
+    /// * Same `device` instance must be passed to all method calls of
+    ///   one `DescriptorSetLayoutCache` instance.
+    /// * Binding numbers must be unique.
+    pub unsafe fn get_or_create(
+        &mut self,
+        device: &impl DescriptorSetLayoutDevice<L>,
+        bindings: &[DescriptorSetLayoutBinding],
+        flags: DescriptorSetLayoutCreateFlags,
+    ) -> Result<DescriptorSetLayout<L>, CreateLayoutError> {
+        self.bindings_cache.clear();
+        self.bindings_cache.extend_from_slice(bindings);
+        self.bindings_cache
+            .sort_unstable_by_key(|binding| binding.binding);
+
+        let key = LayoutKeyRef {
+            bindings: &self.bindings_cache,
+            flags,
+        };
+
+        if let Some(layout) = self.layouts.get(&key) {
+            return Ok(layout.clone());
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            "Create new descriptor set layout with {} bindings",
+            self.bindings_cache.len()
+        );
+
+        let descriptor_count = DescriptorTotalCount::from_bindings(&self.bindings_cache)?;
+        let raw = device.create_descriptor_set_layout(&self.bindings_cache, flags)?;
+
+        let layout = DescriptorSetLayout {
+            inner: Arc::new(LayoutInner {
+                raw,
+                flags,
+                descriptor_count,
+            }),
+        };
+
+        self.layouts.insert(
+            LayoutKey {
+                bindings: self.bindings_cache.as_slice().into(),
+                flags,
+            },
+            layout.clone(),
+        );
+
+        Ok(layout)
+    }
+
+    /// Destroys layouts that are not referenced by any handle outside of the cache.
+    ///
+    /// # Safety
+    ///
+    /// * Same `device` instance must be passed to all method calls of
+    ///   one `DescriptorSetLayoutCache` instance.
+    /// * Destroyed layouts must not be in use by the device.
+    pub unsafe fn cleanup(&mut self, device: &impl DescriptorSetLayoutDevice<L>) {
+        for (_, layout) in self
+            .layouts
+            .extract_if(|_, layout| Arc::strong_count(&layout.inner) == 1)
+        {
+            if let Ok(inner) = Arc::try_unwrap(layout.inner) {
+                #[cfg(feature = "tracing")]
+                tracing::trace!("Destroying unused descriptor set layout");
+
+                device.destroy_descriptor_set_layout(inner.raw);
+            }
+        }
+    }
+}
@@ -9,7 +9,7 @@
 //! let mut allocator = DescriptorAllocator::new(max_update_after_bind_descriptors_in_all_pools); // Limit as dictated by API for selected hardware
 //!
 //! let result = allocator.allocate(
-//!     device, // Implementation of `gpu_descriptor::DescriptorDevice`. Comes from plugins.
+//!     device, // Implementation of `gpu_descriptor::DescriptorDeviceV2`. Comes from plugins.
 //!     layout, // Descriptor set layout recognized by device's type.
 //!     flags,  // Flags specified when layout was created.
 //!     layout_descriptor_count, // Descriptors count in the layout.
@@ -17,6 +17,11 @@
 //! );
 //! ```
 //!
+//! Descriptor buffers allocated with `DescriptorBufferAllocator`
+//! are supported only by the `gpu-descriptor-ash` backend, via `AshDescriptorBufferDevice`.
+//! `gpu-descriptor-erupt` does not implement `DescriptorBufferDevice`,
+//! as erupt has no bindings for `VK_EXT_descriptor_buffer`.
+//!
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![warn(
@@ -31,5 +36,24 @@
 extern crate alloc;
 
 mod allocator;
+mod bindless;
+mod buffer;
+mod dump;
+mod layout;
+mod listener;
+mod profile;
+mod recovery;
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+#[cfg(feature = "trace")]
+pub mod trace;
 
-pub use {crate::allocator::*, gpu_descriptor_types::*};
+pub use {
+    crate::{
+        allocator::*, bindless::*, buffer::*, dump::*, layout::*, listener::*, profile::*,
+        recovery::*,
+    },
+    gpu_descriptor_types::*,
+};
@@ -0,0 +1,175 @@
+use {
+    crate::{
+        allocator::{AllocationError, DescriptorSetLifetime},
+        buffer::BufferAllocationError,
+    },
+    alloc::boxed::Box,
+    core::fmt::{self, Debug},
+    gpu_descriptor_types::{
+        DescriptorBufferUsageFlags, DescriptorPoolCreateFlags, DescriptorTotalCount,
+    },
+};
+
+/// Description of the descriptor bucket involved in allocator event.
+///
+/// Buckets group sets with equal descriptor counts, update-after-bind and host-only flags
+/// and lifetime hint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BucketInfo {
+    /// Number of descriptors in each set of the bucket.
+    pub size: DescriptorTotalCount,
+
+    /// Whether sets of the bucket are allocated from update-after-bind pools.
+    pub update_after_bind: bool,
+
+    /// Whether sets of the bucket are allocated from host-only pools.
+    pub host_only: bool,
+
+    /// Lifetime hint of sets in the bucket.
+    pub lifetime: DescriptorSetLifetime,
+
+    /// Number of sets currently allocated from the bucket.
+    pub total: u32,
+
+    /// Number of pools currently owned by the bucket.
+    pub pools: u32,
+}
+
+/// Description of the descriptor pool involved in allocator event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolInfo {
+    /// Id of the pool within its bucket.
+    pub pool_id: u64,
+
+    /// Number of descriptors the pool was created with.
+    pub descriptor_count: DescriptorTotalCount,
+
+    /// Maximum number of sets the pool was created with.
+    pub max_sets: u32,
+
+    /// Flags the pool was created with.
+    pub flags: DescriptorPoolCreateFlags,
+}
+
+/// Listener of `DescriptorAllocator` activity.
+///
+/// All methods do nothing by default.
+/// Methods are called synchronously from allocator methods
+/// and should not take long.
+#[allow(unused_variables)]
+pub trait DescriptorAllocatorListener {
+    /// Called after new descriptor pool is created.
+    fn on_pool_created(&mut self, bucket: &BucketInfo, pool: &PoolInfo) {}
+
+    /// Called after descriptor pool is destroyed.
+    fn on_pool_destroyed(&mut self, bucket: &BucketInfo, pool: &PoolInfo) {}
+
+    /// Called after a batch of sets is allocated from one pool.
+    fn on_sets_allocated(&mut self, bucket: &BucketInfo, pool_id: u64, count: u32) {}
+
+    /// Called after a batch of sets is returned to one pool.
+    fn on_sets_freed(&mut self, bucket: &BucketInfo, pool_id: u64, count: u32) {}
+
+    /// Called when allocation fails, before error is returned to the caller.
+    fn on_error(&mut self, bucket: &BucketInfo, error: &AllocationError) {}
+}
+
+/// Description of the descriptor buffer bucket involved in allocator event.
+///
+/// Buckets group ranges with equal stride and usage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorBufferBucketInfo {
+    /// Number of bytes each range of the bucket occupies, including alignment padding.
+    /// Saturated to `u64::MAX` if padding overflows.
+    pub stride: u64,
+
+    /// Usage of descriptor buffers of the bucket.
+    pub usage: DescriptorBufferUsageFlags,
+
+    /// Number of ranges currently allocated from the bucket.
+    pub total: u32,
+
+    /// Number of descriptor buffers currently owned by the bucket.
+    pub buffers: u32,
+}
+
+/// Description of the descriptor buffer involved in allocator event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorBufferInfo {
+    /// Id of the buffer within its bucket.
+    pub buffer_id: u64,
+
+    /// Number of ranges the buffer holds.
+    pub capacity: u32,
+
+    /// Size of the buffer in bytes.
+    pub size: u64,
+}
+
+/// Listener of `DescriptorBufferAllocator` activity.
+///
+/// All methods do nothing by default.
+/// Methods are called synchronously from allocator methods
+/// and should not take long.
+#[allow(unused_variables)]
+pub trait DescriptorBufferAllocatorListener {
+    /// Called after new descriptor buffer is created.
+    fn on_buffer_created(
+        &mut self,
+        bucket: &DescriptorBufferBucketInfo,
+        buffer: &DescriptorBufferInfo,
+    ) {
+    }
+
+    /// Called after descriptor buffer is destroyed.
+    fn on_buffer_destroyed(
+        &mut self,
+        bucket: &DescriptorBufferBucketInfo,
+        buffer: &DescriptorBufferInfo,
+    ) {
+    }
+
+    /// Called after a batch of ranges is allocated from one buffer.
+    fn on_ranges_allocated(
+        &mut self,
+        bucket: &DescriptorBufferBucketInfo,
+        buffer_id: u64,
+        count: u32,
+    ) {
+    }
+
+    /// Called after a batch of ranges is returned to one buffer.
+    fn on_ranges_freed(&mut self, bucket: &DescriptorBufferBucketInfo, buffer_id: u64, count: u32) {
+    }
+
+    /// Called when allocation fails, before error is returned to the caller.
+    fn on_error(&mut self, bucket: &DescriptorBufferBucketInfo, error: &BufferAllocationError) {}
+}
+
+/// Optional listener slot of the allocator.
+pub(crate) struct Listener<T: ?Sized = dyn DescriptorAllocatorListener + Send>(
+    pub(crate) Option<Box<T>>,
+);
+
+impl<T: ?Sized> Default for Listener<T> {
+    fn default() -> Self {
+        Listener(None)
+    }
+}
+
+impl<T: ?Sized> Debug for Listener<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            None => fmt.write_str("None"),
+            Some(_) => fmt.write_str("Some(..)"),
+        }
+    }
+}
+
+impl<T: ?Sized> Listener<T> {
+    pub(crate) fn emit(&mut self, f: impl FnOnce(&mut T)) {
+        if let Some(listener) = &mut self.0 {
+            f(&mut **listener);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Mock device for testing and offline evaluation of the allocator.
+
+use {
+    core::cell::{Cell, RefCell},
+    gpu_descriptor_types::{
+        CreatePoolError, DescriptorDeviceCapabilities, DescriptorDeviceV2,
+        DescriptorPoolCreateFlags, DescriptorTotalCount, DeviceAllocationError,
+    },
+};
+
+/// Descriptor pool created by `MockDevice`.
+#[derive(Debug)]
+pub struct MockPool {
+    id: u64,
+    max_sets: u32,
+    descriptors: u64,
+    allocated_sets: u32,
+    allocated_descriptors: u64,
+}
+
+impl MockPool {
+    /// Returns unique id of the pool.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns number of sets allocated from the pool.
+    pub fn allocated_sets(&self) -> u32 {
+        self.allocated_sets
+    }
+}
+
+/// Descriptor set allocated by `MockDevice`.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct MockSet {
+    id: u64,
+    pool: u64,
+    descriptors: u64,
+}
+
+impl MockSet {
+    /// Returns unique id of the set.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns id of the pool the set was allocated from.
+    pub fn pool(&self) -> u64 {
+        self.pool
+    }
+}
+
+/// Device that imitates descriptor pools on host.
+///
+/// Layouts are represented by their descriptor counts.
+/// Pools enforce both set and descriptor limits they were created with,
+/// and every misuse the real device would not tolerate panics.
+/// Device has no optional capabilities unless set with `set_capabilities`.
+#[derive(Debug, Default)]
+pub struct MockDevice {
+    capabilities: Cell<DescriptorDeviceCapabilities>,
+    next_pool_id: Cell<u64>,
+    next_set_id: Cell<u64>,
+    pools_created: Cell<u64>,
+    pools_destroyed: Cell<u64>,
+    live_sets: Cell<u64>,
+    pools_reset: Cell<u64>,
+    fail_pool_creation: RefCell<Option<CreatePoolError>>,
+    fail_allocation: RefCell<Option<DeviceAllocationError>>,
+}
+
+impl MockDevice {
+    /// Creates new mock device.
+    pub fn new() -> Self {
+        MockDevice::default()
+    }
+
+    /// Sets optional capabilities reported by the device.
+    pub fn set_capabilities(&self, capabilities: DescriptorDeviceCapabilities) {
+        self.capabilities.set(capabilities);
+    }
+
+    /// Makes next pool creation fail with specified error.
+    pub fn fail_next_pool_creation(&self, err: CreatePoolError) {
+        *self.fail_pool_creation.borrow_mut() = Some(err);
+    }
+
+    /// Makes next set allocation fail with specified error.
+    pub fn fail_next_allocation(&self, err: DeviceAllocationError) {
+        *self.fail_allocation.borrow_mut() = Some(err);
+    }
+
+    /// Returns number of pools created so far.
+    pub fn pools_created(&self) -> u64 {
+        self.pools_created.get()
+    }
+
+    /// Returns number of pools destroyed so far.
+    pub fn pools_destroyed(&self) -> u64 {
+        self.pools_destroyed.get()
+    }
+
+    /// Returns number of pool resets so far.
+    pub fn pools_reset(&self) -> u64 {
+        self.pools_reset.get()
+    }
+
+    /// Returns number of pools currently alive.
+    pub fn live_pools(&self) -> u64 {
+        self.pools_created.get() - self.pools_destroyed.get()
+    }
+
+    /// Returns number of sets currently allocated.
+    pub fn live_sets(&self) -> u64 {
+        self.live_sets.get()
+    }
+}
+
+impl DescriptorDeviceV2 for MockDevice {
+    type Layout = DescriptorTotalCount;
+    type Pool = MockPool;
+    type Set = MockSet;
+
+    fn capabilities(&self) -> DescriptorDeviceCapabilities {
+        self.capabilities.get()
+    }
+
+    fn create_descriptor_pool(
+        &self,
+        descriptor_count: &DescriptorTotalCount,
+        max_sets: u32,
+        flags: DescriptorPoolCreateFlags,
+    ) -> Result<MockPool, CreatePoolError> {
+        assert!(
+            flags.contains(DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET),
+            "Pools must allow freeing individual sets"
+        );
+
+        if let Some(err) = self.fail_pool_creation.borrow_mut().take() {
+            return Err(err);
+        }
+
+        let id = self.next_pool_id.get();
+        self.next_pool_id.set(id + 1);
+        self.pools_created.set(self.pools_created.get() + 1);
+
+        Ok(MockPool {
+            id,
+            max_sets,
+            descriptors: total(descriptor_count),
+            allocated_sets: 0,
+            allocated_descriptors: 0,
+        })
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, pool: MockPool) {
+        self.live_sets
+            .set(self.live_sets.get() - u64::from(pool.allocated_sets));
+        self.pools_destroyed.set(self.pools_destroyed.get() + 1);
+    }
+
+    unsafe fn reset_descriptor_pool(&self, pool: &mut MockPool) {
+        assert!(
+            self.capabilities
+                .get()
+                .contains(DescriptorDeviceCapabilities::RESET_POOL),
+            "Pool reset is not enabled"
+        );
+        self.live_sets
+            .set(self.live_sets.get() - u64::from(pool.allocated_sets));
+        self.pools_reset.set(self.pools_reset.get() + 1);
+        pool.allocated_sets = 0;
+        pool.allocated_descriptors = 0;
+    }
+
+    unsafe fn alloc_descriptor_sets<'a>(
+        &self,
+        pool: &mut MockPool,
+        layouts: impl ExactSizeIterator<Item = &'a DescriptorTotalCount>,
+        sets: &mut impl Extend<MockSet>,
+    ) -> Result<(), DeviceAllocationError> {
+        if let Some(err) = self.fail_allocation.borrow_mut().take() {
+            return Err(err);
+        }
+
+        let count = layouts.len() as u32;
+
+        if pool.max_sets - pool.allocated_sets < count {
+            return Err(DeviceAllocationError::OutOfPoolMemory);
+        }
+
+        let pool_id = pool.id;
+        sets.extend(layouts.map(|layout| {
+            let descriptors = total(layout);
+            assert!(
+                pool.descriptors - pool.allocated_descriptors >= descriptors,
+                "Pool must have enough descriptors for its `max_sets`"
+            );
+            pool.allocated_sets += 1;
+            pool.allocated_descriptors += descriptors;
+
+            let id = self.next_set_id.get();
+            self.next_set_id.set(id + 1);
+            self.live_sets.set(self.live_sets.get() + 1);
+
+            MockSet {
+                id,
+                pool: pool_id,
+                descriptors,
+            }
+        }));
+        Ok(())
+    }
+
+    unsafe fn dealloc_descriptor_sets(
+        &self,
+        pool: &mut MockPool,
+        sets: impl Iterator<Item = MockSet>,
+    ) {
+        for set in sets {
+            assert_eq!(set.pool, pool.id, "Set must be freed to its own pool");
+            pool.allocated_sets -= 1;
+            pool.allocated_descriptors -= set.descriptors;
+            self.live_sets.set(self.live_sets.get() - 1);
+        }
+    }
+}
+
+/// Sums descriptor counts without risk of overflow.
+fn total(count: &DescriptorTotalCount) -> u64 {
+    count.iter().map(|(_, count)| u64::from(count)).sum()
+}
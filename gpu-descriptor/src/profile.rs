@@ -0,0 +1,38 @@
+use {
+    crate::allocator::DescriptorSetLifetime, alloc::vec::Vec,
+    gpu_descriptor_types::DescriptorTotalCount,
+};
+
+/// Peak numbers of sets observed in `DescriptorAllocator` buckets.
+///
+/// Export it at shutdown with `DescriptorAllocator::pool_size_profile`
+/// and import on next launch with `DescriptorAllocator::import_pool_size_profile`,
+/// so that right-sized pools are created from the first frame.
+/// With `serde` feature it can be serialized and deserialized for persistence.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoolSizeProfile {
+    /// One entry per bucket.
+    pub entries: Vec<PoolSizeProfileEntry>,
+}
+
+/// Peak number of sets observed in one bucket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoolSizeProfileEntry {
+    /// Number of descriptors in each set of the bucket.
+    pub size: DescriptorTotalCount,
+
+    /// Whether sets of the bucket are allocated from update-after-bind pools.
+    pub update_after_bind: bool,
+
+    /// Whether sets of the bucket are allocated from host-only pools.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub host_only: bool,
+
+    /// Lifetime hint of sets in the bucket.
+    pub lifetime: DescriptorSetLifetime,
+
+    /// Maximum number of sets allocated from the bucket at once.
+    pub peak: u32,
+}
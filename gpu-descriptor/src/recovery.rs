@@ -0,0 +1,56 @@
+use {
+    crate::allocator::AllocationError,
+    alloc::boxed::Box,
+    core::fmt::{self, Debug},
+};
+
+/// Callback that releases memory held outside of the allocator,
+/// e.g. caches of other resources.
+///
+/// Receives the error that triggered recovery.
+/// Returns `true` if any memory was released.
+pub type ReleaseMemoryCallback = Box<dyn FnMut(&AllocationError) -> bool + Send>;
+
+/// Policy of recovery from out-of-memory errors reported by device
+/// when `DescriptorAllocator` creates pools or allocates sets.
+///
+/// Before each retry allocator destroys empty pools of all buckets if `cleanup` is set
+/// and then calls `release_memory` callback if any.
+/// Allocation is not retried if neither released anything.
+///
+/// Default policy does not retry and returns error immediately.
+#[derive(Default)]
+pub struct OutOfMemoryPolicy {
+    /// Maximum number of retries per allocation call.
+    pub retries: u32,
+
+    /// Whether empty pools of all buckets are destroyed before retrying.
+    pub cleanup: bool,
+
+    /// Callback that is called before retrying.
+    pub release_memory: Option<ReleaseMemoryCallback>,
+}
+
+impl OutOfMemoryPolicy {
+    /// Returns policy that destroys empty pools and retries once.
+    pub fn cleanup_and_retry() -> Self {
+        OutOfMemoryPolicy {
+            retries: 1,
+            cleanup: true,
+            release_memory: None,
+        }
+    }
+}
+
+impl Debug for OutOfMemoryPolicy {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("OutOfMemoryPolicy")
+            .field("retries", &self.retries)
+            .field("cleanup", &self.cleanup)
+            .field(
+                "release_memory",
+                &self.release_memory.as_ref().map(|_| ".."),
+            )
+            .finish()
+    }
+}
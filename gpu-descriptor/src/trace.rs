@@ -0,0 +1,1269 @@
+//! Recording of allocator calls and their replay against `MockDevice`.
+//!
+//! Trace starts with `GDTR` magic and format version,
+//! followed by allocator configuration and one record per call.
+//! Calls that configure the allocator are recorded as well,
+//! so replay runs with the same limits, sizing, recovery policy and profile.
+//! All integers are LEB128 encoded, so typical records take a few bytes.
+
+use {
+    crate::{
+        allocator::{
+            AllocationError, DescriptorAllocator, DescriptorSet, DescriptorSetLifetime, PoolSizing,
+        },
+        listener::DescriptorAllocatorListener,
+        mock::{MockDevice, MockPool, MockSet},
+        profile::{PoolSizeProfile, PoolSizeProfileEntry},
+        recovery::OutOfMemoryPolicy,
+    },
+    alloc::{boxed::Box, vec::Vec},
+    core::{
+        convert::TryFrom as _,
+        fmt::{self, Debug, Display},
+    },
+    gpu_descriptor_types::{
+        DescriptorDeviceV2, DescriptorKind, DescriptorSetLayoutCreateFlags, DescriptorTotalCount,
+        DeviceLimits, MAX_VENDOR_DESCRIPTOR_KINDS,
+    },
+    hashbrown::HashMap,
+    std::io::{self, Read, Write},
+};
+
+const MAGIC: [u8; 4] = *b"GDTR";
+const VERSION: u8 = 1;
+
+const TAG_ALLOCATE: u8 = 1;
+const TAG_FREE: u8 = 2;
+const TAG_CLEANUP: u8 = 3;
+const TAG_ALLOCATE_INTO: u8 = 4;
+const TAG_ALLOCATE_ONE: u8 = 5;
+const TAG_FREE_UNORDERED: u8 = 6;
+const TAG_FORGET_ALL: u8 = 7;
+const TAG_DESTROY_ALL: u8 = 8;
+const TAG_SET_DEVICE_LIMITS: u8 = 9;
+const TAG_SET_POOL_SIZING: u8 = 10;
+const TAG_SET_OUT_OF_MEMORY_POLICY: u8 = 11;
+const TAG_IMPORT_POOL_SIZE_PROFILE: u8 = 12;
+
+const SETS_UPDATE_AFTER_BIND: u8 = 1;
+const SETS_HOST_ONLY: u8 = 2;
+const SETS_TRANSIENT: u8 = 4;
+
+/// Error that may occur when reading trace.
+#[derive(Debug)]
+pub enum TraceError {
+    /// Underlying reader failed.
+    Io(io::Error),
+
+    /// Data is not a trace of supported version.
+    InvalidFormat,
+}
+
+impl Display for TraceError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io(err) => write!(fmt, "Failed to read trace: {}", err),
+            TraceError::InvalidFormat => fmt.write_str("Invalid trace format"),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TraceError::Io(err) => Some(err),
+            TraceError::InvalidFormat => None,
+        }
+    }
+}
+
+impl From<io::Error> for TraceError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => TraceError::InvalidFormat,
+            _ => TraceError::Io(err),
+        }
+    }
+}
+
+/// Outcome of traced allocation call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceResult {
+    /// Sets were allocated.
+    Success,
+
+    /// `AllocationError::OutOfDeviceMemory` was returned.
+    OutOfDeviceMemory,
+
+    /// `AllocationError::OutOfHostMemory` was returned.
+    OutOfHostMemory,
+
+    /// `AllocationError::Fragmentation` was returned.
+    Fragmentation,
+
+    /// `AllocationError::LimitExceeded` was returned.
+    LimitExceeded,
+
+    /// `AllocationError::Overflow` was returned.
+    Overflow,
+
+    /// `AllocationError::InvalidLayoutFlags` was returned.
+    InvalidLayoutFlags,
+}
+
+impl<T> From<&Result<T, AllocationError>> for TraceResult {
+    fn from(result: &Result<T, AllocationError>) -> Self {
+        match result {
+            Ok(_) => TraceResult::Success,
+            Err(AllocationError::OutOfDeviceMemory) => TraceResult::OutOfDeviceMemory,
+            Err(AllocationError::OutOfHostMemory) => TraceResult::OutOfHostMemory,
+            Err(AllocationError::Fragmentation) => TraceResult::Fragmentation,
+            Err(AllocationError::LimitExceeded(_)) => TraceResult::LimitExceeded,
+            Err(AllocationError::Overflow) => TraceResult::Overflow,
+            Err(AllocationError::InvalidLayoutFlags) => TraceResult::InvalidLayoutFlags,
+        }
+    }
+}
+
+/// Sets freed from one pool in traced `free` or `free_unordered` call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceFreedSets {
+    /// Number of descriptors in each set.
+    pub descriptor_count: DescriptorTotalCount,
+
+    /// Whether sets were allocated from update-after-bind pool.
+    pub update_after_bind: bool,
+
+    /// Whether sets were allocated from host-only pool.
+    pub host_only: bool,
+
+    /// Lifetime hint sets were allocated with.
+    pub lifetime: DescriptorSetLifetime,
+
+    /// Number of `forget_all` and `destroy_all` calls recorded before sets were allocated.
+    pub epoch: u64,
+
+    /// Id of the pool sets were allocated from.
+    pub pool_id: u64,
+
+    /// Number of sets.
+    pub count: u32,
+}
+
+/// One traced allocator call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceRecord {
+    /// `DescriptorAllocator::allocate` or `DescriptorAllocator::allocate_with_lifetime` call.
+    Allocate {
+        /// Descriptors count in the layout.
+        descriptor_count: DescriptorTotalCount,
+
+        /// Flags the layout was created with.
+        flags: DescriptorSetLayoutCreateFlags,
+
+        /// Number of sets requested.
+        count: u32,
+
+        /// Lifetime hint of requested sets.
+        lifetime: DescriptorSetLifetime,
+
+        /// Outcome of the call.
+        result: TraceResult,
+    },
+
+    /// `DescriptorAllocator::allocate_into` call.
+    AllocateInto {
+        /// Descriptors count in the layout.
+        descriptor_count: DescriptorTotalCount,
+
+        /// Flags the layout was created with.
+        flags: DescriptorSetLayoutCreateFlags,
+
+        /// Number of sets requested.
+        count: u32,
+
+        /// Lifetime hint of requested sets.
+        lifetime: DescriptorSetLifetime,
+
+        /// Outcome of the call.
+        result: TraceResult,
+    },
+
+    /// `DescriptorAllocator::allocate_one` call.
+    AllocateOne {
+        /// Descriptors count in the layout.
+        descriptor_count: DescriptorTotalCount,
+
+        /// Flags the layout was created with.
+        flags: DescriptorSetLayoutCreateFlags,
+
+        /// Lifetime hint of requested set.
+        lifetime: DescriptorSetLifetime,
+
+        /// Outcome of the call.
+        result: TraceResult,
+    },
+
+    /// `DescriptorAllocator::free` call.
+    Free {
+        /// Freed sets grouped by pool.
+        sets: Vec<TraceFreedSets>,
+    },
+
+    /// `DescriptorAllocator::free_unordered` call.
+    FreeUnordered {
+        /// Freed sets grouped by pool.
+        sets: Vec<TraceFreedSets>,
+    },
+
+    /// `DescriptorAllocator::cleanup` call.
+    Cleanup,
+
+    /// `DescriptorAllocator::forget_all` call.
+    ForgetAll,
+
+    /// `DescriptorAllocator::destroy_all` call.
+    DestroyAll,
+
+    /// `DescriptorAllocator::set_device_limits` call.
+    SetDeviceLimits(DeviceLimits),
+
+    /// `DescriptorAllocator::set_pool_sizing` call.
+    SetPoolSizing(PoolSizing),
+
+    /// `DescriptorAllocator::set_out_of_memory_policy` call.
+    SetOutOfMemoryPolicy {
+        /// Maximum number of retries per allocation call.
+        retries: u32,
+
+        /// Whether empty pools of all buckets are destroyed before retrying.
+        cleanup: bool,
+
+        /// Whether policy had `release_memory` callback.
+        /// Callback itself cannot be recorded and is not replayed.
+        release_memory: bool,
+    },
+
+    /// `DescriptorAllocator::import_pool_size_profile` call.
+    ImportPoolSizeProfile(PoolSizeProfile),
+}
+
+/// Allocator wrapper that records every call into a trace.
+///
+/// Recording never fails allocator calls.
+/// First write error stops recording and is reported by `finish`.
+#[derive(Debug)]
+pub struct RecordingAllocator<P, S, W: Write> {
+    allocator: DescriptorAllocator<P, S>,
+    writer: W,
+    buffer: Vec<u8>,
+    sets_cache: Vec<DescriptorSet<S>>,
+    error: Option<io::Error>,
+}
+
+impl<P, S, W> RecordingAllocator<P, S, W>
+where
+    W: Write,
+{
+    /// Create new allocator instance that writes trace into `writer`.
+    pub fn new(max_update_after_bind_descriptors_in_all_pools: u32, writer: W) -> Self {
+        let mut recorder = RecordingAllocator {
+            allocator: DescriptorAllocator::new(max_update_after_bind_descriptors_in_all_pools),
+            writer,
+            buffer: Vec::new(),
+            sets_cache: Vec::new(),
+            error: None,
+        };
+
+        recorder.buffer.extend_from_slice(&MAGIC);
+        recorder.buffer.push(VERSION);
+        write_varint(
+            &mut recorder.buffer,
+            max_update_after_bind_descriptors_in_all_pools.into(),
+        );
+        recorder.flush_buffer();
+        recorder
+    }
+
+    /// Returns reference to wrapped allocator.
+    pub fn allocator(&self) -> &DescriptorAllocator<P, S> {
+        &self.allocator
+    }
+
+    /// Sets device limits and records the call.
+    ///
+    /// See `DescriptorAllocator::set_device_limits`.
+    pub fn set_device_limits(&mut self, limits: DeviceLimits) {
+        self.allocator.set_device_limits(limits);
+
+        self.buffer.push(TAG_SET_DEVICE_LIMITS);
+        for limit in limit_fields(&limits) {
+            write_varint(&mut self.buffer, limit.into());
+        }
+        self.flush_buffer();
+    }
+
+    /// Sets strategy of choosing number of sets for new pools and records the call.
+    ///
+    /// See `DescriptorAllocator::set_pool_sizing`.
+    pub fn set_pool_sizing(&mut self, sizing: PoolSizing) {
+        self.allocator.set_pool_sizing(sizing);
+
+        self.buffer.push(TAG_SET_POOL_SIZING);
+        self.buffer.push(match sizing {
+            PoolSizing::Fixed => 0,
+            PoolSizing::Adaptive => 1,
+        });
+        self.flush_buffer();
+    }
+
+    /// Sets policy of recovery from out-of-memory errors and records the call.
+    ///
+    /// See `DescriptorAllocator::set_out_of_memory_policy`.
+    pub fn set_out_of_memory_policy(&mut self, policy: OutOfMemoryPolicy) {
+        self.buffer.push(TAG_SET_OUT_OF_MEMORY_POLICY);
+        write_varint(&mut self.buffer, policy.retries.into());
+        self.buffer.push(policy.cleanup as u8);
+        self.buffer.push(policy.release_memory.is_some() as u8);
+        self.flush_buffer();
+
+        self.allocator.set_out_of_memory_policy(policy);
+    }
+
+    /// Sets listener of the wrapped allocator.
+    /// Listener only observes the allocator, so the call is not recorded.
+    ///
+    /// See `DescriptorAllocator::set_listener`.
+    pub fn set_listener(&mut self, listener: Box<dyn DescriptorAllocatorListener + Send>) {
+        self.allocator.set_listener(listener);
+    }
+
+    /// Imports pool size profile and records the call.
+    ///
+    /// See `DescriptorAllocator::import_pool_size_profile`.
+    pub fn import_pool_size_profile(&mut self, profile: &PoolSizeProfile) {
+        self.allocator.import_pool_size_profile(profile);
+
+        self.buffer.push(TAG_IMPORT_POOL_SIZE_PROFILE);
+        write_varint(&mut self.buffer, profile.entries.len() as u64);
+        for entry in &profile.entries {
+            write_count(&mut self.buffer, &entry.size);
+            self.buffer.push(sets_bits(
+                entry.update_after_bind,
+                entry.host_only,
+                entry.lifetime,
+            ));
+            write_varint(&mut self.buffer, entry.peak.into());
+        }
+        self.flush_buffer();
+    }
+
+    /// Flushes the writer and returns wrapped allocator and writer.
+    pub fn finish(mut self) -> Result<(DescriptorAllocator<P, S>, W), io::Error> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.writer.flush()?;
+        Ok((self.allocator, self.writer))
+    }
+
+    /// Allocate descriptor set with specified layout and record the call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::allocate`.
+    pub unsafe fn allocate<D>(
+        &mut self,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+        count: u32,
+    ) -> Result<Vec<DescriptorSet<S>>, AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        self.allocate_with_lifetime(
+            device,
+            layout,
+            flags,
+            layout_descriptor_count,
+            count,
+            DescriptorSetLifetime::Persistent,
+        )
+    }
+
+    /// Allocate descriptor set with specified layout and lifetime hint and record the call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::allocate_with_lifetime`.
+    pub unsafe fn allocate_with_lifetime<D>(
+        &mut self,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+        count: u32,
+        lifetime: DescriptorSetLifetime,
+    ) -> Result<Vec<DescriptorSet<S>>, AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        let result = self.allocator.allocate_with_lifetime(
+            device,
+            layout,
+            flags,
+            layout_descriptor_count,
+            count,
+            lifetime,
+        );
+
+        self.buffer.push(TAG_ALLOCATE);
+        write_allocation(
+            &mut self.buffer,
+            layout_descriptor_count,
+            flags,
+            Some(count),
+            lifetime,
+            TraceResult::from(&result),
+        );
+        self.flush_buffer();
+
+        result
+    }
+
+    /// Allocate descriptor sets into caller-provided collection and record the call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::allocate_into`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn allocate_into<D>(
+        &mut self,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+        count: u32,
+        lifetime: DescriptorSetLifetime,
+        out: &mut impl Extend<DescriptorSet<S>>,
+    ) -> Result<(), AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        let result = self.allocator.allocate_into(
+            device,
+            layout,
+            flags,
+            layout_descriptor_count,
+            count,
+            lifetime,
+            out,
+        );
+
+        self.buffer.push(TAG_ALLOCATE_INTO);
+        write_allocation(
+            &mut self.buffer,
+            layout_descriptor_count,
+            flags,
+            Some(count),
+            lifetime,
+            TraceResult::from(&result),
+        );
+        self.flush_buffer();
+
+        result
+    }
+
+    /// Allocate one descriptor set and record the call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::allocate_one`.
+    pub unsafe fn allocate_one<D>(
+        &mut self,
+        device: &D,
+        layout: &D::Layout,
+        flags: DescriptorSetLayoutCreateFlags,
+        layout_descriptor_count: &DescriptorTotalCount,
+        lifetime: DescriptorSetLifetime,
+    ) -> Result<DescriptorSet<S>, AllocationError>
+    where
+        S: Debug,
+        D::Layout: Debug,
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+    {
+        let result =
+            self.allocator
+                .allocate_one(device, layout, flags, layout_descriptor_count, lifetime);
+
+        self.buffer.push(TAG_ALLOCATE_ONE);
+        write_allocation(
+            &mut self.buffer,
+            layout_descriptor_count,
+            flags,
+            None,
+            lifetime,
+            TraceResult::from(&result),
+        );
+        self.flush_buffer();
+
+        result
+    }
+
+    /// Free descriptor sets and record the call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::free`.
+    pub unsafe fn free<D, I>(&mut self, device: &D, sets: I)
+    where
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+        I: IntoIterator<Item = DescriptorSet<S>>,
+    {
+        debug_assert!(self.sets_cache.is_empty());
+        self.sets_cache.extend(sets);
+        self.record_freed_sets(TAG_FREE);
+        self.allocator.free(device, self.sets_cache.drain(..));
+    }
+
+    /// Free descriptor sets in any order and record the call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::free_unordered`.
+    pub unsafe fn free_unordered<D, I>(&mut self, device: &D, sets: I)
+    where
+        D: DescriptorDeviceV2<Pool = P, Set = S>,
+        I: IntoIterator<Item = DescriptorSet<S>>,
+    {
+        debug_assert!(self.sets_cache.is_empty());
+        self.sets_cache.extend(sets);
+        self.record_freed_sets(TAG_FREE_UNORDERED);
+        self.allocator
+            .free_unordered(device, self.sets_cache.drain(..));
+    }
+
+    /// Perform cleanup to allow resources reuse and record the call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::cleanup`.
+    pub unsafe fn cleanup(&mut self, device: &impl DescriptorDeviceV2<Pool = P, Set = S>) {
+        self.allocator.cleanup(device);
+
+        self.buffer.push(TAG_CLEANUP);
+        self.flush_buffer();
+    }
+
+    /// Discard all pools without calling the device and record the call.
+    ///
+    /// See `DescriptorAllocator::forget_all`.
+    pub fn forget_all(&mut self) {
+        self.allocator.forget_all();
+
+        self.buffer.push(TAG_FORGET_ALL);
+        self.flush_buffer();
+    }
+
+    /// Destroy all pools and record the call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `DescriptorAllocator::destroy_all`.
+    pub unsafe fn destroy_all(&mut self, device: &impl DescriptorDeviceV2<Pool = P, Set = S>) {
+        self.allocator.destroy_all(device);
+
+        self.buffer.push(TAG_DESTROY_ALL);
+        self.flush_buffer();
+    }
+
+    /// Records sets from `sets_cache` grouped by pool.
+    fn record_freed_sets(&mut self, tag: u8) {
+        let mut groups: Vec<TraceFreedSets> = Vec::new();
+        for set in &self.sets_cache {
+            match groups.last_mut() {
+                Some(group)
+                    if group.descriptor_count == set.size
+                        && group.update_after_bind == set.update_after_bind
+                        && group.host_only == set.host_only
+                        && group.lifetime == set.lifetime
+                        && group.epoch == set.epoch
+                        && group.pool_id == set.pool_id =>
+                {
+                    group.count += 1;
+                }
+                _ => groups.push(TraceFreedSets {
+                    descriptor_count: set.size,
+                    update_after_bind: set.update_after_bind,
+                    host_only: set.host_only,
+                    lifetime: set.lifetime,
+                    epoch: set.epoch,
+                    pool_id: set.pool_id,
+                    count: 1,
+                }),
+            }
+        }
+
+        self.buffer.push(tag);
+        write_varint(&mut self.buffer, groups.len() as u64);
+        for group in &groups {
+            write_count(&mut self.buffer, &group.descriptor_count);
+            self.buffer.push(sets_bits(
+                group.update_after_bind,
+                group.host_only,
+                group.lifetime,
+            ));
+            write_varint(&mut self.buffer, group.epoch);
+            write_varint(&mut self.buffer, group.pool_id);
+            write_varint(&mut self.buffer, group.count.into());
+        }
+        self.flush_buffer();
+    }
+
+    fn flush_buffer(&mut self) {
+        if self.error.is_none() {
+            if let Err(err) = self.writer.write_all(&self.buffer) {
+                self.error = Some(err);
+            }
+        }
+        self.buffer.clear();
+    }
+}
+
+/// Reader of traces produced by `RecordingAllocator`.
+#[derive(Debug)]
+pub struct TraceReader<R> {
+    reader: R,
+    max_update_after_bind_descriptors_in_all_pools: u32,
+}
+
+impl<R> TraceReader<R>
+where
+    R: Read,
+{
+    /// Reads trace header.
+    pub fn new(mut reader: R) -> Result<Self, TraceError> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC || header[4] != VERSION {
+            return Err(TraceError::InvalidFormat);
+        }
+
+        let max_update_after_bind_descriptors_in_all_pools = read_u32(&mut reader)?;
+
+        Ok(TraceReader {
+            reader,
+            max_update_after_bind_descriptors_in_all_pools,
+        })
+    }
+
+    /// Returns limit the traced allocator was created with.
+    pub fn max_update_after_bind_descriptors_in_all_pools(&self) -> u32 {
+        self.max_update_after_bind_descriptors_in_all_pools
+    }
+
+    /// Reads next record.
+    /// Returns `None` at the end of the trace.
+    pub fn next_record(&mut self) -> Result<Option<TraceRecord>, TraceError> {
+        let mut tag = [0];
+        loop {
+            match self.reader.read(&mut tag) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let record = match tag[0] {
+            TAG_ALLOCATE => {
+                let descriptor_count = read_count(&mut self.reader)?;
+                let flags = read_flags(&mut self.reader)?;
+                let count = read_u32(&mut self.reader)?;
+                let lifetime = read_lifetime(&mut self.reader)?;
+                let result = read_result(&mut self.reader)?;
+                TraceRecord::Allocate {
+                    descriptor_count,
+                    flags,
+                    count,
+                    lifetime,
+                    result,
+                }
+            }
+            TAG_ALLOCATE_INTO => {
+                let descriptor_count = read_count(&mut self.reader)?;
+                let flags = read_flags(&mut self.reader)?;
+                let count = read_u32(&mut self.reader)?;
+                let lifetime = read_lifetime(&mut self.reader)?;
+                let result = read_result(&mut self.reader)?;
+                TraceRecord::AllocateInto {
+                    descriptor_count,
+                    flags,
+                    count,
+                    lifetime,
+                    result,
+                }
+            }
+            TAG_ALLOCATE_ONE => {
+                let descriptor_count = read_count(&mut self.reader)?;
+                let flags = read_flags(&mut self.reader)?;
+                let lifetime = read_lifetime(&mut self.reader)?;
+                let result = read_result(&mut self.reader)?;
+                TraceRecord::AllocateOne {
+                    descriptor_count,
+                    flags,
+                    lifetime,
+                    result,
+                }
+            }
+            TAG_FREE => TraceRecord::Free {
+                sets: read_freed_sets(&mut self.reader)?,
+            },
+            TAG_FREE_UNORDERED => TraceRecord::FreeUnordered {
+                sets: read_freed_sets(&mut self.reader)?,
+            },
+            TAG_CLEANUP => TraceRecord::Cleanup,
+            TAG_FORGET_ALL => TraceRecord::ForgetAll,
+            TAG_DESTROY_ALL => TraceRecord::DestroyAll,
+            TAG_SET_DEVICE_LIMITS => {
+                let mut limits = DeviceLimits::UNBOUNDED;
+                for limit in limit_fields_mut(&mut limits) {
+                    *limit = read_u32(&mut self.reader)?;
+                }
+                TraceRecord::SetDeviceLimits(limits)
+            }
+            TAG_SET_POOL_SIZING => match read_u8(&mut self.reader)? {
+                0 => TraceRecord::SetPoolSizing(PoolSizing::Fixed),
+                1 => TraceRecord::SetPoolSizing(PoolSizing::Adaptive),
+                _ => return Err(TraceError::InvalidFormat),
+            },
+            TAG_SET_OUT_OF_MEMORY_POLICY => TraceRecord::SetOutOfMemoryPolicy {
+                retries: read_u32(&mut self.reader)?,
+                cleanup: read_bool(&mut self.reader)?,
+                release_memory: read_bool(&mut self.reader)?,
+            },
+            TAG_IMPORT_POOL_SIZE_PROFILE => {
+                let mut profile = PoolSizeProfile::default();
+                for _ in 0..read_u32(&mut self.reader)? {
+                    let size = read_count(&mut self.reader)?;
+                    let (update_after_bind, host_only, lifetime) =
+                        read_sets_bits(&mut self.reader)?;
+                    let peak = read_u32(&mut self.reader)?;
+                    profile.entries.push(PoolSizeProfileEntry {
+                        size,
+                        update_after_bind,
+                        host_only,
+                        lifetime,
+                        peak,
+                    });
+                }
+                TraceRecord::ImportPoolSizeProfile(profile)
+            }
+            _ => return Err(TraceError::InvalidFormat),
+        };
+
+        Ok(Some(record))
+    }
+}
+
+/// Result of trace replay.
+#[derive(Debug)]
+pub struct ReplayReport {
+    /// Number of replayed allocation calls.
+    pub allocations: u64,
+
+    /// Number of replayed `free` and `free_unordered` calls.
+    pub frees: u64,
+
+    /// Number of replayed `cleanup` calls.
+    pub cleanups: u64,
+
+    /// Number of replayed `forget_all` and `destroy_all` calls.
+    pub resets: u64,
+
+    /// Number of replayed calls that configure the allocator.
+    pub configs: u64,
+
+    /// Number of records where replay diverged from the trace.
+    ///
+    /// Either allocation outcome differs from the recorded one
+    /// or sets recorded as freed were not allocated during replay.
+    pub mismatches: u64,
+
+    /// Number of allocation records that failed with out-of-memory error reported by device.
+    ///
+    /// `MockDevice` never runs out of memory during replay,
+    /// so outcomes of such records are not compared and not counted as mismatches.
+    /// Sets allocated by replay instead end up in `live_sets`.
+    pub device_errors: u64,
+
+    /// Sets that were still allocated at the end of the trace.
+    /// Must be freed by the caller.
+    pub live_sets: Vec<DescriptorSet<MockSet>>,
+}
+
+/// Replays trace against `MockDevice`.
+///
+/// Sets freed by the trace are matched with replayed sets by bucket, epoch and pool id,
+/// which is all allocator bookkeeping depends on.
+/// Pools discarded by `forget_all` are leaked by `device`.
+///
+/// Out-of-memory errors reported by recorded device are not injected,
+/// see `ReplayReport::device_errors`.
+pub fn replay<R>(
+    trace: &mut TraceReader<R>,
+    allocator: &mut DescriptorAllocator<MockPool, MockSet>,
+    device: &MockDevice,
+) -> Result<ReplayReport, TraceError>
+where
+    R: Read,
+{
+    let mut report = ReplayReport {
+        allocations: 0,
+        frees: 0,
+        cleanups: 0,
+        resets: 0,
+        configs: 0,
+        mismatches: 0,
+        device_errors: 0,
+        live_sets: Vec::new(),
+    };
+
+    let mut live: HashMap<LiveKey, Vec<DescriptorSet<MockSet>>> = HashMap::new();
+    let mut allocated = Vec::new();
+    let mut freed = Vec::new();
+
+    // Epoch of replayed allocator relative to the start of replay,
+    // matching epochs of the recorded allocator.
+    let mut epoch = 0;
+
+    while let Some(record) = trace.next_record()? {
+        match record {
+            TraceRecord::Allocate {
+                descriptor_count,
+                flags,
+                count,
+                lifetime,
+                result: expected,
+            } => {
+                let result = unsafe {
+                    allocator.allocate_with_lifetime(
+                        device,
+                        &descriptor_count,
+                        flags,
+                        &descriptor_count,
+                        count,
+                        lifetime,
+                    )
+                };
+
+                compare_result(&mut report, &result, expected);
+                report.allocations += 1;
+                allocated.extend(result.into_iter().flatten());
+            }
+            TraceRecord::AllocateInto {
+                descriptor_count,
+                flags,
+                count,
+                lifetime,
+                result: expected,
+            } => {
+                let result = unsafe {
+                    allocator.allocate_into(
+                        device,
+                        &descriptor_count,
+                        flags,
+                        &descriptor_count,
+                        count,
+                        lifetime,
+                        &mut allocated,
+                    )
+                };
+
+                compare_result(&mut report, &result, expected);
+                report.allocations += 1;
+            }
+            TraceRecord::AllocateOne {
+                descriptor_count,
+                flags,
+                lifetime,
+                result: expected,
+            } => {
+                let result = unsafe {
+                    allocator.allocate_one(
+                        device,
+                        &descriptor_count,
+                        flags,
+                        &descriptor_count,
+                        lifetime,
+                    )
+                };
+
+                compare_result(&mut report, &result, expected);
+                report.allocations += 1;
+                allocated.extend(result);
+            }
+            TraceRecord::Free { sets } => {
+                if !take_freed_sets(&mut live, sets, &mut freed) {
+                    report.mismatches += 1;
+                }
+                report.frees += 1;
+                unsafe { allocator.free(device, freed.drain(..)) };
+            }
+            TraceRecord::FreeUnordered { sets } => {
+                if !take_freed_sets(&mut live, sets, &mut freed) {
+                    report.mismatches += 1;
+                }
+                report.frees += 1;
+                unsafe { allocator.free_unordered(device, freed.drain(..)) };
+            }
+            TraceRecord::Cleanup => {
+                report.cleanups += 1;
+                unsafe { allocator.cleanup(device) };
+            }
+            TraceRecord::ForgetAll => {
+                report.resets += 1;
+                allocator.forget_all();
+                epoch += 1;
+            }
+            TraceRecord::DestroyAll => {
+                report.resets += 1;
+                unsafe { allocator.destroy_all(device) };
+                epoch += 1;
+            }
+            TraceRecord::SetDeviceLimits(limits) => {
+                report.configs += 1;
+                allocator.set_device_limits(limits);
+            }
+            TraceRecord::SetPoolSizing(sizing) => {
+                report.configs += 1;
+                allocator.set_pool_sizing(sizing);
+            }
+            TraceRecord::SetOutOfMemoryPolicy {
+                retries, cleanup, ..
+            } => {
+                report.configs += 1;
+                allocator.set_out_of_memory_policy(OutOfMemoryPolicy {
+                    retries,
+                    cleanup,
+                    release_memory: None,
+                });
+            }
+            TraceRecord::ImportPoolSizeProfile(profile) => {
+                report.configs += 1;
+                allocator.import_pool_size_profile(&profile);
+            }
+        }
+
+        for set in allocated.drain(..) {
+            let key = LiveKey {
+                descriptor_count: set.size,
+                update_after_bind: set.update_after_bind,
+                host_only: set.host_only,
+                lifetime: set.lifetime,
+                epoch,
+                pool_id: set.pool_id,
+            };
+            live.entry(key).or_default().push(set);
+        }
+    }
+
+    report.live_sets = live.into_iter().flat_map(|(_, sets)| sets).collect();
+    Ok(report)
+}
+
+/// Compares outcome of replayed allocation with the recorded one.
+fn compare_result<T>(
+    report: &mut ReplayReport,
+    result: &Result<T, AllocationError>,
+    expected: TraceResult,
+) {
+    match expected {
+        TraceResult::OutOfDeviceMemory | TraceResult::OutOfHostMemory => report.device_errors += 1,
+        _ if TraceResult::from(result) != expected => report.mismatches += 1,
+        _ => {}
+    }
+}
+
+/// Key of replayed sets that can be matched with recorded freed sets.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct LiveKey {
+    descriptor_count: DescriptorTotalCount,
+    update_after_bind: bool,
+    host_only: bool,
+    lifetime: DescriptorSetLifetime,
+    epoch: u64,
+    pool_id: u64,
+}
+
+/// Moves replayed sets matching recorded `groups` into `freed`.
+/// Returns `false` if some of the recorded sets were not allocated during replay.
+fn take_freed_sets(
+    live: &mut HashMap<LiveKey, Vec<DescriptorSet<MockSet>>>,
+    groups: Vec<TraceFreedSets>,
+    freed: &mut Vec<DescriptorSet<MockSet>>,
+) -> bool {
+    let mut matched = true;
+
+    for group in groups {
+        let key = LiveKey {
+            descriptor_count: group.descriptor_count,
+            update_after_bind: group.update_after_bind,
+            host_only: group.host_only,
+            lifetime: group.lifetime,
+            epoch: group.epoch,
+            pool_id: group.pool_id,
+        };
+        let sets = live.entry(key).or_default();
+        let count = (group.count as usize).min(sets.len());
+        matched &= count == group.count as usize;
+        freed.extend(sets.drain(sets.len() - count..));
+    }
+
+    matched
+}
+
+fn write_allocation(
+    buffer: &mut Vec<u8>,
+    descriptor_count: &DescriptorTotalCount,
+    flags: DescriptorSetLayoutCreateFlags,
+    count: Option<u32>,
+    lifetime: DescriptorSetLifetime,
+    result: TraceResult,
+) {
+    write_count(buffer, descriptor_count);
+    write_varint(buffer, flags.bits().into());
+    if let Some(count) = count {
+        write_varint(buffer, count.into());
+    }
+    buffer.push(match lifetime {
+        DescriptorSetLifetime::Persistent => 0,
+        DescriptorSetLifetime::Transient => 1,
+    });
+    buffer.push(result_code(result));
+}
+
+fn read_flags(reader: &mut impl Read) -> Result<DescriptorSetLayoutCreateFlags, TraceError> {
+    DescriptorSetLayoutCreateFlags::from_bits(read_u32(reader)?).ok_or(TraceError::InvalidFormat)
+}
+
+fn read_lifetime(reader: &mut impl Read) -> Result<DescriptorSetLifetime, TraceError> {
+    match read_u8(reader)? {
+        0 => Ok(DescriptorSetLifetime::Persistent),
+        1 => Ok(DescriptorSetLifetime::Transient),
+        _ => Err(TraceError::InvalidFormat),
+    }
+}
+
+fn read_result(reader: &mut impl Read) -> Result<TraceResult, TraceError> {
+    match read_u8(reader)? {
+        0 => Ok(TraceResult::Success),
+        1 => Ok(TraceResult::OutOfDeviceMemory),
+        2 => Ok(TraceResult::OutOfHostMemory),
+        3 => Ok(TraceResult::Fragmentation),
+        4 => Ok(TraceResult::LimitExceeded),
+        5 => Ok(TraceResult::Overflow),
+        6 => Ok(TraceResult::InvalidLayoutFlags),
+        _ => Err(TraceError::InvalidFormat),
+    }
+}
+
+fn read_freed_sets(reader: &mut impl Read) -> Result<Vec<TraceFreedSets>, TraceError> {
+    let groups = read_u32(reader)?;
+    let mut sets = Vec::new();
+    for _ in 0..groups {
+        let descriptor_count = read_count(reader)?;
+        let (update_after_bind, host_only, lifetime) = read_sets_bits(reader)?;
+        let epoch = read_varint(reader)?;
+        let pool_id = read_varint(reader)?;
+        let count = read_u32(reader)?;
+        sets.push(TraceFreedSets {
+            descriptor_count,
+            update_after_bind,
+            host_only,
+            lifetime,
+            epoch,
+            pool_id,
+            count,
+        });
+    }
+    Ok(sets)
+}
+
+fn result_code(result: TraceResult) -> u8 {
+    match result {
+        TraceResult::Success => 0,
+        TraceResult::OutOfDeviceMemory => 1,
+        TraceResult::OutOfHostMemory => 2,
+        TraceResult::Fragmentation => 3,
+        TraceResult::LimitExceeded => 4,
+        TraceResult::Overflow => 5,
+        TraceResult::InvalidLayoutFlags => 6,
+    }
+}
+
+fn sets_bits(update_after_bind: bool, host_only: bool, lifetime: DescriptorSetLifetime) -> u8 {
+    let mut bits = 0;
+    if update_after_bind {
+        bits |= SETS_UPDATE_AFTER_BIND;
+    }
+    if host_only {
+        bits |= SETS_HOST_ONLY;
+    }
+    if lifetime == DescriptorSetLifetime::Transient {
+        bits |= SETS_TRANSIENT;
+    }
+    bits
+}
+
+fn read_sets_bits(
+    reader: &mut impl Read,
+) -> Result<(bool, bool, DescriptorSetLifetime), TraceError> {
+    let bits = read_u8(reader)?;
+
+    // Sets cannot be both update-after-bind and host-only.
+    if bits & !(SETS_UPDATE_AFTER_BIND | SETS_HOST_ONLY | SETS_TRANSIENT) != 0
+        || bits & (SETS_UPDATE_AFTER_BIND | SETS_HOST_ONLY)
+            == SETS_UPDATE_AFTER_BIND | SETS_HOST_ONLY
+    {
+        return Err(TraceError::InvalidFormat);
+    }
+
+    let lifetime = if bits & SETS_TRANSIENT != 0 {
+        DescriptorSetLifetime::Transient
+    } else {
+        DescriptorSetLifetime::Persistent
+    };
+
+    Ok((
+        bits & SETS_UPDATE_AFTER_BIND != 0,
+        bits & SETS_HOST_ONLY != 0,
+        lifetime,
+    ))
+}
+
+fn limit_fields(limits: &DeviceLimits) -> [u32; 22] {
+    let mut limits = *limits;
+    limit_fields_mut(&mut limits).map(|limit| *limit)
+}
+
+fn limit_fields_mut(limits: &mut DeviceLimits) -> [&mut u32; 22] {
+    [
+        &mut limits.max_descriptor_set_samplers,
+        &mut limits.max_descriptor_set_uniform_buffers,
+        &mut limits.max_descriptor_set_uniform_buffers_dynamic,
+        &mut limits.max_descriptor_set_storage_buffers,
+        &mut limits.max_descriptor_set_storage_buffers_dynamic,
+        &mut limits.max_descriptor_set_sampled_images,
+        &mut limits.max_descriptor_set_storage_images,
+        &mut limits.max_descriptor_set_input_attachments,
+        &mut limits.max_descriptor_set_acceleration_structures,
+        &mut limits.max_descriptor_set_inline_uniform_blocks,
+        &mut limits.max_descriptor_set_update_after_bind_samplers,
+        &mut limits.max_descriptor_set_update_after_bind_uniform_buffers,
+        &mut limits.max_descriptor_set_update_after_bind_uniform_buffers_dynamic,
+        &mut limits.max_descriptor_set_update_after_bind_storage_buffers,
+        &mut limits.max_descriptor_set_update_after_bind_storage_buffers_dynamic,
+        &mut limits.max_descriptor_set_update_after_bind_sampled_images,
+        &mut limits.max_descriptor_set_update_after_bind_storage_images,
+        &mut limits.max_descriptor_set_update_after_bind_input_attachments,
+        &mut limits.max_descriptor_set_update_after_bind_acceleration_structures,
+        &mut limits.max_descriptor_set_update_after_bind_inline_uniform_blocks,
+        &mut limits.max_pool_sets,
+        &mut limits.max_pool_descriptors,
+    ]
+}
+
+/// Code of `DescriptorKind::Vendor`, followed by raw descriptor type.
+/// Smaller codes are indices in `DescriptorKind::KNOWN`.
+const KIND_VENDOR: u32 = 14;
+
+fn write_count(buffer: &mut Vec<u8>, count: &DescriptorTotalCount) {
+    write_varint(buffer, count.iter().count() as u64);
+    for (kind, count) in count.iter() {
+        match kind.index() {
+            Some(index) => write_varint(buffer, index as u64),
+            None => {
+                write_varint(buffer, KIND_VENDOR.into());
+                if let DescriptorKind::Vendor(raw) = kind {
+                    write_varint(buffer, (raw as u32).into());
+                }
+            }
+        }
+        write_varint(buffer, count.into());
+    }
+}
+
+fn read_count(reader: &mut impl Read) -> Result<DescriptorTotalCount, TraceError> {
+    let mut result = DescriptorTotalCount::new();
+    let mut vendor_kinds = 0;
+
+    for _ in 0..read_u32(reader)? {
+        let kind = match read_u32(reader)? {
+            KIND_VENDOR => {
+                vendor_kinds += 1;
+                DescriptorKind::Vendor(read_u32(reader)? as i32)
+            }
+            code => *DescriptorKind::KNOWN
+                .get(code as usize)
+                .ok_or(TraceError::InvalidFormat)?,
+        };
+
+        let count = read_u32(reader)?;
+
+        // Writer emits each kind once with non-zero count.
+        if count == 0 || result.get(kind) != 0 || vendor_kinds > MAX_VENDOR_DESCRIPTOR_KINDS {
+            return Err(TraceError::InvalidFormat);
+        }
+        result.set(kind, count);
+    }
+
+    Ok(result)
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn read_varint(reader: &mut impl Read) -> Result<u64, TraceError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(TraceError::InvalidFormat)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, TraceError> {
+    let value = read_varint(reader)?;
+    u32::try_from(value).map_err(|_| TraceError::InvalidFormat)
+}
+
+fn read_bool(reader: &mut impl Read) -> Result<bool, TraceError> {
+    match read_u8(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(TraceError::InvalidFormat),
+    }
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8, TraceError> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
@@ -0,0 +1,88 @@
+//! Tests that `allocate_into` and `allocate_one` do not allocate memory
+//! once allocator is warmed up.
+
+use {
+    gpu_descriptor::{
+        mock::MockDevice, DescriptorAllocator, DescriptorKind, DescriptorSetLayoutCreateFlags,
+        DescriptorSetLifetime, DescriptorTotalCount,
+    },
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+    },
+};
+
+/// System allocator that counts allocations made by current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn warmed_up_allocations_do_not_allocate_memory() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = DescriptorTotalCount::new().with(DescriptorKind::UniformBuffer, 1);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+    let lifetime = DescriptorSetLifetime::Transient;
+
+    // Warm up with `allocate` that hands out sets in a new `Vec`.
+    // Internal cache must keep its capacity for the following calls.
+    let sets = unsafe {
+        allocator
+            .allocate_with_lifetime(&device, &layout, empty, &layout, 8, lifetime)
+            .unwrap()
+    };
+    unsafe {
+        allocator.free(&device, sets);
+    }
+
+    let mut sets = Vec::with_capacity(16);
+    for _ in 0..4 {
+        let before = allocations();
+        unsafe {
+            allocator
+                .allocate_into(&device, &layout, empty, &layout, 8, lifetime, &mut sets)
+                .unwrap();
+            let set = allocator
+                .allocate_one(&device, &layout, empty, &layout, lifetime)
+                .unwrap();
+            sets.push(set);
+        }
+        assert_eq!(allocations(), before);
+        assert_eq!(sets.len(), 9);
+
+        unsafe {
+            allocator.free(&device, sets.drain(..));
+        }
+    }
+
+    unsafe {
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+}
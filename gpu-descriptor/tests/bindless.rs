@@ -0,0 +1,112 @@
+//! Tests of `BindlessHeap`.
+
+use gpu_descriptor::{
+    mock::MockDevice, BindlessError, BindlessHeap, DescriptorAllocator, DescriptorKind,
+    DescriptorSetLayoutCreateFlags, DescriptorTotalCount, DescriptorType,
+};
+
+fn heap_layout() -> DescriptorTotalCount {
+    DescriptorTotalCount::new()
+        .with(DescriptorKind::SampledImage, 4)
+        .with(DescriptorKind::StorageBuffer, 2)
+}
+
+#[test]
+fn freed_slots_are_reused_after_frame_completes() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = heap_layout();
+
+    let mut heap = unsafe {
+        BindlessHeap::new(
+            &mut allocator,
+            &device,
+            &layout,
+            DescriptorSetLayoutCreateFlags::empty(),
+            &layout,
+        )
+    }
+    .unwrap();
+
+    let slots: Vec<_> = (0..4)
+        .map(|_| heap.allocate(DescriptorType::SampledImage).unwrap())
+        .collect();
+    assert!(matches!(
+        heap.allocate(DescriptorType::SampledImage),
+        Err(BindlessError::OutOfSlots)
+    ));
+
+    heap.advance(0);
+    let frame = heap.frame();
+    heap.free(slots[2]).unwrap();
+    assert!(!heap.is_valid(slots[2]));
+    assert_eq!(heap.statistics(DescriptorType::SampledImage).pending, 1);
+
+    // Frame the slot was freed in is not completed yet.
+    heap.advance(frame - 1);
+    assert!(matches!(
+        heap.allocate(DescriptorType::SampledImage),
+        Err(BindlessError::OutOfSlots)
+    ));
+
+    heap.advance(frame);
+    let reused = heap.allocate(DescriptorType::SampledImage).unwrap();
+    assert_eq!(reused.index(), slots[2].index());
+    assert_ne!(reused.generation(), slots[2].generation());
+    assert!(heap.is_valid(reused));
+
+    let statistics = heap.statistics(DescriptorType::SampledImage);
+    assert_eq!(statistics.capacity, 4);
+    assert_eq!(statistics.allocated, 4);
+    assert_eq!(statistics.pending, 0);
+
+    unsafe {
+        heap.destroy(&mut allocator, &device);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+}
+
+#[test]
+fn stale_and_foreign_slots_are_rejected() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = heap_layout();
+
+    let mut heap = unsafe {
+        BindlessHeap::new(
+            &mut allocator,
+            &device,
+            &layout,
+            DescriptorSetLayoutCreateFlags::empty(),
+            &layout,
+        )
+    }
+    .unwrap();
+
+    let slot = heap.allocate(DescriptorType::StorageBuffer).unwrap();
+    heap.free(slot).unwrap();
+    assert!(matches!(heap.free(slot), Err(BindlessError::StaleSlot)));
+
+    // Reallocated slot does not revive old handle.
+    heap.advance(heap.frame());
+    let reused = heap.allocate(DescriptorType::StorageBuffer).unwrap();
+    assert_eq!(reused.index(), slot.index());
+    assert!(!heap.is_valid(slot));
+    assert!(matches!(heap.free(slot), Err(BindlessError::StaleSlot)));
+    heap.free(reused).unwrap();
+
+    assert!(matches!(
+        heap.allocate(DescriptorType::UniformBuffer),
+        Err(BindlessError::UnknownType)
+    ));
+    assert_eq!(
+        heap.statistics(DescriptorType::UniformBuffer),
+        Default::default()
+    );
+
+    unsafe {
+        heap.destroy(&mut allocator, &device);
+        allocator.cleanup(&device);
+    }
+}
@@ -0,0 +1,325 @@
+//! Tests of `DescriptorBufferAllocator`.
+
+use {
+    gpu_descriptor::{
+        BufferAllocationError, CreateBufferError, DescriptorBufferAllocator,
+        DescriptorBufferAllocatorListener, DescriptorBufferBucketInfo, DescriptorBufferDevice,
+        DescriptorBufferInfo, DescriptorBufferUsageFlags,
+    },
+    std::{
+        cell::{Cell, RefCell},
+        collections::HashSet,
+        sync::{Arc, Mutex},
+    },
+};
+
+const ALIGNMENT: u64 = 64;
+
+/// Device where layouts are their sizes in bytes and buffers are sequential ids.
+/// Bindings are placed 16 bytes apart.
+#[derive(Default)]
+struct BufferDevice {
+    next_id: Cell<u32>,
+    live: RefCell<Vec<(u32, u64)>>,
+}
+
+impl DescriptorBufferDevice<u64, u32> for BufferDevice {
+    fn descriptor_buffer_offset_alignment(&self) -> u64 {
+        ALIGNMENT
+    }
+
+    unsafe fn get_descriptor_set_layout_size(&self, layout: &u64) -> u64 {
+        *layout
+    }
+
+    unsafe fn get_descriptor_set_layout_binding_offset(&self, _layout: &u64, binding: u32) -> u64 {
+        u64::from(binding) * 16
+    }
+
+    unsafe fn create_descriptor_buffer(
+        &self,
+        size: u64,
+        _usage: DescriptorBufferUsageFlags,
+    ) -> Result<u32, CreateBufferError> {
+        assert_ne!(size, 0, "Buffer size must not be zero");
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.live.borrow_mut().push((id, size));
+        Ok(id)
+    }
+
+    unsafe fn destroy_descriptor_buffer(&self, buffer: u32) {
+        let mut live = self.live.borrow_mut();
+        let index = live
+            .iter()
+            .position(|&(id, _)| id == buffer)
+            .expect("Buffer must be alive");
+        live.swap_remove(index);
+    }
+}
+
+impl BufferDevice {
+    fn buffer_size(&self, buffer: u32) -> u64 {
+        self.live
+            .borrow()
+            .iter()
+            .find(|&&(id, _)| id == buffer)
+            .expect("Buffer must be alive")
+            .1
+    }
+}
+
+#[test]
+fn ranges_are_aligned_and_disjoint() {
+    let device = BufferDevice::default();
+    let mut allocator = DescriptorBufferAllocator::new();
+
+    unsafe {
+        let ranges = allocator
+            .allocate(&device, &100, DescriptorBufferUsageFlags::RESOURCE, 10)
+            .unwrap();
+
+        let mut offsets = HashSet::new();
+        for range in &ranges {
+            assert_eq!(range.size(), 100);
+            assert_eq!(range.offset() % ALIGNMENT, 0);
+            assert!(range.offset() + range.size() <= device.buffer_size(*range.buffer()));
+            assert!(offsets.insert((*range.buffer(), range.offset())));
+        }
+
+        // Size of 100 is padded to stride of 128 bytes.
+        let mut sorted: Vec<_> = offsets.iter().map(|&(_, offset)| offset).collect();
+        sorted.sort_unstable();
+        assert!(sorted.windows(2).all(|w| w[1] - w[0] == 128));
+
+        let statistics = allocator.statistics();
+        assert_eq!(statistics.allocated_sets, 10);
+        assert_eq!(statistics.allocated_bytes, 10 * 128);
+        assert_eq!(statistics.buffers, 1);
+
+        allocator.free(&device, ranges);
+        allocator.cleanup(&device);
+    }
+
+    assert_eq!(allocator.statistics().buffers, 0);
+    assert!(device.live.borrow().is_empty());
+}
+
+#[test]
+fn layouts_of_different_usage_do_not_share_buffers() {
+    let device = BufferDevice::default();
+    let mut allocator = DescriptorBufferAllocator::new();
+
+    unsafe {
+        let samplers = allocator
+            .allocate(&device, &64, DescriptorBufferUsageFlags::SAMPLER, 1)
+            .unwrap();
+        let resources = allocator
+            .allocate(&device, &64, DescriptorBufferUsageFlags::RESOURCE, 1)
+            .unwrap();
+        assert_ne!(samplers[0].buffer(), resources[0].buffer());
+
+        allocator.free(&device, samplers);
+        allocator.free(&device, resources);
+        allocator.cleanup(&device);
+    }
+}
+
+#[test]
+fn freed_slots_are_reused() {
+    let device = BufferDevice::default();
+    let mut allocator = DescriptorBufferAllocator::new();
+
+    unsafe {
+        let mut ranges = allocator
+            .allocate(&device, &32, DescriptorBufferUsageFlags::RESOURCE, 4)
+            .unwrap();
+        let freed = ranges.swap_remove(1);
+        let freed_slot = (*freed.buffer(), freed.offset());
+        allocator.free(&device, Some(freed));
+
+        // Freed slot is handed out again instead of a fresh one.
+        let reused = allocator
+            .allocate(&device, &32, DescriptorBufferUsageFlags::RESOURCE, 1)
+            .unwrap();
+        assert_eq!((*reused[0].buffer(), reused[0].offset()), freed_slot);
+        assert_eq!(device.live.borrow().len(), 1);
+
+        allocator.free(&device, ranges);
+        allocator.free(&device, reused);
+        allocator.cleanup(&device);
+    }
+
+    assert!(device.live.borrow().is_empty());
+}
+
+#[test]
+fn stride_overflow_is_reported() {
+    let device = BufferDevice::default();
+    let mut allocator = DescriptorBufferAllocator::new();
+
+    unsafe {
+        let result =
+            allocator.allocate(&device, &u64::MAX, DescriptorBufferUsageFlags::RESOURCE, 1);
+        assert!(matches!(result, Err(BufferAllocationError::Overflow)));
+    }
+
+    assert_eq!(allocator.statistics().buffers, 0);
+    assert!(device.live.borrow().is_empty());
+}
+
+#[test]
+fn binding_offsets_are_relative_to_buffer() {
+    let device = BufferDevice::default();
+    let mut allocator = DescriptorBufferAllocator::new();
+
+    unsafe {
+        let ranges = allocator
+            .allocate(&device, &100, DescriptorBufferUsageFlags::RESOURCE, 2)
+            .unwrap();
+
+        for range in &ranges {
+            assert_eq!(range.binding_offset(&device, &100, 0), range.offset());
+            assert_eq!(range.binding_offset(&device, &100, 3), range.offset() + 48);
+        }
+
+        allocator.free(&device, ranges);
+        allocator.cleanup(&device);
+    }
+}
+
+#[test]
+fn bucket_statistics_track_peak() {
+    let device = BufferDevice::default();
+    let mut allocator = DescriptorBufferAllocator::new();
+
+    unsafe {
+        let mut ranges = allocator
+            .allocate(&device, &100, DescriptorBufferUsageFlags::RESOURCE, 10)
+            .unwrap();
+        let samplers = allocator
+            .allocate(&device, &64, DescriptorBufferUsageFlags::SAMPLER, 1)
+            .unwrap();
+        allocator.free(&device, ranges.drain(4..));
+
+        let mut buckets: Vec<_> = allocator.bucket_statistics().collect();
+        buckets.sort_unstable_by_key(|bucket| bucket.stride);
+        assert_eq!(buckets.len(), 2);
+
+        assert_eq!(buckets[0].stride, 64);
+        assert_eq!(buckets[0].usage, DescriptorBufferUsageFlags::SAMPLER);
+        assert_eq!(buckets[0].allocated_sets, 1);
+
+        assert_eq!(buckets[1].stride, 128);
+        assert_eq!(buckets[1].usage, DescriptorBufferUsageFlags::RESOURCE);
+        assert_eq!(buckets[1].buffers, 1);
+        assert_eq!(buckets[1].allocated_sets, 4);
+        assert_eq!(buckets[1].peak_sets, 10);
+        assert_eq!(
+            buckets[1].reserved_bytes,
+            device.buffer_size(*ranges[0].buffer())
+        );
+
+        allocator.free(&device, ranges);
+        allocator.free(&device, samplers);
+        allocator.cleanup(&device);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    BufferCreated(u64),
+    BufferDestroyed(u64),
+    Allocated(u64, u32),
+    Freed(u64, u32),
+    Error,
+}
+
+struct RecordingListener(Arc<Mutex<Vec<Event>>>);
+
+impl DescriptorBufferAllocatorListener for RecordingListener {
+    fn on_buffer_created(
+        &mut self,
+        _bucket: &DescriptorBufferBucketInfo,
+        buffer: &DescriptorBufferInfo,
+    ) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::BufferCreated(buffer.buffer_id));
+    }
+
+    fn on_buffer_destroyed(
+        &mut self,
+        _bucket: &DescriptorBufferBucketInfo,
+        buffer: &DescriptorBufferInfo,
+    ) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::BufferDestroyed(buffer.buffer_id));
+    }
+
+    fn on_ranges_allocated(
+        &mut self,
+        _bucket: &DescriptorBufferBucketInfo,
+        buffer_id: u64,
+        count: u32,
+    ) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::Allocated(buffer_id, count));
+    }
+
+    fn on_ranges_freed(
+        &mut self,
+        _bucket: &DescriptorBufferBucketInfo,
+        buffer_id: u64,
+        count: u32,
+    ) {
+        self.0.lock().unwrap().push(Event::Freed(buffer_id, count));
+    }
+
+    fn on_error(&mut self, _bucket: &DescriptorBufferBucketInfo, _error: &BufferAllocationError) {
+        self.0.lock().unwrap().push(Event::Error);
+    }
+}
+
+#[test]
+fn listener_observes_buffers() {
+    let device = BufferDevice::default();
+    let mut allocator = DescriptorBufferAllocator::new();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    allocator.set_listener(Box::new(RecordingListener(events.clone())));
+
+    unsafe {
+        let ranges = allocator
+            .allocate(&device, &32, DescriptorBufferUsageFlags::RESOURCE, 4)
+            .unwrap();
+        let more = allocator
+            .allocate(&device, &32, DescriptorBufferUsageFlags::RESOURCE, 2)
+            .unwrap();
+        assert!(allocator
+            .allocate(&device, &u64::MAX, DescriptorBufferUsageFlags::RESOURCE, 1)
+            .is_err());
+
+        allocator.free(&device, ranges);
+        allocator.free(&device, more);
+        allocator.cleanup(&device);
+    }
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Event::BufferCreated(0),
+            Event::Allocated(0, 4),
+            Event::Allocated(0, 2),
+            Event::Error,
+            Event::Freed(0, 4),
+            Event::Freed(0, 2),
+            Event::BufferDestroyed(0),
+        ]
+    );
+    assert!(allocator.take_listener().is_some());
+}
@@ -0,0 +1,101 @@
+//! Tests of `DescriptorAllocator::dump`.
+
+use gpu_descriptor::{
+    mock::MockDevice, DescriptorAllocator, DescriptorKind, DescriptorSetLayoutCreateFlags,
+    DescriptorSetLifetime, DescriptorTotalCount,
+};
+
+#[test]
+fn dump_display() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = DescriptorTotalCount::new()
+        .with(DescriptorKind::Sampler, 1)
+        .with(DescriptorKind::UniformBuffer, 2);
+
+    let sets = unsafe {
+        allocator.allocate(
+            &device,
+            &layout,
+            DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL,
+            &layout,
+            3,
+        )
+    }
+    .unwrap();
+
+    let dump = allocator.dump();
+    assert_eq!(dump.total_descriptors, 9);
+    assert_eq!(dump.buckets.len(), 1);
+
+    let bucket = &dump.buckets[0];
+    assert_eq!(bucket.size, layout);
+    assert!(bucket.host_only);
+    assert!(!bucket.update_after_bind);
+    assert_eq!(bucket.lifetime, DescriptorSetLifetime::Persistent);
+    assert_eq!(bucket.total, 3);
+    assert_eq!(bucket.pools.len(), 1);
+    assert_eq!(bucket.pools[0].allocated, 3);
+
+    let text = dump.to_string();
+    let mut lines = text.lines();
+    assert_eq!(
+        lines.next(),
+        Some("DescriptorAllocator: 1 buckets, 9 descriptors, 0/0 update-after-bind descriptors")
+    );
+    assert_eq!(
+        lines.next(),
+        Some("  bucket [Sampler: 1, UniformBuffer: 2] host-only Persistent: 3 sets (peak 3), pools 0..1")
+    );
+    let pool = format!(
+        "    pool 0: 3/{} sets allocated, {} available",
+        bucket.pools[0].max_sets, bucket.pools[0].available
+    );
+    assert_eq!(lines.next(), Some(pool.as_str()));
+    assert_eq!(lines.next(), None);
+
+    unsafe {
+        allocator.free(&device, sets);
+        allocator.cleanup(&device);
+    }
+
+    assert_eq!(
+        allocator.dump().to_string(),
+        "DescriptorAllocator: 0 buckets, 0 descriptors, 0/0 update-after-bind descriptors\n"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn dump_json() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = DescriptorTotalCount::new().with(DescriptorKind::StorageImage, 4);
+
+    let sets = unsafe {
+        allocator.allocate_with_lifetime(
+            &device,
+            &layout,
+            DescriptorSetLayoutCreateFlags::empty(),
+            &layout,
+            2,
+            DescriptorSetLifetime::Transient,
+        )
+    }
+    .unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&allocator.dump().to_json()).unwrap();
+    assert_eq!(json["total_descriptors"], 8);
+
+    let bucket = &json["buckets"][0];
+    assert_eq!(bucket["size"], serde_json::json!([["StorageImage", 4]]));
+    assert_eq!(bucket["lifetime"], "Transient");
+    assert_eq!(bucket["host_only"], false);
+    assert_eq!(bucket["total"], 2);
+    assert_eq!(bucket["pools"][0]["allocated"], 2);
+
+    unsafe {
+        allocator.free(&device, sets);
+        allocator.cleanup(&device);
+    }
+}
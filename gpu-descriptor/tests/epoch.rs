@@ -0,0 +1,90 @@
+//! Tests of sets outliving `forget_all` and `destroy_all`.
+
+use gpu_descriptor::{
+    mock::MockDevice, AllocationError, DescriptorAllocator, DescriptorKind,
+    DescriptorSetLayoutCreateFlags, DescriptorTotalCount,
+};
+
+fn layout() -> DescriptorTotalCount {
+    DescriptorTotalCount::new().with(DescriptorKind::StorageImage, 4)
+}
+
+#[test]
+fn sets_from_before_forget_all_are_ignored() {
+    let lost_device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(8);
+    let layout = layout();
+    let update_after_bind = DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND;
+
+    let old = unsafe {
+        allocator
+            .allocate(&lost_device, &layout, update_after_bind, &layout, 2)
+            .unwrap()
+    };
+    assert!(matches!(
+        unsafe { allocator.allocate(&lost_device, &layout, update_after_bind, &layout, 1) },
+        Err(AllocationError::Fragmentation)
+    ));
+
+    allocator.forget_all();
+    assert_eq!(allocator.statistics().pools, 0);
+    assert_eq!(allocator.statistics().allocated_sets, 0);
+
+    // Update-after-bind budget is available again on the new device.
+    let device = MockDevice::new();
+    let new = unsafe {
+        allocator
+            .allocate(&device, &layout, update_after_bind, &layout, 2)
+            .unwrap()
+    };
+    // New pool gets the same id as the forgotten one.
+    assert_eq!(old[0].raw().pool(), new[0].raw().pool());
+
+    unsafe {
+        allocator.free(&device, old.into_iter().chain(new));
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_sets(), 0);
+    assert_eq!(device.live_pools(), 0);
+
+    // Nothing is returned to the lost device.
+    assert_eq!(lost_device.live_sets(), 2);
+    assert_eq!(lost_device.pools_destroyed(), 0);
+}
+
+#[test]
+fn sets_from_before_destroy_all_are_ignored() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = layout();
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    let old = unsafe {
+        allocator
+            .allocate(&device, &layout, empty, &layout, 3)
+            .unwrap()
+    };
+
+    // Pools are destroyed with their sets.
+    unsafe {
+        allocator.destroy_all(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+    assert_eq!(device.live_sets(), 0);
+    assert_eq!(allocator.statistics().buckets, 0);
+
+    let new = unsafe {
+        allocator
+            .allocate(&device, &layout, empty, &layout, 1)
+            .unwrap()
+    };
+    assert_eq!(device.live_sets(), 1);
+
+    unsafe {
+        allocator.free_unordered(&device, old.into_iter().chain(new));
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_sets(), 0);
+    assert_eq!(device.live_pools(), 0);
+    assert_eq!(allocator.statistics(), Default::default());
+}
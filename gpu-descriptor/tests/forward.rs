@@ -0,0 +1,72 @@
+//! Tests of allocation through pointers to devices.
+
+use {
+    core::fmt::Debug,
+    gpu_descriptor::{
+        mock::MockDevice, DescriptorAllocator, DescriptorDeviceV2, DescriptorKind,
+        DescriptorSetLayoutCreateFlags, DescriptorTotalCount,
+    },
+    std::sync::Arc,
+};
+
+/// Allocates and frees sets through `device`.
+fn allocate_and_free<D>(device: &D)
+where
+    D: DescriptorDeviceV2<Layout = DescriptorTotalCount>,
+    D::Pool: Debug,
+    D::Set: Debug,
+{
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = DescriptorTotalCount::new().with(DescriptorKind::UniformBuffer, 2);
+
+    unsafe {
+        let sets = allocator
+            .allocate(
+                device,
+                &layout,
+                DescriptorSetLayoutCreateFlags::empty(),
+                &layout,
+                3,
+            )
+            .unwrap();
+        assert_eq!(sets.len(), 3);
+        allocator.free(device, sets);
+        allocator.cleanup(device);
+    }
+}
+
+#[test]
+fn allocator_works_through_reference() {
+    let device = MockDevice::new();
+    allocate_and_free(&&device);
+    assert_eq!(device.pools_created(), 1);
+    assert_eq!(device.live_pools(), 0);
+}
+
+#[test]
+fn allocator_works_through_box() {
+    let device = Box::new(MockDevice::new());
+    allocate_and_free(&device);
+    assert_eq!(device.pools_created(), 1);
+    assert_eq!(device.live_pools(), 0);
+}
+
+#[test]
+// Mock device is not `Sync`, but only `Arc` forwarding is tested here.
+#[allow(clippy::arc_with_non_send_sync)]
+fn allocator_works_through_arc() {
+    let device = Arc::new(MockDevice::new());
+    allocate_and_free(&device);
+    assert_eq!(device.pools_created(), 1);
+    assert_eq!(device.live_pools(), 0);
+}
+
+#[test]
+// Mock device is not `Sync`, but only `Arc` forwarding is tested here.
+#[allow(clippy::arc_with_non_send_sync)]
+fn allocator_works_through_nested_pointers() {
+    let device = Arc::new(Box::new(MockDevice::new()));
+    allocate_and_free(&&device);
+    assert_eq!(device.pools_created(), 1);
+    assert_eq!(device.live_pools(), 0);
+}
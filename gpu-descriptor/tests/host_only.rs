@@ -0,0 +1,86 @@
+//! Tests of host-only descriptor pools.
+
+use {
+    gpu_descriptor::{
+        mock::MockDevice, AllocationError, BucketInfo, DescriptorAllocator,
+        DescriptorAllocatorListener, DescriptorKind, DescriptorPoolCreateFlags,
+        DescriptorSetLayoutCreateFlags, DescriptorTotalCount, PoolInfo,
+    },
+    std::sync::{Arc, Mutex},
+};
+
+/// Listener that records flags of created pools.
+struct PoolFlagsListener(Arc<Mutex<Vec<DescriptorPoolCreateFlags>>>);
+
+impl DescriptorAllocatorListener for PoolFlagsListener {
+    fn on_pool_created(&mut self, _bucket: &BucketInfo, pool: &PoolInfo) {
+        self.0.lock().unwrap().push(pool.flags);
+    }
+}
+
+#[test]
+fn host_only_sets_are_kept_in_separate_bucket() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let flags = Arc::new(Mutex::new(Vec::new()));
+    allocator.set_listener(Box::new(PoolFlagsListener(flags.clone())));
+
+    let layout = DescriptorTotalCount::new().with(DescriptorKind::CombinedImageSampler, 2);
+    let regular = DescriptorSetLayoutCreateFlags::empty();
+    let host_only = DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL;
+
+    let (regular_sets, host_only_sets) = unsafe {
+        let regular_sets = allocator
+            .allocate(&device, &layout, regular, &layout, 2)
+            .unwrap();
+        let host_only_sets = allocator
+            .allocate(&device, &layout, host_only, &layout, 2)
+            .unwrap();
+        (regular_sets, host_only_sets)
+    };
+
+    // Sets with equal layouts do not share pools.
+    assert_ne!(regular_sets[0].raw().pool(), host_only_sets[0].raw().pool());
+    assert_eq!(
+        *flags.lock().unwrap(),
+        [
+            DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+            DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET | DescriptorPoolCreateFlags::HOST_ONLY,
+        ]
+    );
+
+    let mut buckets: Vec<_> = allocator
+        .bucket_statistics()
+        .map(|bucket| (bucket.host_only, bucket.allocated_sets))
+        .collect();
+    buckets.sort_unstable();
+    assert_eq!(buckets, [(false, 2), (true, 2)]);
+
+    unsafe {
+        allocator.free(&device, regular_sets);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 1);
+    assert_eq!(device.live_sets(), 2);
+
+    unsafe {
+        allocator.free(&device, host_only_sets);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+}
+
+#[test]
+fn host_only_update_after_bind_layout_is_rejected() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(1024);
+    let layout = DescriptorTotalCount::new().with(DescriptorKind::SampledImage, 1);
+    let flags = DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL
+        | DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND;
+
+    assert!(matches!(
+        unsafe { allocator.allocate(&device, &layout, flags, &layout, 1) },
+        Err(AllocationError::InvalidLayoutFlags)
+    ));
+    assert_eq!(device.pools_created(), 0);
+}
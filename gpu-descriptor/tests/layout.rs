@@ -0,0 +1,168 @@
+//! Tests of `DescriptorSetLayoutCache`.
+
+use {
+    gpu_descriptor::{
+        CreateLayoutError, DescriptorBindingFlags, DescriptorCountError, DescriptorKind,
+        DescriptorSetLayoutBinding, DescriptorSetLayoutCache, DescriptorSetLayoutCreateFlags,
+        DescriptorSetLayoutDevice, DescriptorType, ShaderStageFlags,
+    },
+    std::cell::{Cell, RefCell},
+};
+
+/// Device that creates layouts as sequential ids and tracks live ones.
+#[derive(Default)]
+struct LayoutDevice {
+    next_id: Cell<u32>,
+    live: RefCell<Vec<u32>>,
+}
+
+impl DescriptorSetLayoutDevice<u32> for LayoutDevice {
+    unsafe fn create_descriptor_set_layout(
+        &self,
+        bindings: &[DescriptorSetLayoutBinding],
+        _flags: DescriptorSetLayoutCreateFlags,
+    ) -> Result<u32, CreateLayoutError> {
+        assert!(
+            bindings.windows(2).all(|w| w[0].binding < w[1].binding),
+            "Bindings must be sorted and unique"
+        );
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.live.borrow_mut().push(id);
+        Ok(id)
+    }
+
+    unsafe fn destroy_descriptor_set_layout(&self, layout: u32) {
+        let mut live = self.live.borrow_mut();
+        let index = live
+            .iter()
+            .position(|&id| id == layout)
+            .expect("Layout must be alive");
+        live.swap_remove(index);
+    }
+}
+
+fn binding(binding: u32, ty: DescriptorType, count: u32) -> DescriptorSetLayoutBinding {
+    DescriptorSetLayoutBinding {
+        binding,
+        ty,
+        count,
+        stages: ShaderStageFlags::FRAGMENT,
+        flags: DescriptorBindingFlags::empty(),
+    }
+}
+
+#[test]
+fn deduplicates_layouts() {
+    let device = LayoutDevice::default();
+    let mut cache = DescriptorSetLayoutCache::new();
+
+    let bindings = [
+        binding(0, DescriptorType::UniformBuffer, 1),
+        binding(1, DescriptorType::CombinedImageSampler, 4),
+    ];
+    let reversed = [bindings[1], bindings[0]];
+
+    unsafe {
+        let a = cache
+            .get_or_create(&device, &bindings, DescriptorSetLayoutCreateFlags::empty())
+            .unwrap();
+        let b = cache
+            .get_or_create(&device, &reversed, DescriptorSetLayoutCreateFlags::empty())
+            .unwrap();
+        let c = cache
+            .get_or_create(
+                &device,
+                &bindings,
+                DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND,
+            )
+            .unwrap();
+
+        // Order of bindings does not matter, flags do.
+        assert_eq!(a.raw(), b.raw());
+        assert_ne!(a.raw(), c.raw());
+        assert_eq!(cache.len(), 2);
+        assert_eq!(device.live.borrow().len(), 2);
+
+        assert_eq!(a.flags(), DescriptorSetLayoutCreateFlags::empty());
+        assert_eq!(c.flags(), DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND);
+        assert_eq!(a.descriptor_count().get(DescriptorKind::UniformBuffer), 1);
+        assert_eq!(
+            a.descriptor_count()
+                .get(DescriptorKind::CombinedImageSampler),
+            4
+        );
+
+        drop((a, b, c));
+        cache.cleanup(&device);
+    }
+}
+
+#[test]
+fn cleanup_destroys_unreferenced_layouts() {
+    let device = LayoutDevice::default();
+    let mut cache = DescriptorSetLayoutCache::new();
+
+    unsafe {
+        let kept = cache
+            .get_or_create(
+                &device,
+                &[binding(0, DescriptorType::Sampler, 1)],
+                DescriptorSetLayoutCreateFlags::empty(),
+            )
+            .unwrap();
+        let dropped = cache
+            .get_or_create(
+                &device,
+                &[binding(0, DescriptorType::StorageBuffer, 2)],
+                DescriptorSetLayoutCreateFlags::empty(),
+            )
+            .unwrap();
+        let dropped_raw = *dropped.raw();
+        drop(dropped);
+
+        cache.cleanup(&device);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(*device.live.borrow(), [*kept.raw()]);
+
+        // Destroyed layout is created anew.
+        let recreated = cache
+            .get_or_create(
+                &device,
+                &[binding(0, DescriptorType::StorageBuffer, 2)],
+                DescriptorSetLayoutCreateFlags::empty(),
+            )
+            .unwrap();
+        assert_ne!(*recreated.raw(), dropped_raw);
+
+        drop((kept, recreated));
+        cache.cleanup(&device);
+    }
+
+    assert!(cache.is_empty());
+    assert!(device.live.borrow().is_empty());
+}
+
+#[test]
+fn descriptor_count_overflow_is_reported() {
+    let device = LayoutDevice::default();
+    let mut cache = DescriptorSetLayoutCache::new();
+
+    let bindings = [
+        binding(0, DescriptorType::StorageBuffer, u32::MAX),
+        binding(1, DescriptorType::StorageBuffer, 1),
+    ];
+
+    let result =
+        unsafe { cache.get_or_create(&device, &bindings, DescriptorSetLayoutCreateFlags::empty()) };
+    assert!(matches!(
+        result,
+        Err(CreateLayoutError::DescriptorCount(
+            DescriptorCountError::Overflow
+        ))
+    ));
+
+    // Nothing is created for rejected bindings.
+    assert!(cache.is_empty());
+    assert!(device.live.borrow().is_empty());
+}
@@ -0,0 +1,99 @@
+//! Tests of `DescriptorSetLifetime` hints.
+
+use gpu_descriptor::{
+    mock::MockDevice, DescriptorAllocator, DescriptorDeviceCapabilities, DescriptorKind,
+    DescriptorSetLayoutCreateFlags, DescriptorSetLifetime, DescriptorTotalCount,
+};
+
+fn layout() -> DescriptorTotalCount {
+    DescriptorTotalCount::new().with(DescriptorKind::UniformBuffer, 1)
+}
+
+#[test]
+fn persistent_pool_is_destroyed_not_reset() {
+    let device = MockDevice::new();
+    device.set_capabilities(DescriptorDeviceCapabilities::RESET_POOL);
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = layout();
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    let (first, second) = unsafe {
+        let first = allocator
+            .allocate(&device, &layout, empty, &layout, 64)
+            .unwrap();
+        let second = allocator
+            .allocate(&device, &layout, empty, &layout, 1)
+            .unwrap();
+        (first, second)
+    };
+    assert_eq!(device.pools_created(), 2);
+    assert_ne!(first[0].raw().pool(), second[0].raw().pool());
+
+    unsafe {
+        allocator.free(&device, first);
+    }
+
+    // Emptied front pool is destroyed right away without resetting it first.
+    assert_eq!(device.pools_reset(), 0);
+    assert_eq!(device.pools_destroyed(), 1);
+    assert_eq!(device.live_pools(), 1);
+
+    unsafe {
+        allocator.free(&device, second);
+    }
+
+    // Last pool is kept for reuse.
+    assert_eq!(device.pools_reset(), 1);
+    assert_eq!(device.live_pools(), 1);
+
+    unsafe {
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+}
+
+#[test]
+fn transient_sets_use_separate_pools_kept_until_cleanup() {
+    let device = MockDevice::new();
+    device.set_capabilities(DescriptorDeviceCapabilities::RESET_POOL);
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = layout();
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    let (persistent, transient) = unsafe {
+        let persistent = allocator
+            .allocate(&device, &layout, empty, &layout, 1)
+            .unwrap();
+        let transient = allocator
+            .allocate_with_lifetime(
+                &device,
+                &layout,
+                empty,
+                &layout,
+                64,
+                DescriptorSetLifetime::Transient,
+            )
+            .unwrap();
+        (persistent, transient)
+    };
+    assert_eq!(device.pools_created(), 2);
+    assert_eq!(transient[0].lifetime(), DescriptorSetLifetime::Transient);
+    assert!(transient
+        .iter()
+        .all(|set| set.raw().pool() != persistent[0].raw().pool()));
+
+    unsafe {
+        allocator.free(&device, transient);
+    }
+
+    // Emptied transient pool is reset and kept.
+    assert_eq!(device.pools_reset(), 1);
+    assert_eq!(device.live_pools(), 2);
+
+    unsafe {
+        allocator.free(&device, persistent);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+    assert_eq!(device.live_sets(), 0);
+}
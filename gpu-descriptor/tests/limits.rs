@@ -0,0 +1,47 @@
+//! Tests of per-set device limits.
+
+use gpu_descriptor::{
+    mock::MockDevice, AllocationError, DescriptorAllocator, DescriptorKind,
+    DescriptorSetLayoutCreateFlags, DescriptorTotalCount, DeviceLimits,
+};
+
+#[test]
+fn update_after_bind_sets_are_checked_against_their_own_limits() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(1024);
+    allocator.set_device_limits(DeviceLimits {
+        max_descriptor_set_sampled_images: 4,
+        max_descriptor_set_update_after_bind_sampled_images: 16,
+        ..DeviceLimits::UNBOUNDED
+    });
+
+    let regular = DescriptorSetLayoutCreateFlags::empty();
+    let update_after_bind = DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND;
+    let small = DescriptorTotalCount::new().with(DescriptorKind::SampledImage, 8);
+    let large = DescriptorTotalCount::new().with(DescriptorKind::SampledImage, 32);
+
+    unsafe {
+        assert!(matches!(
+            allocator.allocate(&device, &small, regular, &small, 1),
+            Err(AllocationError::LimitExceeded(
+                "maxDescriptorSetSampledImages"
+            ))
+        ));
+
+        let sets = allocator
+            .allocate(&device, &small, update_after_bind, &small, 1)
+            .unwrap();
+
+        assert!(matches!(
+            allocator.allocate(&device, &large, update_after_bind, &large, 1),
+            Err(AllocationError::LimitExceeded(
+                "maxDescriptorSetUpdateAfterBindSampledImages"
+            ))
+        ));
+
+        allocator.free(&device, sets);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.pools_created(), 1);
+    assert_eq!(device.live_pools(), 0);
+}
@@ -0,0 +1,153 @@
+//! Tests of `DescriptorAllocatorListener` notifications.
+
+use {
+    gpu_descriptor::{
+        AllocationError, BucketInfo, CreatePoolError, DescriptorAllocator,
+        DescriptorAllocatorListener, DescriptorDevice, DescriptorKind, DescriptorPoolCreateFlags,
+        DescriptorSetLayoutCreateFlags, DescriptorTotalCount, DeviceAllocationError,
+        LegacyDescriptorDevice, PoolInfo,
+    },
+    std::{
+        cell::Cell,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Device that hands out sequential ids and fails on request.
+#[derive(Default)]
+struct ListenerDevice {
+    next_id: Cell<u32>,
+    live_pools: Cell<u32>,
+    fail_pool_creation: Cell<bool>,
+    fail_allocation: Cell<bool>,
+}
+
+impl ListenerDevice {
+    fn next_id(&self) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+}
+
+impl DescriptorDevice<DescriptorTotalCount, u32, u32> for ListenerDevice {
+    unsafe fn create_descriptor_pool(
+        &self,
+        _descriptor_count: &DescriptorTotalCount,
+        _max_sets: u32,
+        _flags: DescriptorPoolCreateFlags,
+    ) -> Result<u32, CreatePoolError> {
+        if self.fail_pool_creation.replace(false) {
+            return Err(CreatePoolError::OutOfDeviceMemory);
+        }
+        self.live_pools.set(self.live_pools.get() + 1);
+        Ok(self.next_id())
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, _pool: u32) {
+        self.live_pools.set(self.live_pools.get() - 1);
+    }
+
+    unsafe fn alloc_descriptor_sets<'a>(
+        &self,
+        _pool: &mut u32,
+        layouts: impl ExactSizeIterator<Item = &'a DescriptorTotalCount>,
+        sets: &mut impl Extend<u32>,
+    ) -> Result<(), DeviceAllocationError> {
+        if self.fail_allocation.replace(false) {
+            return Err(DeviceAllocationError::OutOfHostMemory);
+        }
+        sets.extend(layouts.map(|_| self.next_id()));
+        Ok(())
+    }
+
+    unsafe fn dealloc_descriptor_sets(&self, _pool: &mut u32, sets: impl Iterator<Item = u32>) {
+        sets.for_each(drop);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    PoolCreated(u64),
+    PoolDestroyed(u64),
+    Allocated(u64, u32),
+    Freed(u64, u32),
+    Error(String),
+}
+
+struct RecordingListener(Arc<Mutex<Vec<Event>>>);
+
+impl DescriptorAllocatorListener for RecordingListener {
+    fn on_pool_created(&mut self, _bucket: &BucketInfo, pool: &PoolInfo) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::PoolCreated(pool.pool_id));
+    }
+
+    fn on_pool_destroyed(&mut self, _bucket: &BucketInfo, pool: &PoolInfo) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::PoolDestroyed(pool.pool_id));
+    }
+
+    fn on_sets_allocated(&mut self, _bucket: &BucketInfo, pool_id: u64, count: u32) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::Allocated(pool_id, count));
+    }
+
+    fn on_sets_freed(&mut self, _bucket: &BucketInfo, pool_id: u64, count: u32) {
+        self.0.lock().unwrap().push(Event::Freed(pool_id, count));
+    }
+
+    fn on_error(&mut self, _bucket: &BucketInfo, error: &AllocationError) {
+        self.0.lock().unwrap().push(Event::Error(error.to_string()));
+    }
+}
+
+#[test]
+fn listener_events_are_ordered() {
+    let device = LegacyDescriptorDevice::new(ListenerDevice::default());
+    let mut allocator = DescriptorAllocator::new(0);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    allocator.set_listener(Box::new(RecordingListener(events.clone())));
+
+    let layout = DescriptorTotalCount::new().with(DescriptorKind::UniformBuffer, 2);
+    let flags = DescriptorSetLayoutCreateFlags::empty();
+
+    unsafe {
+        let sets = allocator
+            .allocate(&device, &layout, flags, &layout, 3)
+            .unwrap();
+        allocator.free(&device, sets);
+        allocator.cleanup(&device);
+
+        // Cleanup removed the empty bucket, so pool ids of the new one start over.
+        device.inner().fail_pool_creation.set(true);
+        let result = allocator.allocate(&device, &layout, flags, &layout, 1);
+        assert!(matches!(result, Err(AllocationError::OutOfDeviceMemory)));
+
+        device.inner().fail_allocation.set(true);
+        let result = allocator.allocate(&device, &layout, flags, &layout, 1);
+        assert!(matches!(result, Err(AllocationError::OutOfHostMemory)));
+    }
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Event::PoolCreated(0),
+            Event::Allocated(0, 3),
+            Event::Freed(0, 3),
+            Event::PoolDestroyed(0),
+            Event::Error(AllocationError::OutOfDeviceMemory.to_string()),
+            Event::PoolCreated(0),
+            Event::PoolDestroyed(0),
+            Event::Error(AllocationError::OutOfHostMemory.to_string()),
+        ]
+    );
+    assert!(allocator.take_listener().is_some());
+    assert_eq!(device.inner().live_pools.get(), 0);
+}
@@ -0,0 +1,805 @@
+//! Model-based tests of `DescriptorAllocator`.
+//!
+//! Random sequences of operations are applied to the allocator backed by `MockDevice`
+//! and to a simple reference model that tracks live sets per layout and per pool.
+//! Observable behaviour of the allocator is compared against the model after every step.
+
+use {
+    gpu_descriptor::{
+        mock::{MockDevice, MockPool, MockSet},
+        AllocationError, CreatePoolError, DescriptorAllocator, DescriptorDeviceCapabilities,
+        DescriptorDeviceV2, DescriptorKind, DescriptorPoolCreateFlags, DescriptorSet,
+        DescriptorSetLayoutCreateFlags, DescriptorSetLifetime, DescriptorTotalCount,
+        DeviceAllocationError, DeviceLimits, OutOfMemoryPolicy, PoolSizing, ReleaseMemoryCallback,
+    },
+    proptest::{prelude::*, sample::Index},
+    std::{
+        cell::{Cell, RefCell},
+        collections::{BTreeMap, HashMap, HashSet},
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+    },
+};
+
+fn layout(sampler: u32, sampled_image: u32, uniform_buffer: u32) -> DescriptorTotalCount {
+    DescriptorTotalCount::new()
+        .with(DescriptorKind::Sampler, sampler)
+        .with(DescriptorKind::SampledImage, sampled_image)
+        .with(DescriptorKind::UniformBuffer, uniform_buffer)
+}
+
+fn layouts() -> [DescriptorTotalCount; 4] {
+    [
+        layout(0, 0, 0),
+        layout(1, 0, 0),
+        layout(0, 4, 2),
+        layout(2, 16, 1),
+    ]
+}
+
+/// Kind of pools sets are allocated from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum PoolKind {
+    Regular,
+    UpdateAfterBind,
+    HostOnly,
+}
+
+impl PoolKind {
+    fn from_pool_flags(flags: DescriptorPoolCreateFlags) -> Self {
+        let update_after_bind = flags.contains(DescriptorPoolCreateFlags::UPDATE_AFTER_BIND);
+        let host_only = flags.contains(DescriptorPoolCreateFlags::HOST_ONLY);
+        match (update_after_bind, host_only) {
+            (false, false) => PoolKind::Regular,
+            (true, false) => PoolKind::UpdateAfterBind,
+            (false, true) => PoolKind::HostOnly,
+            (true, true) => panic!("Pool cannot be both update-after-bind and host-only"),
+        }
+    }
+
+    fn layout_flags(self) -> DescriptorSetLayoutCreateFlags {
+        match self {
+            PoolKind::Regular => DescriptorSetLayoutCreateFlags::empty(),
+            PoolKind::UpdateAfterBind => DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND,
+            PoolKind::HostOnly => DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL,
+        }
+    }
+}
+
+/// Layout index, kind of pools and lifetime of sets.
+type Bucket = (usize, PoolKind, DescriptorSetLifetime);
+
+#[derive(Clone, Debug)]
+enum Op {
+    Allocate {
+        layout: usize,
+        kind: PoolKind,
+        lifetime: DescriptorSetLifetime,
+        count: u32,
+    },
+    AllocateInvalid {
+        layout: usize,
+        count: u32,
+        descriptor_buffer: bool,
+    },
+    Free {
+        indices: Vec<Index>,
+        unordered: bool,
+    },
+    FreeAll,
+    FreeStale {
+        unordered: bool,
+    },
+    Cleanup,
+    FailNextPoolCreation,
+    FailNextAllocation,
+    SetOutOfMemoryPolicy {
+        retries: u32,
+        cleanup: bool,
+        release: bool,
+    },
+    SetPoolSizing(PoolSizing),
+    SetDeviceLimits(DeviceLimits),
+    ForgetAll,
+    DestroyAll,
+}
+
+fn device_limits() -> impl Strategy<Value = DeviceLimits> {
+    (
+        prop::sample::select(vec![u32::MAX, 1, 3, 16, 100]),
+        prop::sample::select(vec![u32::MAX, 16, 40, 200]),
+        prop::sample::select(vec![u32::MAX, 4, 8]),
+        prop::sample::select(vec![u32::MAX, 4, 8]),
+    )
+        .prop_map(
+            |(
+                max_pool_sets,
+                max_pool_descriptors,
+                max_descriptor_set_sampled_images,
+                max_descriptor_set_update_after_bind_sampled_images,
+            )| DeviceLimits {
+                max_pool_sets,
+                max_pool_descriptors,
+                max_descriptor_set_sampled_images,
+                max_descriptor_set_update_after_bind_sampled_images,
+                ..DeviceLimits::UNBOUNDED
+            },
+        )
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let kind = prop_oneof![
+        Just(PoolKind::Regular),
+        Just(PoolKind::UpdateAfterBind),
+        Just(PoolKind::HostOnly),
+    ];
+    let lifetime = prop_oneof![
+        Just(DescriptorSetLifetime::Persistent),
+        Just(DescriptorSetLifetime::Transient),
+    ];
+    let sizing = prop_oneof![Just(PoolSizing::Fixed), Just(PoolSizing::Adaptive)];
+
+    prop_oneof![
+        4 => (0..layouts().len(), kind, lifetime, 0..300u32).prop_map(
+            |(layout, kind, lifetime, count)| Op::Allocate {
+                layout,
+                kind,
+                lifetime,
+                count,
+            }
+        ),
+        1 => (0..layouts().len(), 0..3u32, any::<bool>()).prop_map(
+            |(layout, count, descriptor_buffer)| Op::AllocateInvalid {
+                layout,
+                count,
+                descriptor_buffer,
+            }
+        ),
+        4 => (prop::collection::vec(any::<Index>(), 0..100), any::<bool>())
+            .prop_map(|(indices, unordered)| Op::Free { indices, unordered }),
+        1 => Just(Op::FreeAll),
+        1 => any::<bool>().prop_map(|unordered| Op::FreeStale { unordered }),
+        1 => Just(Op::Cleanup),
+        1 => Just(Op::FailNextPoolCreation),
+        1 => Just(Op::FailNextAllocation),
+        1 => (0..3u32, any::<bool>(), any::<bool>()).prop_map(|(retries, cleanup, release)| {
+            Op::SetOutOfMemoryPolicy {
+                retries,
+                cleanup,
+                release,
+            }
+        }),
+        1 => sizing.prop_map(Op::SetPoolSizing),
+        1 => device_limits().prop_map(Op::SetDeviceLimits),
+        1 => Just(Op::ForgetAll),
+        1 => Just(Op::DestroyAll),
+    ]
+}
+
+/// Model of one pool.
+#[derive(Debug)]
+struct PoolModel {
+    max_sets: u32,
+    descriptor_count: DescriptorTotalCount,
+    kind: PoolKind,
+    destroyed: bool,
+
+    /// Pool was dropped by `forget_all` and is leaked.
+    forgotten: bool,
+
+    /// Bucket of sets allocated from the pool, once known.
+    bucket: Option<Bucket>,
+
+    /// Number of live sets allocated from the pool.
+    live: u32,
+}
+
+/// `MockDevice` wrapper that records pool lifetimes and injects errors.
+#[derive(Default)]
+struct ModelDevice {
+    mock: MockDevice,
+    pools: RefCell<HashMap<u64, PoolModel>>,
+    fail_pool_creation: Cell<bool>,
+    fail_allocation: Cell<bool>,
+    injected: Cell<u32>,
+    dealloc_calls: Cell<u32>,
+    reset_calls: Cell<u32>,
+}
+
+impl DescriptorDeviceV2 for ModelDevice {
+    type Layout = DescriptorTotalCount;
+    type Pool = MockPool;
+    type Set = MockSet;
+
+    fn capabilities(&self) -> DescriptorDeviceCapabilities {
+        self.mock.capabilities()
+    }
+
+    fn create_descriptor_pool(
+        &self,
+        descriptor_count: &DescriptorTotalCount,
+        max_sets: u32,
+        flags: DescriptorPoolCreateFlags,
+    ) -> Result<MockPool, CreatePoolError> {
+        if self.fail_pool_creation.replace(false) {
+            self.injected.set(self.injected.get() + 1);
+            return Err(CreatePoolError::OutOfDeviceMemory);
+        }
+
+        let pool = self
+            .mock
+            .create_descriptor_pool(descriptor_count, max_sets, flags)?;
+        let old = self.pools.borrow_mut().insert(
+            pool.id(),
+            PoolModel {
+                max_sets,
+                descriptor_count: *descriptor_count,
+                kind: PoolKind::from_pool_flags(flags),
+                destroyed: false,
+                forgotten: false,
+                bucket: None,
+                live: 0,
+            },
+        );
+        assert!(old.is_none(), "Pool ids must be unique");
+        Ok(pool)
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, pool: MockPool) {
+        {
+            let mut pools = self.pools.borrow_mut();
+            let model = pools.get_mut(&pool.id()).expect("Unknown pool");
+            assert!(!model.destroyed, "Pool must be destroyed exactly once");
+            assert!(!model.forgotten, "Forgotten pool must not be destroyed");
+            model.destroyed = true;
+        }
+        self.mock.destroy_descriptor_pool(pool)
+    }
+
+    unsafe fn reset_descriptor_pool(&self, pool: &mut MockPool) {
+        self.reset_calls.set(self.reset_calls.get() + 1);
+        self.mock.reset_descriptor_pool(pool)
+    }
+
+    unsafe fn alloc_descriptor_sets<'a>(
+        &self,
+        pool: &mut MockPool,
+        layouts: impl ExactSizeIterator<Item = &'a DescriptorTotalCount>,
+        sets: &mut impl Extend<MockSet>,
+    ) -> Result<(), DeviceAllocationError> {
+        if self.fail_allocation.replace(false) {
+            self.injected.set(self.injected.get() + 1);
+            return Err(DeviceAllocationError::OutOfHostMemory);
+        }
+        self.mock.alloc_descriptor_sets(pool, layouts, sets)
+    }
+
+    unsafe fn dealloc_descriptor_sets(
+        &self,
+        pool: &mut MockPool,
+        sets: impl Iterator<Item = MockSet>,
+    ) {
+        self.dealloc_calls.set(self.dealloc_calls.get() + 1);
+        self.mock.dealloc_descriptor_sets(pool, sets)
+    }
+}
+
+struct Harness {
+    device: ModelDevice,
+    allocator: DescriptorAllocator<MockPool, MockSet>,
+    max_update_after_bind: u32,
+    sizing: PoolSizing,
+    limits: DeviceLimits,
+
+    /// Out-of-memory policy set on the allocator.
+    retries: u32,
+    cleanup: bool,
+    release: bool,
+
+    /// Number of calls of `release_memory` callback.
+    release_calls: Arc<AtomicU32>,
+
+    /// Live sets with layout index.
+    held: Vec<(DescriptorSet<MockSet>, usize)>,
+
+    /// Sets allocated before `forget_all` or `destroy_all`.
+    stale: Vec<DescriptorSet<MockSet>>,
+
+    /// Number of sets left in forgotten pools.
+    leaked_sets: u64,
+
+    /// Number of live sets per bucket.
+    per_bucket: HashMap<Bucket, u32>,
+
+    /// Number of descriptors in live update-after-bind sets.
+    update_after_bind_descriptors: u32,
+}
+
+impl Harness {
+    fn new(max_update_after_bind: u32, reset_pool: bool) -> Self {
+        let device = ModelDevice::default();
+        if reset_pool {
+            device
+                .mock
+                .set_capabilities(DescriptorDeviceCapabilities::RESET_POOL);
+        }
+
+        Harness {
+            device,
+            allocator: DescriptorAllocator::new(max_update_after_bind),
+            max_update_after_bind,
+            sizing: PoolSizing::default(),
+            limits: DeviceLimits::default(),
+            retries: 0,
+            cleanup: false,
+            release: false,
+            release_calls: Arc::new(AtomicU32::new(0)),
+            held: Vec::new(),
+            stale: Vec::new(),
+            leaked_sets: 0,
+            per_bucket: HashMap::new(),
+            update_after_bind_descriptors: 0,
+        }
+    }
+
+    fn apply(&mut self, op: Op) {
+        match op {
+            Op::Allocate {
+                layout,
+                kind,
+                lifetime,
+                count,
+            } => self.allocate(layout, kind, lifetime, count),
+            Op::AllocateInvalid {
+                layout,
+                count,
+                descriptor_buffer,
+            } => self.allocate_invalid(layout, count, descriptor_buffer),
+            Op::Free { indices, unordered } => {
+                let mut sets = Vec::new();
+                for index in indices {
+                    if self.held.is_empty() {
+                        break;
+                    }
+                    let index = index.index(self.held.len());
+                    sets.push(self.held.swap_remove(index));
+                }
+                self.free(sets, unordered);
+            }
+            Op::FreeAll => {
+                let sets = std::mem::take(&mut self.held);
+                self.free(sets, false);
+            }
+            Op::FreeStale { unordered } => self.free_stale(unordered),
+            Op::Cleanup => unsafe { self.allocator.cleanup(&self.device) },
+            Op::FailNextPoolCreation => self.device.fail_pool_creation.set(true),
+            Op::FailNextAllocation => self.device.fail_allocation.set(true),
+            Op::SetOutOfMemoryPolicy {
+                retries,
+                cleanup,
+                release,
+            } => {
+                let release_memory: Option<ReleaseMemoryCallback> = if release {
+                    let calls = self.release_calls.clone();
+                    Some(Box::new(move |_: &AllocationError| {
+                        calls.fetch_add(1, Ordering::Relaxed);
+                        true
+                    }))
+                } else {
+                    None
+                };
+                self.allocator.set_out_of_memory_policy(OutOfMemoryPolicy {
+                    retries,
+                    cleanup,
+                    release_memory,
+                });
+                self.retries = retries;
+                self.cleanup = cleanup;
+                self.release = release;
+            }
+            Op::SetPoolSizing(sizing) => {
+                self.allocator.set_pool_sizing(sizing);
+                self.sizing = sizing;
+            }
+            Op::SetDeviceLimits(limits) => {
+                self.allocator.set_device_limits(limits);
+                self.limits = limits;
+            }
+            Op::ForgetAll => {
+                self.allocator.forget_all();
+                for pool in self.device.pools.borrow_mut().values_mut() {
+                    pool.forgotten |= !pool.destroyed;
+                }
+                self.leaked_sets += self.held.len() as u64;
+                self.retire_held();
+            }
+            Op::DestroyAll => {
+                unsafe { self.allocator.destroy_all(&self.device) };
+                assert!(
+                    self.device
+                        .pools
+                        .borrow()
+                        .values()
+                        .all(|pool| pool.destroyed || pool.forgotten),
+                    "Every pool must be destroyed"
+                );
+                self.retire_held();
+            }
+        }
+
+        self.check();
+    }
+
+    /// Moves all held sets to stale ones after `forget_all` or `destroy_all`.
+    fn retire_held(&mut self) {
+        self.stale.extend(self.held.drain(..).map(|(set, _)| set));
+        self.per_bucket.clear();
+        self.update_after_bind_descriptors = 0;
+    }
+
+    fn allocate(
+        &mut self,
+        layout: usize,
+        kind: PoolKind,
+        lifetime: DescriptorSetLifetime,
+        count: u32,
+    ) {
+        let size = &layouts()[layout];
+        let descriptors = count * size.total();
+        let update_after_bind = kind == PoolKind::UpdateAfterBind;
+
+        // Update-after-bind layouts have their own per-set limits.
+        let max_sampled_images = if update_after_bind {
+            self.limits
+                .max_descriptor_set_update_after_bind_sampled_images
+        } else {
+            self.limits.max_descriptor_set_sampled_images
+        };
+        let exceeded_limit = count > 0
+            && (size
+                .iter()
+                .any(|(_, count)| count > self.limits.max_pool_descriptors)
+                || size.get(DescriptorKind::SampledImage) > max_sampled_images);
+        let over_budget = update_after_bind
+            && self.update_after_bind_descriptors + descriptors > self.max_update_after_bind;
+
+        let first_new_pool = self.device.mock.pools_created();
+        let release_calls = self.release_calls.load(Ordering::Relaxed);
+        self.device.injected.set(0);
+
+        let result = unsafe {
+            self.allocator.allocate_with_lifetime(
+                &self.device,
+                size,
+                kind.layout_flags(),
+                size,
+                count,
+                lifetime,
+            )
+        };
+
+        let injected = self.device.injected.get();
+        let released = self.release_calls.load(Ordering::Relaxed) - release_calls;
+        if self.release {
+            assert_eq!(
+                released,
+                injected.min(self.retries),
+                "Memory must be released before each retry"
+            );
+        }
+
+        self.check_new_pools(size, kind, first_new_pool);
+
+        // Each retry gets past one injected error,
+        // and released memory guarantees that retry happens.
+        let retried = injected > 0 && injected <= self.retries;
+        let must_recover = retried && self.release;
+        let may_recover = retried && (self.release || self.cleanup);
+
+        let sets = match result {
+            Ok(sets) => sets,
+            Err(err) => {
+                assert!(
+                    count > 0 && (exceeded_limit || over_budget || (injected > 0 && !must_recover)),
+                    "Allocation may fail only due to limits, budget or device error, failed with {}",
+                    err
+                );
+                if exceeded_limit {
+                    assert!(matches!(err, AllocationError::LimitExceeded(_)));
+                } else if over_budget {
+                    assert!(matches!(err, AllocationError::Fragmentation));
+                }
+                return;
+            }
+        };
+
+        assert!(!exceeded_limit, "Device limits must be honoured");
+        assert!(!over_budget, "Update-after-bind budget must be honoured");
+        assert!(
+            injected == 0 || may_recover,
+            "Device error must be returned unless recovered"
+        );
+        assert_eq!(
+            sets.len(),
+            count as usize,
+            "Requested number of sets must be allocated"
+        );
+
+        let mut pools = self.device.pools.borrow_mut();
+        let bucket = (layout, kind, lifetime);
+        for set in &sets {
+            assert_eq!(set.lifetime(), lifetime);
+            let pool = pools
+                .get_mut(&set.raw().pool())
+                .expect("Set must come from known pool");
+            assert!(
+                !pool.destroyed && !pool.forgotten,
+                "Set must come from live pool"
+            );
+            assert_eq!(pool.kind, kind);
+            assert_eq!(
+                *pool.bucket.get_or_insert(bucket),
+                bucket,
+                "Pool must serve one bucket only"
+            );
+            pool.live += 1;
+            assert!(
+                pool.live <= pool.max_sets,
+                "Pool capacity must not be exceeded"
+            );
+        }
+        drop(pools);
+
+        *self.per_bucket.entry(bucket).or_default() += count;
+        if update_after_bind {
+            self.update_after_bind_descriptors += descriptors;
+        }
+        self.held.extend(sets.into_iter().map(|set| (set, layout)));
+    }
+
+    /// Allocates sets with layout that is both update-after-bind and host-only.
+    fn allocate_invalid(&mut self, layout: usize, count: u32, descriptor_buffer: bool) {
+        let size = &layouts()[layout];
+        let flags = if descriptor_buffer {
+            DescriptorSetLayoutCreateFlags::DESCRIPTOR_BUFFER
+        } else {
+            DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND
+                | DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL
+        };
+        let pools_created = self.device.mock.pools_created();
+
+        let result = unsafe {
+            self.allocator
+                .allocate(&self.device, size, flags, size, count)
+        };
+
+        match result {
+            Ok(sets) => assert!(count == 0 && sets.is_empty()),
+            Err(err) => assert!(
+                count > 0 && matches!(err, AllocationError::InvalidLayoutFlags),
+                "Invalid flags must be rejected, failed with {}",
+                err
+            ),
+        }
+        assert_eq!(self.device.mock.pools_created(), pools_created);
+    }
+
+    /// Checks pools created since `first_id` against sizing strategy and device limits.
+    fn check_new_pools(&self, size: &DescriptorTotalCount, kind: PoolKind, first_id: u64) {
+        let min_sets = match self.sizing {
+            PoolSizing::Fixed => 64,
+            PoolSizing::Adaptive => 8,
+        };
+
+        let mut clamp = self.limits.max_pool_sets.max(1);
+        for (_, count) in size.iter() {
+            clamp = clamp.min(self.limits.max_pool_descriptors / count);
+        }
+
+        let pools = self.device.pools.borrow();
+        for id in first_id..self.device.mock.pools_created() {
+            let pool = &pools[&id];
+            assert_eq!(pool.kind, kind);
+            assert!(pool.max_sets <= clamp, "Pool must respect device limits");
+            assert!(
+                pool.max_sets >= min_sets.min(clamp),
+                "Pool must not be smaller than sizing strategy allows"
+            );
+            assert!(
+                pool.max_sets == clamp || pool.max_sets.is_power_of_two(),
+                "Unclamped pool must have power of two sets"
+            );
+            assert!(pool
+                .descriptor_count
+                .iter()
+                .all(|(_, count)| count <= self.limits.max_pool_descriptors));
+        }
+    }
+
+    fn free(&mut self, sets: Vec<(DescriptorSet<MockSet>, usize)>, unordered: bool) {
+        // Device is called once per run of adjacent sets from one pool,
+        // unordered free makes one run per pool.
+        let mut runs: Vec<(u64, u32)> = Vec::new();
+        if unordered {
+            let mut per_pool = BTreeMap::new();
+            for (set, _) in &sets {
+                *per_pool.entry(set.raw().pool()).or_insert(0) += 1;
+            }
+            runs.extend(per_pool);
+        } else {
+            for (set, _) in &sets {
+                match runs.last_mut() {
+                    Some((pool, count)) if *pool == set.raw().pool() => *count += 1,
+                    _ => runs.push((set.raw().pool(), 1)),
+                }
+            }
+        }
+
+        let reset_pool = self
+            .device
+            .capabilities()
+            .contains(DescriptorDeviceCapabilities::RESET_POOL);
+
+        // Pool is reset instead of freeing sets one by one when run empties it,
+        // unless it is destroyed right away as empty front pool of persistent bucket.
+        let mut resets = 0;
+        let mut skipped = 0;
+        {
+            let mut pools = self.device.pools.borrow_mut();
+            let mut destroyed = HashSet::new();
+            for &(id, count) in &runs {
+                let pool = &pools[&id];
+                let (layout, kind, lifetime) = pool.bucket.unwrap();
+
+                // Pools of the bucket in queue order.
+                let mut queue: Vec<u64> = pools
+                    .iter()
+                    .filter(|&(id, pool)| {
+                        !pool.destroyed
+                            && !pool.forgotten
+                            && !destroyed.contains(id)
+                            && pool.bucket.is_some_and(|(l, k, t)| {
+                                layouts()[l] == layouts()[layout] && k == kind && t == lifetime
+                            })
+                    })
+                    .map(|(&id, _)| id)
+                    .collect();
+                queue.sort_unstable();
+
+                let emptied = pools[&id].live == count;
+                let index = queue.iter().position(|&queued| queued == id).unwrap();
+                let persistent = lifetime == DescriptorSetLifetime::Persistent;
+                if persistent
+                    && emptied
+                    && index + 1 < queue.len()
+                    && queue[..index].iter().all(|queued| pools[queued].live == 0)
+                {
+                    skipped += 1;
+                } else if reset_pool && emptied {
+                    resets += 1;
+                }
+                pools.get_mut(&id).unwrap().live -= count;
+
+                if persistent {
+                    for &queued in &queue[..queue.len() - 1] {
+                        if pools[&queued].live != 0 {
+                            break;
+                        }
+                        destroyed.insert(queued);
+                    }
+                }
+            }
+
+            for (set, layout) in &sets {
+                let kind = pools[&set.raw().pool()].kind;
+                *self
+                    .per_bucket
+                    .get_mut(&(*layout, kind, set.lifetime()))
+                    .unwrap() -= 1;
+                if kind == PoolKind::UpdateAfterBind {
+                    self.update_after_bind_descriptors -= layouts()[*layout].total();
+                }
+            }
+        }
+
+        self.device.dealloc_calls.set(0);
+        self.device.reset_calls.set(0);
+
+        let sets = sets.into_iter().map(|(set, _)| set);
+        if unordered {
+            unsafe { self.allocator.free_unordered(&self.device, sets) };
+        } else {
+            unsafe { self.allocator.free(&self.device, sets) };
+        }
+
+        assert_eq!(
+            self.device.reset_calls.get(),
+            resets,
+            "Pools must be reset exactly when all their sets are freed at once and pool is kept"
+        );
+        assert_eq!(
+            self.device.dealloc_calls.get() as usize,
+            runs.len() - resets as usize - skipped,
+            "Sets must be freed with one device call per run"
+        );
+    }
+
+    fn free_stale(&mut self, unordered: bool) {
+        let sets = std::mem::take(&mut self.stale);
+        self.device.dealloc_calls.set(0);
+        self.device.reset_calls.set(0);
+
+        if unordered {
+            unsafe { self.allocator.free_unordered(&self.device, sets) };
+        } else {
+            unsafe { self.allocator.free(&self.device, sets) };
+        }
+
+        assert_eq!(
+            self.device.dealloc_calls.get() + self.device.reset_calls.get(),
+            0,
+            "Sets from previous epochs must be ignored"
+        );
+    }
+
+    fn check(&self) {
+        self.allocator.check_invariants();
+
+        assert_eq!(
+            self.device.mock.live_sets(),
+            self.held.len() as u64 + self.leaked_sets,
+            "No set may be lost"
+        );
+        assert_eq!(
+            self.per_bucket
+                .values()
+                .map(|&count| u64::from(count))
+                .sum::<u64>(),
+            self.held.len() as u64,
+        );
+        assert!(self.update_after_bind_descriptors <= self.max_update_after_bind);
+
+        let pools = self.device.pools.borrow();
+        for (set, _) in &self.held {
+            let pool = &pools[&set.raw().pool()];
+            assert!(
+                !pool.destroyed && !pool.forgotten,
+                "Pool with live sets must not be destroyed or forgotten"
+            );
+        }
+
+        let live_pools = pools.values().filter(|pool| !pool.destroyed).count() as u64;
+        assert_eq!(self.device.mock.live_pools(), live_pools);
+    }
+
+    fn finish(mut self) {
+        self.apply(Op::FreeAll);
+        self.apply(Op::FreeStale { unordered: false });
+        self.apply(Op::Cleanup);
+
+        let pools = self.device.pools.borrow();
+        assert!(
+            pools.values().all(|pool| pool.destroyed || pool.forgotten),
+            "Every pool must be destroyed by the end"
+        );
+        let forgotten = pools.values().filter(|pool| pool.forgotten).count() as u64;
+        assert_eq!(self.device.mock.live_pools(), forgotten);
+    }
+}
+
+proptest! {
+    #[test]
+    fn allocator_matches_model(
+        max_update_after_bind in 0..4096u32,
+        reset_pool in any::<bool>(),
+        ops in prop::collection::vec(op(), 0..64),
+    ) {
+        let mut harness = Harness::new(max_update_after_bind, reset_pool);
+        for op in ops {
+            harness.apply(op);
+        }
+        harness.finish();
+    }
+}
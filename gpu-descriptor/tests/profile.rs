@@ -0,0 +1,87 @@
+//! Tests of pool size profile export and import.
+
+use gpu_descriptor::{
+    mock::{MockDevice, MockPool, MockSet},
+    DescriptorAllocator, DescriptorKind, DescriptorSetLayoutCreateFlags, DescriptorTotalCount,
+    PoolSizeProfile,
+};
+
+const PEAK: u32 = 300;
+
+fn layout() -> DescriptorTotalCount {
+    DescriptorTotalCount::new().with(DescriptorKind::CombinedImageSampler, 2)
+}
+
+/// Allocates `PEAK` sets one by one and returns number of pools created.
+fn run_session(allocator: &mut DescriptorAllocator<MockPool, MockSet>) -> u64 {
+    let device = MockDevice::new();
+    let layout = layout();
+
+    let mut sets = Vec::new();
+    for _ in 0..PEAK {
+        sets.extend(
+            unsafe {
+                allocator.allocate(
+                    &device,
+                    &layout,
+                    DescriptorSetLayoutCreateFlags::empty(),
+                    &layout,
+                    1,
+                )
+            }
+            .unwrap(),
+        );
+    }
+
+    unsafe {
+        allocator.free(&device, sets);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+    device.pools_created()
+}
+
+#[test]
+fn imported_profile_sizes_pools() {
+    let mut first = DescriptorAllocator::new(0);
+    let cold_pools = run_session(&mut first);
+    assert!(cold_pools > 1, "Pools must grow without profile");
+
+    let profile = first.pool_size_profile();
+    assert_eq!(profile.entries.len(), 1);
+    assert_eq!(profile.entries[0].size, layout());
+    assert_eq!(profile.entries[0].peak, PEAK);
+
+    let mut second = DescriptorAllocator::new(0);
+    second.import_pool_size_profile(&profile);
+    assert_eq!(run_session(&mut second), 1);
+
+    // Imported peaks are exported again.
+    assert_eq!(second.pool_size_profile(), profile);
+}
+
+#[test]
+fn larger_peak_wins_on_import() {
+    let mut allocator = DescriptorAllocator::new(0);
+    run_session(&mut allocator);
+
+    let mut profile = allocator.pool_size_profile();
+    profile.entries[0].peak = 10;
+    allocator.import_pool_size_profile(&profile);
+    assert_eq!(allocator.pool_size_profile().entries[0].peak, PEAK);
+
+    allocator.import_pool_size_profile(&PoolSizeProfile::default());
+    assert_eq!(allocator.pool_size_profile().entries[0].peak, PEAK);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn profile_serde_round_trip() {
+    let mut allocator = DescriptorAllocator::new(0);
+    run_session(&mut allocator);
+
+    let profile = allocator.pool_size_profile();
+    let json = serde_json::to_string(&profile).unwrap();
+    let restored: PoolSizeProfile = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, profile);
+}
@@ -0,0 +1,119 @@
+//! Tests of `OutOfMemoryPolicy`.
+
+use {
+    gpu_descriptor::{
+        mock::MockDevice, AllocationError, CreatePoolError, DescriptorAllocator, DescriptorKind,
+        DescriptorSetLayoutCreateFlags, DescriptorTotalCount, OutOfMemoryPolicy,
+    },
+    std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+fn layout(kind: DescriptorKind) -> DescriptorTotalCount {
+    DescriptorTotalCount::new().with(kind, 1)
+}
+
+/// Returns policy with one retry that counts calls to `release_memory`
+/// and reports whether anything was released.
+fn counting_policy(released: bool) -> (OutOfMemoryPolicy, Arc<AtomicU32>) {
+    let calls = Arc::new(AtomicU32::new(0));
+    let policy = OutOfMemoryPolicy {
+        retries: 1,
+        cleanup: false,
+        release_memory: Some(Box::new({
+            let calls = calls.clone();
+            move |err| {
+                assert!(matches!(err, AllocationError::OutOfDeviceMemory));
+                calls.fetch_add(1, Ordering::Relaxed);
+                released
+            }
+        })),
+    };
+    (policy, calls)
+}
+
+#[test]
+fn default_policy_does_not_retry() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = layout(DescriptorKind::UniformBuffer);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    device.fail_next_pool_creation(CreatePoolError::OutOfDeviceMemory);
+    let result = unsafe { allocator.allocate(&device, &layout, empty, &layout, 1) };
+    assert!(matches!(result, Err(AllocationError::OutOfDeviceMemory)));
+    assert_eq!(device.pools_created(), 0);
+}
+
+#[test]
+fn released_memory_callback_is_called_before_retry() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = layout(DescriptorKind::UniformBuffer);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    let (policy, calls) = counting_policy(true);
+    allocator.set_out_of_memory_policy(policy);
+
+    device.fail_next_pool_creation(CreatePoolError::OutOfDeviceMemory);
+    let sets = unsafe { allocator.allocate(&device, &layout, empty, &layout, 3) }.unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    assert_eq!(sets.len(), 3);
+    assert_eq!(device.pools_created(), 1);
+
+    unsafe {
+        allocator.free(&device, sets);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+}
+
+#[test]
+fn allocation_is_not_retried_if_nothing_was_released() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = layout(DescriptorKind::UniformBuffer);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    let (policy, calls) = counting_policy(false);
+    allocator.set_out_of_memory_policy(policy);
+
+    device.fail_next_pool_creation(CreatePoolError::OutOfDeviceMemory);
+    let result = unsafe { allocator.allocate(&device, &layout, empty, &layout, 1) };
+    assert!(matches!(result, Err(AllocationError::OutOfDeviceMemory)));
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    assert_eq!(device.pools_created(), 0);
+}
+
+#[test]
+fn cleanup_destroys_empty_pools_of_other_buckets_before_retry() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let samplers = layout(DescriptorKind::Sampler);
+    let buffers = layout(DescriptorKind::UniformBuffer);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    allocator.set_out_of_memory_policy(OutOfMemoryPolicy::cleanup_and_retry());
+
+    // Last pool of the bucket is kept empty for reuse.
+    unsafe {
+        let sets = allocator
+            .allocate(&device, &samplers, empty, &samplers, 1)
+            .unwrap();
+        allocator.free(&device, sets);
+    }
+    assert_eq!(device.live_pools(), 1);
+
+    device.fail_next_pool_creation(CreatePoolError::OutOfDeviceMemory);
+    let sets = unsafe { allocator.allocate(&device, &buffers, empty, &buffers, 1) }.unwrap();
+    assert_eq!(device.pools_destroyed(), 1);
+    assert_eq!(device.live_pools(), 1);
+
+    unsafe {
+        allocator.free(&device, sets);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+}
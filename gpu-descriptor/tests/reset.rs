@@ -0,0 +1,91 @@
+//! Tests of pool reset through `DescriptorDeviceV2`.
+
+use gpu_descriptor::{
+    mock::MockDevice, DescriptorAllocator, DescriptorDeviceCapabilities, DescriptorKind,
+    DescriptorSetLayoutCreateFlags, DescriptorSetLifetime, DescriptorTotalCount,
+};
+
+fn layout() -> DescriptorTotalCount {
+    DescriptorTotalCount::new()
+        .with(DescriptorKind::Sampler, 1)
+        .with(DescriptorKind::SampledImage, 1)
+}
+
+#[test]
+fn emptied_pool_is_reset_and_reused() {
+    let device = MockDevice::new();
+    device.set_capabilities(DescriptorDeviceCapabilities::RESET_POOL);
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = layout();
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+    let lifetime = DescriptorSetLifetime::Transient;
+
+    let mut sets = unsafe {
+        allocator
+            .allocate_with_lifetime(&device, &layout, empty, &layout, 4, lifetime)
+            .unwrap()
+    };
+    let pool = sets[0].raw().pool();
+
+    // Partially freed pool is not reset.
+    unsafe {
+        allocator.free(&device, sets.drain(..2));
+    }
+    assert_eq!(device.pools_reset(), 0);
+    assert_eq!(device.live_sets(), 2);
+
+    unsafe {
+        allocator.free(&device, sets);
+    }
+    assert_eq!(device.pools_reset(), 1);
+    assert_eq!(device.live_sets(), 0);
+
+    let sets = unsafe {
+        allocator
+            .allocate_with_lifetime(&device, &layout, empty, &layout, 4, lifetime)
+            .unwrap()
+    };
+    assert_eq!(device.pools_created(), 1);
+    assert!(sets.iter().all(|set| set.raw().pool() == pool));
+
+    unsafe {
+        allocator.free(&device, sets);
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.pools_reset(), 2);
+    assert_eq!(device.live_pools(), 0);
+}
+
+#[test]
+fn pool_is_not_reset_without_capability() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let layout = layout();
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    let sets = unsafe {
+        allocator
+            .allocate_with_lifetime(
+                &device,
+                &layout,
+                empty,
+                &layout,
+                4,
+                DescriptorSetLifetime::Transient,
+            )
+            .unwrap()
+    };
+
+    // Sets are freed one by one instead.
+    unsafe {
+        allocator.free(&device, sets);
+    }
+    assert_eq!(device.pools_reset(), 0);
+    assert_eq!(device.live_sets(), 0);
+    assert_eq!(device.live_pools(), 1);
+
+    unsafe {
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+}
@@ -0,0 +1,52 @@
+//! Tests of allocator statistics and adaptive pool sizing.
+
+use gpu_descriptor::{
+    mock::MockDevice, DescriptorAllocator, DescriptorKind, DescriptorSetLayoutCreateFlags,
+    DescriptorSetLifetime, DescriptorTotalCount, PoolSizing,
+};
+
+#[test]
+fn sets_per_allocation_follows_call_sizes() {
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    allocator.set_pool_sizing(PoolSizing::Adaptive);
+    let layout = DescriptorTotalCount::new().with(DescriptorKind::StorageBuffer, 1);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+    let lifetime = DescriptorSetLifetime::Transient;
+
+    let allocate_and_free = |allocator: &mut DescriptorAllocator<_, _>, count| unsafe {
+        let sets = allocator
+            .allocate_with_lifetime(&device, &layout, empty, &layout, count, lifetime)
+            .unwrap();
+        allocator.free(&device, sets);
+    };
+
+    for _ in 0..64 {
+        allocate_and_free(&mut allocator, 4);
+    }
+
+    let statistics = allocator.bucket_statistics().next().unwrap();
+    assert_eq!(statistics.sets_per_allocation, 4);
+    assert_eq!(statistics.peak_sets, 4);
+    assert_eq!(statistics.allocated_sets, 0);
+    // Room for a few more calls of the usual size.
+    assert_eq!(statistics.next_pool_sets, 16);
+
+    allocate_and_free(&mut allocator, 64);
+    let statistics = allocator.bucket_statistics().next().unwrap();
+    assert!(statistics.sets_per_allocation > 4);
+    assert_eq!(statistics.peak_sets, 64);
+
+    // Single spike fades away as call sizes settle.
+    for _ in 0..64 {
+        allocate_and_free(&mut allocator, 4);
+    }
+    let statistics = allocator.bucket_statistics().next().unwrap();
+    assert_eq!(statistics.sets_per_allocation, 4);
+    assert_eq!(statistics.next_pool_sets, 16);
+
+    unsafe {
+        allocator.cleanup(&device);
+    }
+    assert_eq!(device.live_pools(), 0);
+}
@@ -0,0 +1,322 @@
+//! Tests of allocation trace recording and replay.
+
+use {
+    gpu_descriptor::{
+        mock::MockDevice,
+        trace::{replay, RecordingAllocator, TraceReader, TraceRecord},
+        AllocationError, CreatePoolError, DescriptorAllocator, DescriptorAllocatorListener,
+        DescriptorKind, DescriptorSetLayoutCreateFlags, DescriptorSetLifetime,
+        DescriptorTotalCount, DeviceLimits, OutOfMemoryPolicy, PoolSizeProfile,
+        PoolSizeProfileEntry, PoolSizing,
+    },
+    std::io::{self, Read},
+};
+
+fn layout(sampler: u32, uniform_buffer: u32) -> DescriptorTotalCount {
+    DescriptorTotalCount::new()
+        .with(DescriptorKind::Sampler, sampler)
+        .with(DescriptorKind::UniformBuffer, uniform_buffer)
+}
+
+/// Records session that goes through every traced allocator entry point.
+fn record_session() -> Vec<u8> {
+    let device = MockDevice::new();
+    let mut recorder = RecordingAllocator::new(1024, Vec::new());
+    let small = layout(1, 0);
+    let large = layout(2, 8);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    unsafe {
+        let mut sets = recorder
+            .allocate(&device, &small, empty, &small, 70)
+            .unwrap();
+        sets.extend(
+            recorder
+                .allocate_with_lifetime(
+                    &device,
+                    &large,
+                    DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND,
+                    &large,
+                    5,
+                    DescriptorSetLifetime::Transient,
+                )
+                .unwrap(),
+        );
+        recorder
+            .allocate_into(
+                &device,
+                &large,
+                DescriptorSetLayoutCreateFlags::HOST_ONLY_POOL,
+                &large,
+                3,
+                DescriptorSetLifetime::Persistent,
+                &mut sets,
+            )
+            .unwrap();
+        sets.push(
+            recorder
+                .allocate_one(
+                    &device,
+                    &small,
+                    empty,
+                    &small,
+                    DescriptorSetLifetime::Transient,
+                )
+                .unwrap(),
+        );
+
+        // Exceeds update-after-bind limit.
+        assert!(recorder
+            .allocate(
+                &device,
+                &large,
+                DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND,
+                &large,
+                1000,
+            )
+            .is_err());
+
+        let rest = sets.split_off(40);
+        let mut index = 0;
+        let (every_third, others): (Vec<_>, Vec<_>) = sets.into_iter().partition(|_| {
+            index += 1;
+            index % 3 == 0
+        });
+        recorder.free(&device, every_third);
+        recorder.free_unordered(&device, rest.into_iter().rev());
+        recorder.free(&device, others);
+        recorder.cleanup(&device);
+
+        let stale = recorder
+            .allocate(&device, &small, empty, &small, 2)
+            .unwrap();
+        recorder.forget_all();
+
+        // Same bucket and pool id as stale sets, but new epoch.
+        let fresh = recorder
+            .allocate(&device, &small, empty, &small, 2)
+            .unwrap();
+        recorder.free(&device, stale);
+        recorder.free(&device, fresh);
+
+        let kept = recorder
+            .allocate(&device, &large, empty, &large, 4)
+            .unwrap();
+        recorder.destroy_all(&device);
+        recorder.free(&device, kept);
+    }
+
+    let (allocator, trace) = recorder.finish().unwrap();
+    drop(allocator);
+    trace
+}
+
+#[test]
+fn replay_matches_recording() {
+    let trace = record_session();
+
+    let mut reader = TraceReader::new(&trace[..]).unwrap();
+    assert_eq!(
+        reader.max_update_after_bind_descriptors_in_all_pools(),
+        1024
+    );
+
+    let device = MockDevice::new();
+    let mut allocator =
+        DescriptorAllocator::new(reader.max_update_after_bind_descriptors_in_all_pools());
+    let report = replay(&mut reader, &mut allocator, &device).unwrap();
+
+    assert_eq!(report.mismatches, 0);
+    assert_eq!(report.allocations, 8);
+    assert_eq!(report.frees, 6);
+    assert_eq!(report.cleanups, 1);
+    assert_eq!(report.resets, 2);
+    assert!(report.live_sets.is_empty());
+
+    // Pool with stale sets was forgotten and leaked, as it would be on device loss.
+    assert_eq!(device.live_sets(), 2);
+    assert_eq!(device.live_pools(), 1);
+
+    allocator.check_invariants();
+    unsafe { allocator.cleanup(&device) };
+}
+
+#[test]
+fn records_every_entry_point() {
+    let trace = record_session();
+    let mut reader = TraceReader::new(&trace[..]).unwrap();
+
+    let mut records = Vec::new();
+    while let Some(record) = reader.next_record().unwrap() {
+        records.push(record);
+    }
+
+    assert!(matches!(
+        records[1],
+        TraceRecord::Allocate {
+            lifetime: DescriptorSetLifetime::Transient,
+            count: 5,
+            ..
+        }
+    ));
+    assert!(matches!(
+        records[2],
+        TraceRecord::AllocateInto { count: 3, .. }
+    ));
+    assert!(matches!(records[3], TraceRecord::AllocateOne { .. }));
+    assert!(records
+        .iter()
+        .any(|record| matches!(record, TraceRecord::FreeUnordered { .. })));
+    assert!(records.contains(&TraceRecord::Cleanup));
+    assert!(records.contains(&TraceRecord::ForgetAll));
+    assert!(records.contains(&TraceRecord::DestroyAll));
+}
+
+struct NoopListener;
+
+impl DescriptorAllocatorListener for NoopListener {}
+
+#[test]
+fn configuration_is_replayed() {
+    let layout = layout(0, 4);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+    let limits = DeviceLimits {
+        max_pool_sets: 16,
+        max_descriptor_set_uniform_buffers: 8,
+        ..DeviceLimits::UNBOUNDED
+    };
+    let profile = PoolSizeProfile {
+        entries: vec![PoolSizeProfileEntry {
+            size: layout,
+            update_after_bind: false,
+            host_only: false,
+            lifetime: DescriptorSetLifetime::Persistent,
+            peak: 100,
+        }],
+    };
+
+    let recorded_device = MockDevice::new();
+    let mut recorder = RecordingAllocator::new(0, Vec::new());
+    recorder.set_device_limits(limits);
+    recorder.set_pool_sizing(PoolSizing::Adaptive);
+    recorder.set_out_of_memory_policy(OutOfMemoryPolicy {
+        release_memory: Some(Box::new(|_| false)),
+        ..OutOfMemoryPolicy::cleanup_and_retry()
+    });
+    recorder.set_listener(Box::new(NoopListener));
+    recorder.import_pool_size_profile(&profile);
+
+    unsafe {
+        let sets = recorder
+            .allocate(&recorded_device, &layout, empty, &layout, 40)
+            .unwrap();
+
+        // Exceeds per-set limit.
+        let large = layout.with(DescriptorKind::UniformBuffer, 16);
+        assert!(matches!(
+            recorder.allocate(&recorded_device, &large, empty, &large, 1),
+            Err(AllocationError::LimitExceeded(_))
+        ));
+
+        recorder.free(&recorded_device, sets);
+    }
+    let (recorded, trace) = recorder.finish().unwrap();
+
+    let mut reader = TraceReader::new(&trace[..]).unwrap();
+    let mut records = Vec::new();
+    while let Some(record) = reader.next_record().unwrap() {
+        records.push(record);
+    }
+    assert_eq!(
+        records[..4],
+        [
+            TraceRecord::SetDeviceLimits(limits),
+            TraceRecord::SetPoolSizing(PoolSizing::Adaptive),
+            TraceRecord::SetOutOfMemoryPolicy {
+                retries: 1,
+                cleanup: true,
+                release_memory: true,
+            },
+            TraceRecord::ImportPoolSizeProfile(profile.clone()),
+        ]
+    );
+
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let mut reader = TraceReader::new(&trace[..]).unwrap();
+    let report = replay(&mut reader, &mut allocator, &device).unwrap();
+
+    assert_eq!(report.mismatches, 0);
+    assert_eq!(report.configs, 4);
+    assert_eq!(allocator.device_limits(), &limits);
+    assert_eq!(allocator.pool_sizing(), PoolSizing::Adaptive);
+    assert_eq!(allocator.pool_size_profile(), recorded.pool_size_profile());
+    assert_eq!(device.pools_created(), recorded_device.pools_created());
+
+    unsafe { allocator.cleanup(&device) };
+}
+
+#[test]
+fn device_errors_are_not_mismatches() {
+    let layout = layout(1, 0);
+    let empty = DescriptorSetLayoutCreateFlags::empty();
+
+    let recorded_device = MockDevice::new();
+    let mut recorder = RecordingAllocator::new(0, Vec::new());
+    unsafe {
+        recorded_device.fail_next_pool_creation(CreatePoolError::OutOfDeviceMemory);
+        assert!(matches!(
+            recorder.allocate(&recorded_device, &layout, empty, &layout, 1),
+            Err(AllocationError::OutOfDeviceMemory)
+        ));
+    }
+    let (_, trace) = recorder.finish().unwrap();
+
+    let device = MockDevice::new();
+    let mut allocator = DescriptorAllocator::new(0);
+    let mut reader = TraceReader::new(&trace[..]).unwrap();
+    let report = replay(&mut reader, &mut allocator, &device).unwrap();
+
+    // Replay succeeds where recorded device ran out of memory.
+    assert_eq!(report.mismatches, 0);
+    assert_eq!(report.device_errors, 1);
+    assert_eq!(report.live_sets.len(), 1);
+
+    unsafe {
+        allocator.free(&device, report.live_sets);
+        allocator.cleanup(&device);
+    }
+}
+
+/// Reader that is interrupted before every successful read.
+struct Interrupting<'a> {
+    data: &'a [u8],
+    interrupt: bool,
+}
+
+impl Read for Interrupting<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        self.data.read(buf)
+    }
+}
+
+#[test]
+fn interrupted_reads_are_retried() {
+    let trace = record_session();
+    let reader = Interrupting {
+        data: &trace,
+        interrupt: false,
+    };
+
+    let mut reader = TraceReader::new(reader).unwrap();
+    let device = MockDevice::new();
+    let mut allocator =
+        DescriptorAllocator::new(reader.max_update_after_bind_descriptors_in_all_pools());
+    let report = replay(&mut reader, &mut allocator, &device).unwrap();
+    assert_eq!(report.mismatches, 0);
+    unsafe { allocator.cleanup(&device) };
+}
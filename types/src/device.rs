@@ -1,4 +1,15 @@
-use crate::types::{DescriptorPoolCreateFlags, DescriptorTotalCount};
+use {
+    crate::types::{
+        DescriptorBufferUsageFlags, DescriptorCountError, DescriptorDeviceCapabilities,
+        DescriptorPoolCreateFlags, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateFlags,
+        DescriptorTotalCount, DescriptorType,
+    },
+    alloc::{boxed::Box, sync::Arc},
+    core::{
+        fmt::{self, Debug},
+        marker::PhantomData,
+    },
+};
 
 /// Memory exhausted error.
 #[derive(Debug)]
@@ -13,6 +24,41 @@ pub enum CreatePoolError {
     Fragmentation,
 }
 
+/// Error that may occur when creating descriptor set layout.
+#[derive(Debug)]
+pub enum CreateLayoutError {
+    /// Device memory exhausted.
+    OutOfDeviceMemory,
+
+    /// Host memory exhausted.
+    OutOfHostMemory,
+
+    /// Descriptors of the bindings cannot be counted.
+    DescriptorCount(DescriptorCountError),
+
+    /// Device does not support descriptors of this type in layouts.
+    UnsupportedDescriptorType(DescriptorType),
+}
+
+impl From<DescriptorCountError> for CreateLayoutError {
+    fn from(err: DescriptorCountError) -> Self {
+        CreateLayoutError::DescriptorCount(err)
+    }
+}
+
+/// Error that may occur when creating descriptor buffer.
+#[derive(Debug)]
+pub enum CreateBufferError {
+    /// Device memory exhausted.
+    OutOfDeviceMemory,
+
+    /// Host memory exhausted.
+    OutOfHostMemory,
+
+    /// Buffer cannot be bound to memory of the type the device allocates.
+    IncompatibleMemoryType,
+}
+
 /// Memory exhausted error.
 #[derive(Debug)]
 pub enum DeviceAllocationError {
@@ -30,6 +76,9 @@ pub enum DeviceAllocationError {
 }
 
 /// Abstract device that can create pools of type `P` and allocate sets `S` with layout `L`.
+///
+/// New backends should implement `DescriptorDeviceV2` instead.
+/// Wrap existing implementations into `LegacyDescriptorDevice` to use them with `DescriptorAllocator`.
 pub trait DescriptorDevice<L, P, S> {
     /// Creates a new descriptor pool.
     ///
@@ -73,3 +122,336 @@ pub trait DescriptorDevice<L, P, S> {
     /// Sets must be allocated from specified pool and not deallocated before.
     unsafe fn dealloc_descriptor_sets(&self, pool: &mut P, sets: impl Iterator<Item = S>);
 }
+
+/// Abstract device that can create descriptor pools and allocate descriptor sets.
+///
+/// Supersedes `DescriptorDevice`.
+/// Devices implementing only `DescriptorDevice` can be used through `LegacyDescriptorDevice`.
+pub trait DescriptorDeviceV2 {
+    /// Descriptor set layout type.
+    type Layout;
+
+    /// Descriptor pool type.
+    type Pool;
+
+    /// Descriptor set type.
+    type Set;
+
+    /// Returns optional capabilities of the device.
+    fn capabilities(&self) -> DescriptorDeviceCapabilities {
+        DescriptorDeviceCapabilities::empty()
+    }
+
+    /// Creates a new descriptor pool.
+    fn create_descriptor_pool(
+        &self,
+        descriptor_count: &DescriptorTotalCount,
+        max_sets: u32,
+        flags: DescriptorPoolCreateFlags,
+    ) -> Result<Self::Pool, CreatePoolError>;
+
+    /// Destroys descriptor pool.
+    ///
+    /// # Safety
+    ///
+    /// Pool must be created from this device.
+    /// All descriptor sets allocated from this pool become invalid.
+    unsafe fn destroy_descriptor_pool(&self, pool: Self::Pool);
+
+    /// Returns all descriptor sets allocated from the pool back to the pool.
+    ///
+    /// Called only if `capabilities` contain `RESET_POOL`,
+    /// so devices reporting `RESET_POOL` must implement this method.
+    ///
+    /// # Panics
+    ///
+    /// Default implementation panics, as devices without `RESET_POOL` are never asked to reset.
+    ///
+    /// # Safety
+    ///
+    /// Pool must be created from this device.
+    /// All descriptor sets allocated from this pool become invalid.
+    unsafe fn reset_descriptor_pool(&self, pool: &mut Self::Pool) {
+        let _ = pool;
+        panic!("Device does not support descriptor pool reset")
+    }
+
+    /// Allocates descriptor sets.
+    ///
+    /// # Safety
+    ///
+    /// Pool must be created from this device.
+    unsafe fn alloc_descriptor_sets<'a>(
+        &self,
+        pool: &mut Self::Pool,
+        layouts: impl ExactSizeIterator<Item = &'a Self::Layout>,
+        sets: &mut impl Extend<Self::Set>,
+    ) -> Result<(), DeviceAllocationError>
+    where
+        Self::Layout: 'a;
+
+    /// Deallocates descriptor sets.
+    ///
+    /// # Safety
+    ///
+    /// Sets must be allocated from specified pool and not deallocated before.
+    unsafe fn dealloc_descriptor_sets(
+        &self,
+        pool: &mut Self::Pool,
+        sets: impl Iterator<Item = Self::Set>,
+    );
+}
+
+/// Adapter that implements `DescriptorDeviceV2` for device implementing `DescriptorDevice`.
+///
+/// Legacy devices have no optional capabilities.
+pub struct LegacyDescriptorDevice<D, L, P, S> {
+    device: D,
+    marker: Marker<L, P, S>,
+}
+
+type Marker<L, P, S> = PhantomData<fn() -> (L, P, S)>;
+
+impl<D, L, P, S> Clone for LegacyDescriptorDevice<D, L, P, S>
+where
+    D: Clone,
+{
+    fn clone(&self) -> Self {
+        LegacyDescriptorDevice::new(self.device.clone())
+    }
+}
+
+impl<D, L, P, S> Copy for LegacyDescriptorDevice<D, L, P, S> where D: Copy {}
+
+impl<D, L, P, S> Debug for LegacyDescriptorDevice<D, L, P, S>
+where
+    D: Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_tuple("LegacyDescriptorDevice")
+            .field(&self.device)
+            .finish()
+    }
+}
+
+impl<D, L, P, S> LegacyDescriptorDevice<D, L, P, S> {
+    /// Wraps legacy device.
+    pub fn new(device: D) -> Self {
+        LegacyDescriptorDevice {
+            device,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns reference to wrapped device.
+    pub fn inner(&self) -> &D {
+        &self.device
+    }
+
+    /// Unwraps legacy device.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D, L, P, S> DescriptorDeviceV2 for LegacyDescriptorDevice<D, L, P, S>
+where
+    D: DescriptorDevice<L, P, S>,
+{
+    type Layout = L;
+    type Pool = P;
+    type Set = S;
+
+    fn create_descriptor_pool(
+        &self,
+        descriptor_count: &DescriptorTotalCount,
+        max_sets: u32,
+        flags: DescriptorPoolCreateFlags,
+    ) -> Result<P, CreatePoolError> {
+        // Safe, see `DescriptorDevice::create_descriptor_pool`.
+        unsafe {
+            self.device
+                .create_descriptor_pool(descriptor_count, max_sets, flags)
+        }
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, pool: P) {
+        self.device.destroy_descriptor_pool(pool)
+    }
+
+    unsafe fn alloc_descriptor_sets<'a>(
+        &self,
+        pool: &mut P,
+        layouts: impl ExactSizeIterator<Item = &'a L>,
+        sets: &mut impl Extend<S>,
+    ) -> Result<(), DeviceAllocationError>
+    where
+        L: 'a,
+    {
+        self.device.alloc_descriptor_sets(pool, layouts, sets)
+    }
+
+    unsafe fn dealloc_descriptor_sets(&self, pool: &mut P, sets: impl Iterator<Item = S>) {
+        self.device.dealloc_descriptor_sets(pool, sets)
+    }
+}
+
+/// Implements device traits for pointers to devices,
+/// so that owners of the allocator can keep their device however they like.
+macro_rules! forward_device {
+    ($($ptr:ty),*) => {
+        $(
+            impl<T, L, P, S> DescriptorDevice<L, P, S> for $ptr
+            where
+                T: DescriptorDevice<L, P, S> + ?Sized,
+            {
+                unsafe fn create_descriptor_pool(
+                    &self,
+                    descriptor_count: &DescriptorTotalCount,
+                    max_sets: u32,
+                    flags: DescriptorPoolCreateFlags,
+                ) -> Result<P, CreatePoolError> {
+                    T::create_descriptor_pool(self, descriptor_count, max_sets, flags)
+                }
+
+                unsafe fn destroy_descriptor_pool(&self, pool: P) {
+                    T::destroy_descriptor_pool(self, pool)
+                }
+
+                unsafe fn alloc_descriptor_sets<'a>(
+                    &self,
+                    pool: &mut P,
+                    layouts: impl ExactSizeIterator<Item = &'a L>,
+                    sets: &mut impl Extend<S>,
+                ) -> Result<(), DeviceAllocationError>
+                where
+                    L: 'a,
+                {
+                    T::alloc_descriptor_sets(self, pool, layouts, sets)
+                }
+
+                unsafe fn dealloc_descriptor_sets(
+                    &self,
+                    pool: &mut P,
+                    sets: impl Iterator<Item = S>,
+                ) {
+                    T::dealloc_descriptor_sets(self, pool, sets)
+                }
+            }
+
+            impl<T> DescriptorDeviceV2 for $ptr
+            where
+                T: DescriptorDeviceV2 + ?Sized,
+            {
+                type Layout = T::Layout;
+                type Pool = T::Pool;
+                type Set = T::Set;
+
+                fn capabilities(&self) -> DescriptorDeviceCapabilities {
+                    T::capabilities(self)
+                }
+
+                fn create_descriptor_pool(
+                    &self,
+                    descriptor_count: &DescriptorTotalCount,
+                    max_sets: u32,
+                    flags: DescriptorPoolCreateFlags,
+                ) -> Result<T::Pool, CreatePoolError> {
+                    T::create_descriptor_pool(self, descriptor_count, max_sets, flags)
+                }
+
+                unsafe fn destroy_descriptor_pool(&self, pool: T::Pool) {
+                    T::destroy_descriptor_pool(self, pool)
+                }
+
+                unsafe fn reset_descriptor_pool(&self, pool: &mut T::Pool) {
+                    T::reset_descriptor_pool(self, pool)
+                }
+
+                unsafe fn alloc_descriptor_sets<'a>(
+                    &self,
+                    pool: &mut T::Pool,
+                    layouts: impl ExactSizeIterator<Item = &'a T::Layout>,
+                    sets: &mut impl Extend<T::Set>,
+                ) -> Result<(), DeviceAllocationError>
+                where
+                    T::Layout: 'a,
+                {
+                    T::alloc_descriptor_sets(self, pool, layouts, sets)
+                }
+
+                unsafe fn dealloc_descriptor_sets(
+                    &self,
+                    pool: &mut T::Pool,
+                    sets: impl Iterator<Item = T::Set>,
+                ) {
+                    T::dealloc_descriptor_sets(self, pool, sets)
+                }
+            }
+        )*
+    };
+}
+
+forward_device!(&T, Box<T>, Arc<T>);
+
+/// Abstract device that can create descriptor set layouts of type `L`.
+pub trait DescriptorSetLayoutDevice<L> {
+    /// Creates a new descriptor set layout.
+    ///
+    /// # Safety
+    ///
+    /// Binding numbers must be unique.
+    unsafe fn create_descriptor_set_layout(
+        &self,
+        bindings: &[DescriptorSetLayoutBinding],
+        flags: DescriptorSetLayoutCreateFlags,
+    ) -> Result<L, CreateLayoutError>;
+
+    /// Destroys descriptor set layout.
+    ///
+    /// # Safety
+    ///
+    /// Layout must be created from this device.
+    unsafe fn destroy_descriptor_set_layout(&self, layout: L);
+}
+
+/// Abstract device that can create descriptor buffers of type `B`
+/// and place descriptor sets with layout `L` into them.
+pub trait DescriptorBufferDevice<L, B> {
+    /// Returns alignment required for descriptor set offsets within descriptor buffer.
+    fn descriptor_buffer_offset_alignment(&self) -> u64;
+
+    /// Returns number of bytes descriptor set with specified layout occupies in descriptor buffer.
+    ///
+    /// # Safety
+    ///
+    /// Layout must be created from this device for use with descriptor buffers.
+    unsafe fn get_descriptor_set_layout_size(&self, layout: &L) -> u64;
+
+    /// Returns offset of the binding from the start of descriptor set in descriptor buffer.
+    ///
+    /// # Safety
+    ///
+    /// Layout must be created from this device for use with descriptor buffers.
+    /// Layout must have specified binding.
+    unsafe fn get_descriptor_set_layout_binding_offset(&self, layout: &L, binding: u32) -> u64;
+
+    /// Creates a new descriptor buffer.
+    ///
+    /// # Safety
+    ///
+    /// `size` must not be zero.
+    unsafe fn create_descriptor_buffer(
+        &self,
+        size: u64,
+        usage: DescriptorBufferUsageFlags,
+    ) -> Result<B, CreateBufferError>;
+
+    /// Destroys descriptor buffer.
+    ///
+    /// # Safety
+    ///
+    /// Buffer must be created from this device.
+    /// Buffer must not be in use by the device.
+    unsafe fn destroy_descriptor_buffer(&self, buffer: B);
+}
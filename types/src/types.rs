@@ -1,3 +1,9 @@
+use core::{
+    fmt::{self, Debug},
+    iter::FromIterator,
+    ops::{Add, AddAssign, Sub, SubAssign},
+};
+
 bitflags::bitflags! {
     /// Flags to augment descriptor pool creation.
     ///
@@ -9,45 +15,1124 @@ bitflags::bitflags! {
 
         /// Allows allocating sets with layout created with matching backend-specific flag.
         const UPDATE_AFTER_BIND = 0x2;
+
+        /// Pool and its sets are only usable on host, e.g. as copy source.
+        /// Pool creation and set allocation may be cheaper.
+        ///
+        /// Cannot be combined with `UPDATE_AFTER_BIND`.
+        const HOST_ONLY = 0x4;
+    }
+}
+
+bitflags::bitflags! {
+    /// Optional capabilities of descriptor device.
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+    pub struct DescriptorDeviceCapabilities: u32 {
+        /// Device can return all sets to a pool at once with `reset_descriptor_pool`.
+        const RESET_POOL = 0x1;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags to augment descriptor set allocation.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+    pub struct DescriptorSetLayoutCreateFlags: u32 {
+        /// Specified that descriptor set must be allocated from\
+        /// pool with `DescriptorPoolCreateFlags::UPDATE_AFTER_BIND`.
+        ///
+        /// This flag must be specified when and only when layout was created with matching backend-specific flag,
+        /// that allows layout to have UpdateAfterBind bindings.
+        const UPDATE_AFTER_BIND = 0x2;
+
+        /// Specified that descriptor set must be allocated from\
+        /// pool with `DescriptorPoolCreateFlags::HOST_ONLY`.
+        ///
+        /// This flag must be specified when and only when layout was created with matching backend-specific flag.
+        /// Cannot be combined with `UPDATE_AFTER_BIND`.
+        const HOST_ONLY_POOL = 0x4;
+
+        /// Specifies that layout is used with descriptor buffers
+        /// and its sets are allocated with `DescriptorBufferAllocator`.
+        ///
+        /// Backends create such layouts with matching backend-specific flag.
+        /// Descriptor pools cannot serve such layouts.
+        const DESCRIPTOR_BUFFER = 0x8;
+    }
+}
+
+bitflags::bitflags! {
+    /// Kinds of descriptors a descriptor buffer can hold.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+    pub struct DescriptorBufferUsageFlags: u32 {
+        /// Buffer can hold sampler and combined image sampler descriptors.
+        const SAMPLER = 0x1;
+
+        /// Buffer can hold all other descriptors.
+        const RESOURCE = 0x2;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags to augment individual descriptor set layout bindings.
+    ///
+    /// Match corresponding bits in Vulkan.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+    pub struct DescriptorBindingFlags: u32 {
+        /// Descriptors in the binding can be updated after set is bound.
+        const UPDATE_AFTER_BIND = 0x1;
+
+        /// Descriptors not used by pending command buffers can be updated.
+        const UPDATE_UNUSED_WHILE_PENDING = 0x2;
+
+        /// Descriptors in the binding that are not dynamically used need not be valid.
+        const PARTIALLY_BOUND = 0x4;
+
+        /// Binding has variable size that is specified when set is allocated.
+        const VARIABLE_DESCRIPTOR_COUNT = 0x8;
+    }
+}
+
+bitflags::bitflags! {
+    /// Shader stages that can access a binding.
+    ///
+    /// Match corresponding bits in Vulkan.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+    pub struct ShaderStageFlags: u32 {
+        const VERTEX = 0x1;
+        const TESSELLATION_CONTROL = 0x2;
+        const TESSELLATION_EVALUATION = 0x4;
+        const GEOMETRY = 0x8;
+        const FRAGMENT = 0x10;
+        const COMPUTE = 0x20;
+        const TASK = 0x40;
+        const MESH = 0x80;
+        const RAYGEN = 0x100;
+        const ANY_HIT = 0x200;
+        const CLOSEST_HIT = 0x400;
+        const MISS = 0x800;
+        const INTERSECTION = 0x1000;
+        const CALLABLE = 0x2000;
+        const ALL_GRAPHICS = 0x1F;
+        const ALL = 0x7FFF_FFFF;
+    }
+}
+
+/// Type of descriptors in a binding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DescriptorType {
+    Sampler,
+    CombinedImageSampler,
+    SampledImage,
+    StorageImage,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    UniformBuffer,
+    StorageBuffer,
+    UniformBufferDynamic,
+    StorageBufferDynamic,
+    InputAttachment,
+    AccelerationStructure,
+    InlineUniformBlock,
+}
+
+/// Backend agnostic description of one descriptor set layout binding.
+///
+/// Immutable samplers are not supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DescriptorSetLayoutBinding {
+    /// Binding number.
+    pub binding: u32,
+
+    /// Type of descriptors in the binding.
+    pub ty: DescriptorType,
+
+    /// Number of descriptors in the binding.
+    ///
+    /// For `InlineUniformBlock` this value is number of bytes instead.
+    pub count: u32,
+
+    /// Shader stages that can access the binding.
+    pub stages: ShaderStageFlags,
+
+    /// Additional binding flags.
+    pub flags: DescriptorBindingFlags,
+}
+
+/// Kind of descriptors counted by `DescriptorTotalCount`.
+///
+/// Inline uniform blocks are counted twice, in bytes and in bindings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DescriptorKind {
+    Sampler,
+    CombinedImageSampler,
+    SampledImage,
+    StorageImage,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    UniformBuffer,
+    StorageBuffer,
+    UniformBufferDynamic,
+    StorageBufferDynamic,
+    InputAttachment,
+    AccelerationStructure,
+    InlineUniformBlockBytes,
+    InlineUniformBlockBindings,
+
+    /// Descriptor type unknown to this crate, e.g. from vendor extension.
+    ///
+    /// Value is backend-specific raw descriptor type,
+    /// e.g. `VkDescriptorType` for Vulkan backends.
+    Vendor(i32),
+}
+
+impl DescriptorKind {
+    /// All kinds except `Vendor`.
+    pub const KNOWN: [DescriptorKind; 14] = [
+        DescriptorKind::Sampler,
+        DescriptorKind::CombinedImageSampler,
+        DescriptorKind::SampledImage,
+        DescriptorKind::StorageImage,
+        DescriptorKind::UniformTexelBuffer,
+        DescriptorKind::StorageTexelBuffer,
+        DescriptorKind::UniformBuffer,
+        DescriptorKind::StorageBuffer,
+        DescriptorKind::UniformBufferDynamic,
+        DescriptorKind::StorageBufferDynamic,
+        DescriptorKind::InputAttachment,
+        DescriptorKind::AccelerationStructure,
+        DescriptorKind::InlineUniformBlockBytes,
+        DescriptorKind::InlineUniformBlockBindings,
+    ];
+
+    /// Returns index of the kind in `KNOWN`, or `None` for `Vendor`.
+    pub fn index(self) -> Option<usize> {
+        match self {
+            DescriptorKind::Sampler => Some(0),
+            DescriptorKind::CombinedImageSampler => Some(1),
+            DescriptorKind::SampledImage => Some(2),
+            DescriptorKind::StorageImage => Some(3),
+            DescriptorKind::UniformTexelBuffer => Some(4),
+            DescriptorKind::StorageTexelBuffer => Some(5),
+            DescriptorKind::UniformBuffer => Some(6),
+            DescriptorKind::StorageBuffer => Some(7),
+            DescriptorKind::UniformBufferDynamic => Some(8),
+            DescriptorKind::StorageBufferDynamic => Some(9),
+            DescriptorKind::InputAttachment => Some(10),
+            DescriptorKind::AccelerationStructure => Some(11),
+            DescriptorKind::InlineUniformBlockBytes => Some(12),
+            DescriptorKind::InlineUniformBlockBindings => Some(13),
+            DescriptorKind::Vendor(_) => None,
+        }
+    }
+}
+
+impl From<DescriptorType> for DescriptorKind {
+    /// Inline uniform blocks are mapped to `InlineUniformBlockBytes`.
+    fn from(ty: DescriptorType) -> Self {
+        match ty {
+            DescriptorType::Sampler => DescriptorKind::Sampler,
+            DescriptorType::CombinedImageSampler => DescriptorKind::CombinedImageSampler,
+            DescriptorType::SampledImage => DescriptorKind::SampledImage,
+            DescriptorType::StorageImage => DescriptorKind::StorageImage,
+            DescriptorType::UniformTexelBuffer => DescriptorKind::UniformTexelBuffer,
+            DescriptorType::StorageTexelBuffer => DescriptorKind::StorageTexelBuffer,
+            DescriptorType::UniformBuffer => DescriptorKind::UniformBuffer,
+            DescriptorType::StorageBuffer => DescriptorKind::StorageBuffer,
+            DescriptorType::UniformBufferDynamic => DescriptorKind::UniformBufferDynamic,
+            DescriptorType::StorageBufferDynamic => DescriptorKind::StorageBufferDynamic,
+            DescriptorType::InputAttachment => DescriptorKind::InputAttachment,
+            DescriptorType::AccelerationStructure => DescriptorKind::AccelerationStructure,
+            DescriptorType::InlineUniformBlock => DescriptorKind::InlineUniformBlockBytes,
+        }
+    }
+}
+
+/// Maximum number of distinct `DescriptorKind::Vendor` kinds in one `DescriptorTotalCount`.
+pub const MAX_VENDOR_DESCRIPTOR_KINDS: usize = 4;
+
+/// Error of arithmetic on `DescriptorTotalCount`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DescriptorCountError {
+    /// Count of some kind does not fit into `u32`.
+    Overflow,
+
+    /// Result would have more than `MAX_VENDOR_DESCRIPTOR_KINDS` vendor kinds.
+    TooManyVendorKinds,
+}
+
+impl fmt::Display for DescriptorCountError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescriptorCountError::Overflow => fmt.write_str("Descriptor count overflow"),
+            DescriptorCountError::TooManyVendorKinds => {
+                fmt.write_str("Too many vendor descriptor kinds")
+            }
+        }
     }
 }
 
-/// Number of descriptors of each type.
+/// Number of descriptors of each kind.
 ///
-/// For `InlineUniformBlock` this value is number of bytes instead.
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// For inline uniform blocks number of bytes and number of bindings are counted.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct DescriptorTotalCount {
-    pub sampler: u32,
-    pub combined_image_sampler: u32,
-    pub sampled_image: u32,
-    pub storage_image: u32,
-    pub uniform_texel_buffer: u32,
-    pub storage_texel_buffer: u32,
-    pub uniform_buffer: u32,
-    pub storage_buffer: u32,
-    pub uniform_buffer_dynamic: u32,
-    pub storage_buffer_dynamic: u32,
-    pub input_attachment: u32,
-    pub acceleration_structure: u32,
-    pub inline_uniform_block_bytes: u32,
-    pub inline_uniform_block_bindings: u32,
+    /// Counts of `DescriptorKind::KNOWN` kinds.
+    known: [u32; 14],
+
+    /// Counts of vendor kinds with their raw types.
+    /// Non-zero counts come first sorted by raw type, unused entries are zeroed.
+    vendor: [(i32, u32); MAX_VENDOR_DESCRIPTOR_KINDS],
+}
+
+macro_rules! named_accessors {
+    ($($get:ident, $set:ident => $kind:ident;)*) => {
+        impl DescriptorTotalCount {
+            $(
+                #[doc = concat!("Returns count of `DescriptorKind::", stringify!($kind), "`.")]
+                pub fn $get(&self) -> u32 {
+                    self.get(DescriptorKind::$kind)
+                }
+
+                #[doc = concat!("Sets count of `DescriptorKind::", stringify!($kind), "`.")]
+                pub fn $set(&mut self, count: u32) {
+                    self.set(DescriptorKind::$kind, count)
+                }
+            )*
+        }
+    };
+}
+
+named_accessors! {
+    sampler, set_sampler => Sampler;
+    combined_image_sampler, set_combined_image_sampler => CombinedImageSampler;
+    sampled_image, set_sampled_image => SampledImage;
+    storage_image, set_storage_image => StorageImage;
+    uniform_texel_buffer, set_uniform_texel_buffer => UniformTexelBuffer;
+    storage_texel_buffer, set_storage_texel_buffer => StorageTexelBuffer;
+    uniform_buffer, set_uniform_buffer => UniformBuffer;
+    storage_buffer, set_storage_buffer => StorageBuffer;
+    uniform_buffer_dynamic, set_uniform_buffer_dynamic => UniformBufferDynamic;
+    storage_buffer_dynamic, set_storage_buffer_dynamic => StorageBufferDynamic;
+    input_attachment, set_input_attachment => InputAttachment;
+    acceleration_structure, set_acceleration_structure => AccelerationStructure;
+    inline_uniform_block_bytes, set_inline_uniform_block_bytes => InlineUniformBlockBytes;
+    inline_uniform_block_bindings, set_inline_uniform_block_bindings => InlineUniformBlockBindings;
 }
 
 impl DescriptorTotalCount {
+    /// Returns counts with all kinds set to zero.
+    pub const fn new() -> Self {
+        DescriptorTotalCount {
+            known: [0; 14],
+            vendor: [(0, 0); MAX_VENDOR_DESCRIPTOR_KINDS],
+        }
+    }
+
+    /// Counts descriptors in a layout with specified bindings.
+    pub fn from_bindings(
+        bindings: &[DescriptorSetLayoutBinding],
+    ) -> Result<Self, DescriptorCountError> {
+        let mut result = DescriptorTotalCount::new();
+        for binding in bindings {
+            result.add_count(binding.ty.into(), binding.count)?;
+            if binding.ty == DescriptorType::InlineUniformBlock {
+                result.add_count(DescriptorKind::InlineUniformBlockBindings, 1)?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Counts descriptors of `(kind, count)` pairs.
+    /// Counts of repeated kinds are summed.
+    pub fn try_from_counts(
+        counts: impl IntoIterator<Item = (DescriptorKind, u32)>,
+    ) -> Result<Self, DescriptorCountError> {
+        let mut result = DescriptorTotalCount::new();
+        for (kind, count) in counts {
+            result.add_count(kind, count)?;
+        }
+        Ok(result)
+    }
+
+    /// Returns count of specified kind.
+    pub fn get(&self, kind: DescriptorKind) -> u32 {
+        match kind.index() {
+            Some(index) => self.known[index],
+            None => self.vendor_slot(kind).map_or(0, |slot| self.vendor[slot].1),
+        }
+    }
+
+    /// Sets count of specified kind.
+    ///
+    /// # Panics
+    ///
+    /// Panics if non-zero count would be set for more than
+    /// `MAX_VENDOR_DESCRIPTOR_KINDS` vendor kinds.
+    pub fn set(&mut self, kind: DescriptorKind, count: u32) {
+        if let Err(err) = self.try_set(kind, count) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Adds to count of specified kind.
+    /// Count is left unchanged on error.
+    pub fn add_count(
+        &mut self,
+        kind: DescriptorKind,
+        count: u32,
+    ) -> Result<(), DescriptorCountError> {
+        let count = self
+            .get(kind)
+            .checked_add(count)
+            .ok_or(DescriptorCountError::Overflow)?;
+        self.try_set(kind, count)
+    }
+
+    /// Returns counts with count of specified kind replaced.
+    ///
+    /// # Panics
+    ///
+    /// Same as for `set`.
+    pub fn with(mut self, kind: DescriptorKind, count: u32) -> Self {
+        self.set(kind, count);
+        self
+    }
+
+    /// Iterates over kinds with non-zero counts.
+    /// Known kinds come first in order of `DescriptorKind::KNOWN`,
+    /// followed by vendor kinds in order of their raw types.
+    pub fn iter(&self) -> impl Iterator<Item = (DescriptorKind, u32)> + '_ {
+        let known = DescriptorKind::KNOWN
+            .iter()
+            .copied()
+            .zip(self.known.iter().copied());
+        let vendor = self.vendor[..self.vendor_len()]
+            .iter()
+            .map(|&(raw, count)| (DescriptorKind::Vendor(raw), count));
+        known.chain(vendor).filter(|&(_, count)| count > 0)
+    }
+
+    /// Returns `true` if all counts are zero.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Returns total number of descriptors.
+    ///
+    /// Overflow is not checked, use `checked_total` for untrusted counts.
     pub fn total(&self) -> u32 {
-        self.sampler
-            + self.combined_image_sampler
-            + self.sampled_image
-            + self.storage_image
-            + self.uniform_texel_buffer
-            + self.storage_texel_buffer
-            + self.uniform_buffer
-            + self.storage_buffer
-            + self.uniform_buffer_dynamic
-            + self.storage_buffer_dynamic
-            + self.input_attachment
-            + self.acceleration_structure
-            + self.inline_uniform_block_bytes
-            + self.inline_uniform_block_bindings
+        self.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Returns total number of descriptors, or `None` on overflow.
+    pub fn checked_total(&self) -> Option<u32> {
+        self.iter()
+            .try_fold(0u32, |total, (_, count)| total.checked_add(count))
+    }
+
+    /// Adds counts of each kind.
+    pub fn checked_add(self, other: Self) -> Result<Self, DescriptorCountError> {
+        self.zip_with(&other, u32::checked_add)
+    }
+
+    /// Subtracts counts of each kind, returns `None` on underflow.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        // Result has no vendor kinds absent in `self`, so only underflow can fail.
+        self.zip_with(&other, u32::checked_sub).ok()
+    }
+
+    /// Multiplies counts of each kind, returns `None` on overflow.
+    pub fn checked_mul(self, factor: u32) -> Option<Self> {
+        self.zip_with(&self, |count, _| count.checked_mul(factor))
+            .ok()
+    }
+
+    /// Adds counts of each kind, saturating at `u32::MAX`.
+    ///
+    /// Fails only if result would have more than `MAX_VENDOR_DESCRIPTOR_KINDS` vendor kinds.
+    pub fn saturating_add(self, other: Self) -> Result<Self, DescriptorCountError> {
+        self.zip_with(&other, |lhs, rhs| Some(lhs.saturating_add(rhs)))
+    }
+
+    /// Subtracts counts of each kind, saturating at zero.
+    pub fn saturating_sub(self, other: Self) -> Self {
+        self.zip_with(&other, |lhs, rhs| Some(lhs.saturating_sub(rhs)))
+            .expect("Result has no vendor kinds absent in `self`")
+    }
+
+    /// Multiplies counts of each kind, saturating at `u32::MAX`.
+    pub fn saturating_mul(self, factor: u32) -> Self {
+        self.zip_with(&self, |count, _| Some(count.saturating_mul(factor)))
+            .expect("Result has the same vendor kinds as `self`")
+    }
+
+    /// Returns maximum count of each kind.
+    ///
+    /// Fails only if result would have more than `MAX_VENDOR_DESCRIPTOR_KINDS` vendor kinds.
+    pub fn component_max(self, other: Self) -> Result<Self, DescriptorCountError> {
+        self.zip_with(&other, |lhs, rhs| Some(lhs.max(rhs)))
+    }
+
+    /// Returns `true` if count of each kind does not exceed count of the same kind in `other`.
+    pub fn fits_within(&self, other: &Self) -> bool {
+        self.iter().all(|(kind, count)| count <= other.get(kind))
+    }
+
+    /// Combines counts of each kind present in either operand.
+    /// Fails with `Overflow` if `f` returns `None`.
+    fn zip_with(
+        &self,
+        other: &Self,
+        f: impl Fn(u32, u32) -> Option<u32>,
+    ) -> Result<Self, DescriptorCountError> {
+        let f = |lhs, rhs| f(lhs, rhs).ok_or(DescriptorCountError::Overflow);
+
+        let mut result = DescriptorTotalCount::new();
+        for (index, count) in result.known.iter_mut().enumerate() {
+            *count = f(self.known[index], other.known[index])?;
+        }
+
+        let vendor = self.vendor[..self.vendor_len()]
+            .iter()
+            .chain(&other.vendor[..other.vendor_len()]);
+
+        for &(raw, _) in vendor {
+            let kind = DescriptorKind::Vendor(raw);
+            result.try_set(kind, f(self.get(kind), other.get(kind))?)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Sets count of specified kind.
+    /// Fails if there is no room for another vendor kind.
+    fn try_set(&mut self, kind: DescriptorKind, count: u32) -> Result<(), DescriptorCountError> {
+        let raw = match kind {
+            DescriptorKind::Vendor(raw) => raw,
+            _ => {
+                self.known[kind.index().expect("Known kind has index")] = count;
+                return Ok(());
+            }
+        };
+
+        let len = self.vendor_len();
+        match self.vendor[..len].binary_search_by_key(&raw, |&(raw, _)| raw) {
+            Ok(slot) if count > 0 => self.vendor[slot].1 = count,
+            Ok(slot) => {
+                self.vendor[slot..len].rotate_left(1);
+                self.vendor[len - 1] = (0, 0);
+            }
+            Err(_) if count == 0 => {}
+            Err(_) if len == MAX_VENDOR_DESCRIPTOR_KINDS => {
+                return Err(DescriptorCountError::TooManyVendorKinds)
+            }
+            Err(slot) => {
+                self.vendor[slot..=len].rotate_right(1);
+                self.vendor[slot] = (raw, count);
+            }
+        }
+        Ok(())
+    }
+
+    fn vendor_len(&self) -> usize {
+        self.vendor
+            .iter()
+            .take_while(|&&(_, count)| count > 0)
+            .count()
+    }
+
+    fn vendor_slot(&self, kind: DescriptorKind) -> Option<usize> {
+        match kind {
+            DescriptorKind::Vendor(raw) => self.vendor[..self.vendor_len()]
+                .iter()
+                .position(|&(vendor, _)| vendor == raw),
+            _ => None,
+        }
+    }
+}
+
+impl Add for DescriptorTotalCount {
+    type Output = Self;
+
+    /// # Panics
+    ///
+    /// Panics on overflow or if result would have too many vendor kinds.
+    fn add(self, rhs: Self) -> Self {
+        match self.checked_add(rhs) {
+            Ok(result) => result,
+            Err(err) => panic!("Failed to add descriptor counts: {}", err),
+        }
+    }
+}
+
+impl AddAssign for DescriptorTotalCount {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for DescriptorTotalCount {
+    type Output = Self;
+
+    /// # Panics
+    ///
+    /// Panics on underflow.
+    fn sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs)
+            .expect("Overflow when subtracting descriptor counts")
+    }
+}
+
+impl SubAssign for DescriptorTotalCount {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Debug for DescriptorTotalCount {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<(DescriptorKind, u32)> for DescriptorTotalCount {
+    /// Counts of repeated kinds are summed.
+    ///
+    /// # Panics
+    ///
+    /// Panics on overflow or if there are too many vendor kinds.
+    /// Use `try_from_counts` for untrusted counts.
+    fn from_iter<I: IntoIterator<Item = (DescriptorKind, u32)>>(iter: I) -> Self {
+        match DescriptorTotalCount::try_from_counts(iter) {
+            Ok(result) => result,
+            Err(err) => panic!("Failed to count descriptors: {}", err),
+        }
+    }
+}
+
+/// Serialized as sequence of `(kind, count)` pairs with non-zero counts.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DescriptorTotalCount {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DescriptorTotalCount {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = DescriptorTotalCount;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt.write_str("sequence of descriptor kind and count pairs")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<DescriptorTotalCount, A::Error> {
+                let mut result = DescriptorTotalCount::new();
+                while let Some((kind, count)) = seq.next_element::<(DescriptorKind, u32)>()? {
+                    result
+                        .add_count(kind, count)
+                        .map_err(serde::de::Error::custom)?;
+                }
+                Ok(result)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// Device limits that affect descriptor set allocation.
+///
+/// Per-set limits match corresponding limits in Vulkan
+/// and count descriptors of a set layout the same way.
+/// Limits for pools are not reported by Vulkan,
+/// they allow to respect practical limits of a driver.
+///
+/// Limits are unbounded by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DeviceLimits {
+    /// Maximum number of samplers and combined image samplers in a set.
+    pub max_descriptor_set_samplers: u32,
+
+    /// Maximum number of uniform buffers, including dynamic ones, in a set.
+    pub max_descriptor_set_uniform_buffers: u32,
+
+    /// Maximum number of dynamic uniform buffers in a set.
+    pub max_descriptor_set_uniform_buffers_dynamic: u32,
+
+    /// Maximum number of storage buffers, including dynamic ones, in a set.
+    pub max_descriptor_set_storage_buffers: u32,
+
+    /// Maximum number of dynamic storage buffers in a set.
+    pub max_descriptor_set_storage_buffers_dynamic: u32,
+
+    /// Maximum number of sampled images, combined image samplers
+    /// and uniform texel buffers in a set.
+    pub max_descriptor_set_sampled_images: u32,
+
+    /// Maximum number of storage images and storage texel buffers in a set.
+    pub max_descriptor_set_storage_images: u32,
+
+    /// Maximum number of input attachments in a set.
+    pub max_descriptor_set_input_attachments: u32,
+
+    /// Maximum number of acceleration structures in a set.
+    pub max_descriptor_set_acceleration_structures: u32,
+
+    /// Maximum number of inline uniform block bindings in a set.
+    pub max_descriptor_set_inline_uniform_blocks: u32,
+
+    /// Maximum number of samplers and combined image samplers in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_samplers: u32,
+
+    /// Maximum number of uniform buffers, including dynamic ones, in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_uniform_buffers: u32,
+
+    /// Maximum number of dynamic uniform buffers in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_uniform_buffers_dynamic: u32,
+
+    /// Maximum number of storage buffers, including dynamic ones, in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_storage_buffers: u32,
+
+    /// Maximum number of dynamic storage buffers in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_storage_buffers_dynamic: u32,
+
+    /// Maximum number of sampled images, combined image samplers
+    /// and uniform texel buffers in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_sampled_images: u32,
+
+    /// Maximum number of storage images and storage texel buffers in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_storage_images: u32,
+
+    /// Maximum number of input attachments in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_input_attachments: u32,
+
+    /// Maximum number of acceleration structures in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_acceleration_structures: u32,
+
+    /// Maximum number of inline uniform block bindings in an update-after-bind set.
+    pub max_descriptor_set_update_after_bind_inline_uniform_blocks: u32,
+
+    /// Maximum number of sets in one pool.
+    pub max_pool_sets: u32,
+
+    /// Maximum number of descriptors of each type in one pool.
+    pub max_pool_descriptors: u32,
+}
+
+impl Default for DeviceLimits {
+    fn default() -> Self {
+        DeviceLimits::UNBOUNDED
+    }
+}
+
+impl DeviceLimits {
+    /// Limits that never reject layouts nor clamp pools.
+    pub const UNBOUNDED: Self = DeviceLimits {
+        max_descriptor_set_samplers: u32::MAX,
+        max_descriptor_set_uniform_buffers: u32::MAX,
+        max_descriptor_set_uniform_buffers_dynamic: u32::MAX,
+        max_descriptor_set_storage_buffers: u32::MAX,
+        max_descriptor_set_storage_buffers_dynamic: u32::MAX,
+        max_descriptor_set_sampled_images: u32::MAX,
+        max_descriptor_set_storage_images: u32::MAX,
+        max_descriptor_set_input_attachments: u32::MAX,
+        max_descriptor_set_acceleration_structures: u32::MAX,
+        max_descriptor_set_inline_uniform_blocks: u32::MAX,
+        max_descriptor_set_update_after_bind_samplers: u32::MAX,
+        max_descriptor_set_update_after_bind_uniform_buffers: u32::MAX,
+        max_descriptor_set_update_after_bind_uniform_buffers_dynamic: u32::MAX,
+        max_descriptor_set_update_after_bind_storage_buffers: u32::MAX,
+        max_descriptor_set_update_after_bind_storage_buffers_dynamic: u32::MAX,
+        max_descriptor_set_update_after_bind_sampled_images: u32::MAX,
+        max_descriptor_set_update_after_bind_storage_images: u32::MAX,
+        max_descriptor_set_update_after_bind_input_attachments: u32::MAX,
+        max_descriptor_set_update_after_bind_acceleration_structures: u32::MAX,
+        max_descriptor_set_update_after_bind_inline_uniform_blocks: u32::MAX,
+        max_pool_sets: u32::MAX,
+        max_pool_descriptors: u32::MAX,
+    };
+
+    /// Returns Vulkan name of the first per-set limit exceeded by a set
+    /// with specified descriptor counts, or `None` if set fits into all of them.
+    ///
+    /// Sets with update-after-bind layouts are checked against
+    /// `maxDescriptorSetUpdateAfterBind*` limits instead.
+    pub fn exceeded_set_limit(
+        &self,
+        count: &DescriptorTotalCount,
+        update_after_bind: bool,
+    ) -> Option<&'static str> {
+        let sum = |kinds: &[DescriptorKind]| -> u64 {
+            kinds.iter().map(|&kind| u64::from(count.get(kind))).sum()
+        };
+
+        // Descriptor count, limit and its name for regular and update-after-bind sets.
+        let checks = [
+            (
+                sum(&[
+                    DescriptorKind::Sampler,
+                    DescriptorKind::CombinedImageSampler,
+                ]),
+                (self.max_descriptor_set_samplers, "maxDescriptorSetSamplers"),
+                (
+                    self.max_descriptor_set_update_after_bind_samplers,
+                    "maxDescriptorSetUpdateAfterBindSamplers",
+                ),
+            ),
+            (
+                sum(&[
+                    DescriptorKind::UniformBuffer,
+                    DescriptorKind::UniformBufferDynamic,
+                ]),
+                (
+                    self.max_descriptor_set_uniform_buffers,
+                    "maxDescriptorSetUniformBuffers",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_uniform_buffers,
+                    "maxDescriptorSetUpdateAfterBindUniformBuffers",
+                ),
+            ),
+            (
+                sum(&[DescriptorKind::UniformBufferDynamic]),
+                (
+                    self.max_descriptor_set_uniform_buffers_dynamic,
+                    "maxDescriptorSetUniformBuffersDynamic",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_uniform_buffers_dynamic,
+                    "maxDescriptorSetUpdateAfterBindUniformBuffersDynamic",
+                ),
+            ),
+            (
+                sum(&[
+                    DescriptorKind::StorageBuffer,
+                    DescriptorKind::StorageBufferDynamic,
+                ]),
+                (
+                    self.max_descriptor_set_storage_buffers,
+                    "maxDescriptorSetStorageBuffers",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_storage_buffers,
+                    "maxDescriptorSetUpdateAfterBindStorageBuffers",
+                ),
+            ),
+            (
+                sum(&[DescriptorKind::StorageBufferDynamic]),
+                (
+                    self.max_descriptor_set_storage_buffers_dynamic,
+                    "maxDescriptorSetStorageBuffersDynamic",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_storage_buffers_dynamic,
+                    "maxDescriptorSetUpdateAfterBindStorageBuffersDynamic",
+                ),
+            ),
+            (
+                sum(&[
+                    DescriptorKind::CombinedImageSampler,
+                    DescriptorKind::SampledImage,
+                    DescriptorKind::UniformTexelBuffer,
+                ]),
+                (
+                    self.max_descriptor_set_sampled_images,
+                    "maxDescriptorSetSampledImages",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_sampled_images,
+                    "maxDescriptorSetUpdateAfterBindSampledImages",
+                ),
+            ),
+            (
+                sum(&[
+                    DescriptorKind::StorageImage,
+                    DescriptorKind::StorageTexelBuffer,
+                ]),
+                (
+                    self.max_descriptor_set_storage_images,
+                    "maxDescriptorSetStorageImages",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_storage_images,
+                    "maxDescriptorSetUpdateAfterBindStorageImages",
+                ),
+            ),
+            (
+                sum(&[DescriptorKind::InputAttachment]),
+                (
+                    self.max_descriptor_set_input_attachments,
+                    "maxDescriptorSetInputAttachments",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_input_attachments,
+                    "maxDescriptorSetUpdateAfterBindInputAttachments",
+                ),
+            ),
+            (
+                sum(&[DescriptorKind::AccelerationStructure]),
+                (
+                    self.max_descriptor_set_acceleration_structures,
+                    "maxDescriptorSetAccelerationStructures",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_acceleration_structures,
+                    "maxDescriptorSetUpdateAfterBindAccelerationStructures",
+                ),
+            ),
+            (
+                sum(&[DescriptorKind::InlineUniformBlockBindings]),
+                (
+                    self.max_descriptor_set_inline_uniform_blocks,
+                    "maxDescriptorSetInlineUniformBlocks",
+                ),
+                (
+                    self.max_descriptor_set_update_after_bind_inline_uniform_blocks,
+                    "maxDescriptorSetUpdateAfterBindInlineUniformBlocks",
+                ),
+            ),
+        ];
+
+        checks
+            .iter()
+            .map(|&(value, regular, update_after_bind_limit)| {
+                let (limit, name) = if update_after_bind {
+                    update_after_bind_limit
+                } else {
+                    regular
+                };
+                (value, limit, name)
+            })
+            .find(|&(value, limit, _)| value > u64::from(limit))
+            .map(|(_, _, name)| name)
+    }
+
+    /// Returns `true` if a pool within `max_pool_descriptors` limit
+    /// can fit a set with specified descriptor counts.
+    pub fn pool_fits_set(&self, count: &DescriptorTotalCount) -> bool {
+        count
+            .iter()
+            .all(|(_, count)| count <= self.max_pool_descriptors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use {super::*, alloc::vec::Vec};
+
+    fn vendor_kinds(count: &DescriptorTotalCount) -> Vec<(i32, u32)> {
+        count
+            .iter()
+            .filter_map(|(kind, count)| match kind {
+                DescriptorKind::Vendor(raw) => Some((raw, count)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn vendor_kinds_are_kept_sorted() {
+        let mut count = DescriptorTotalCount::new();
+        count.set(DescriptorKind::Vendor(5), 1);
+        count.set(DescriptorKind::Vendor(-3), 2);
+        count.set(DescriptorKind::Vendor(9), 3);
+        count.set(DescriptorKind::Vendor(0), 4);
+        assert_eq!(vendor_kinds(&count), [(-3, 2), (0, 4), (5, 1), (9, 3)]);
+
+        // Updating existing kind does not move it.
+        count.set(DescriptorKind::Vendor(5), 10);
+        assert_eq!(vendor_kinds(&count), [(-3, 2), (0, 4), (5, 10), (9, 3)]);
+        assert_eq!(count.get(DescriptorKind::Vendor(5)), 10);
+        assert_eq!(count.get(DescriptorKind::Vendor(6)), 0);
+    }
+
+    #[test]
+    fn removed_vendor_kind_frees_its_slot() {
+        let mut count = DescriptorTotalCount::new();
+        for raw in 1..=4 {
+            count.set(DescriptorKind::Vendor(raw), raw as u32);
+        }
+
+        // Removing from the middle shifts the rest towards the front.
+        count.set(DescriptorKind::Vendor(2), 0);
+        assert_eq!(vendor_kinds(&count), [(1, 1), (3, 3), (4, 4)]);
+
+        // Freed slot takes a new kind at its sorted position.
+        count.set(DescriptorKind::Vendor(0), 7);
+        assert_eq!(vendor_kinds(&count), [(0, 7), (1, 1), (3, 3), (4, 4)]);
+
+        // Removing all kinds leaves the count equal to a new one.
+        for raw in [4, 0, 3, 1] {
+            count.set(DescriptorKind::Vendor(raw), 0);
+        }
+        assert!(count.is_empty());
+        assert_eq!(count, DescriptorTotalCount::new());
+
+        // Zero count of absent kind takes no slot.
+        count.set(DescriptorKind::Vendor(8), 0);
+        assert_eq!(count, DescriptorTotalCount::new());
+    }
+
+    #[test]
+    fn vendor_slots_overflow_is_reported() {
+        let mut count = DescriptorTotalCount::new();
+        for raw in 0..MAX_VENDOR_DESCRIPTOR_KINDS as i32 {
+            count.add_count(DescriptorKind::Vendor(raw), 1).unwrap();
+        }
+        let full = count;
+
+        assert_eq!(
+            count.add_count(DescriptorKind::Vendor(100), 1),
+            Err(DescriptorCountError::TooManyVendorKinds)
+        );
+        assert_eq!(count, full);
+
+        // Existing kinds and known kinds can still be updated when all slots are taken.
+        count.add_count(DescriptorKind::Vendor(0), 1).unwrap();
+        count.add_count(DescriptorKind::Sampler, 1).unwrap();
+        assert_eq!(count.get(DescriptorKind::Vendor(0)), 2);
+        assert_eq!(
+            DescriptorTotalCount::try_from_counts(
+                (0..=MAX_VENDOR_DESCRIPTOR_KINDS as i32)
+                    .map(|raw| (DescriptorKind::Vendor(raw), 1))
+            ),
+            Err(DescriptorCountError::TooManyVendorKinds)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many vendor descriptor kinds")]
+    fn set_panics_when_vendor_slots_are_full() {
+        let mut count = DescriptorTotalCount::new();
+        for raw in 0..=MAX_VENDOR_DESCRIPTOR_KINDS as i32 {
+            count.set(DescriptorKind::Vendor(raw), 1);
+        }
+    }
+
+    #[test]
+    fn zip_with_merges_different_vendor_kinds() {
+        let lhs = count(DescriptorKind::Vendor(1), 1).with(DescriptorKind::Vendor(3), 3);
+        let rhs = count(DescriptorKind::Vendor(2), 2).with(DescriptorKind::Vendor(3), 4);
+
+        let sum = lhs.checked_add(rhs).unwrap();
+        assert_eq!(vendor_kinds(&sum), [(1, 1), (2, 2), (3, 7)]);
+        assert_eq!(sum, rhs.checked_add(lhs).unwrap());
+
+        let max = lhs.component_max(rhs).unwrap();
+        assert_eq!(vendor_kinds(&max), [(1, 1), (2, 2), (3, 4)]);
+
+        // Kinds that drop to zero are removed from result.
+        let diff = lhs.saturating_sub(rhs);
+        assert_eq!(vendor_kinds(&diff), [(1, 1)]);
+        assert_eq!(sum.checked_sub(rhs), Some(lhs));
+        assert_eq!(lhs.checked_sub(rhs), None);
+
+        // Union of vendor kinds must fit into slots.
+        let other = count(DescriptorKind::Vendor(4), 1).with(DescriptorKind::Vendor(5), 1);
+        assert_eq!(
+            sum.checked_add(other),
+            Err(DescriptorCountError::TooManyVendorKinds)
+        );
+        assert_eq!(
+            sum.saturating_add(other),
+            Err(DescriptorCountError::TooManyVendorKinds)
+        );
+        assert_eq!(
+            sum.component_max(other),
+            Err(DescriptorCountError::TooManyVendorKinds)
+        );
+    }
+
+    fn count(kind: DescriptorKind, count: u32) -> DescriptorTotalCount {
+        DescriptorTotalCount::new().with(kind, count)
+    }
+
+    #[test]
+    fn checked_add_reports_overflow_at_boundary() {
+        let max = count(DescriptorKind::Sampler, u32::MAX - 1);
+        let one = count(DescriptorKind::Sampler, 1);
+
+        let sum = max.checked_add(one).unwrap();
+        assert_eq!(sum.sampler(), u32::MAX);
+        assert_eq!(sum.checked_add(one), Err(DescriptorCountError::Overflow));
+
+        // Overflow of a vendor kind is detected the same way.
+        let vendor = count(DescriptorKind::Vendor(7), u32::MAX);
+        assert_eq!(
+            vendor.checked_add(count(DescriptorKind::Vendor(7), 1)),
+            Err(DescriptorCountError::Overflow)
+        );
+        let mixed = vendor.checked_add(one).unwrap();
+        assert_eq!(mixed.get(DescriptorKind::Vendor(7)), u32::MAX);
+        assert_eq!(mixed.checked_total(), None);
+    }
+
+    #[test]
+    fn checked_sub_and_mul_report_overflow() {
+        let two = count(DescriptorKind::UniformBuffer, 2);
+        let three = count(DescriptorKind::UniformBuffer, 3);
+
+        assert_eq!(
+            three.checked_sub(two),
+            Some(count(DescriptorKind::UniformBuffer, 1))
+        );
+        assert_eq!(two.checked_sub(two), Some(DescriptorTotalCount::new()));
+        assert_eq!(two.checked_sub(three), None);
+
+        let half = count(DescriptorKind::UniformBuffer, u32::MAX / 2);
+        assert_eq!(half.checked_mul(2).unwrap().uniform_buffer(), u32::MAX - 1);
+        assert_eq!(half.checked_mul(3), None);
+        assert_eq!(half.checked_mul(0), Some(DescriptorTotalCount::new()));
+    }
+
+    #[test]
+    fn saturating_ops_clamp_each_kind() {
+        let lhs =
+            count(DescriptorKind::Sampler, u32::MAX - 1).with(DescriptorKind::StorageImage, 2);
+        let rhs = count(DescriptorKind::Sampler, 5).with(DescriptorKind::StorageImage, 3);
+
+        let sum = lhs.saturating_add(rhs).unwrap();
+        assert_eq!(sum.sampler(), u32::MAX);
+        assert_eq!(sum.storage_image(), 5);
+
+        let diff = lhs.saturating_sub(rhs);
+        assert_eq!(diff.sampler(), u32::MAX - 6);
+        assert_eq!(diff.storage_image(), 0);
+        assert_eq!(
+            rhs.saturating_sub(lhs),
+            count(DescriptorKind::StorageImage, 1)
+        );
+
+        let product = lhs.saturating_mul(2);
+        assert_eq!(product.sampler(), u32::MAX);
+        assert_eq!(product.storage_image(), 4);
+        assert_eq!(lhs.saturating_mul(0), DescriptorTotalCount::new());
+    }
+
+    #[test]
+    fn fits_within_compares_each_kind() {
+        let limit = count(DescriptorKind::Sampler, 4).with(DescriptorKind::Vendor(1), 2);
+
+        assert!(DescriptorTotalCount::new().fits_within(&limit));
+        assert!(limit.fits_within(&limit));
+        assert!(count(DescriptorKind::Sampler, 4).fits_within(&limit));
+        assert!(!count(DescriptorKind::Sampler, 5).fits_within(&limit));
+        assert!(!count(DescriptorKind::Vendor(1), 3).fits_within(&limit));
+
+        // Kinds absent in `other` fit only with zero count.
+        assert!(!count(DescriptorKind::StorageBuffer, 1).fits_within(&limit));
+        assert!(!count(DescriptorKind::Vendor(2), 1).fits_within(&limit));
+        assert!(!limit.fits_within(&DescriptorTotalCount::new()));
     }
 }